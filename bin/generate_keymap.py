@@ -1,73 +1,169 @@
-table = [
-    ('a', 0x1E, 0x9E),
-    ('b', 0x30, 0xB0),
-    ('c', 0x2E, 0xAE),
-    ('d', 0x20, 0xA0),
-    ('e', 0x12, 0x92),
-    ('f', 0x21, 0xA1),
-    ('g', 0x22, 0xA2),
-    ('h', 0x23, 0xA3),
-    ('i', 0x17, 0x97),
-    ('j', 0x24, 0xA4),
-    ('k', 0x25, 0xA5),
-    ('l', 0x26, 0xA6),
-    ('m', 0x32, 0xB2),
-    ('n', 0x31, 0xB1),
-    ('o', 0x18, 0x98),
-    ('p', 0x19, 0x99),
-    ('q', 0x10, 0x90),
-    ('r', 0x13, 0x93),
-    ('s', 0x1F, 0x9F),
-    ('t', 0x14, 0x94),
-    ('u', 0x16, 0x96),
-    ('v', 0x2F, 0xAF),
-    ('w', 0x11, 0x91),
-    ('x', 0x2D, 0xAD),
-    ('y', 0x15, 0x95),
-    ('z', 0x2C, 0xAC),
-    ('0', 0x0B, 0x8B),
-    ('1', 0x2, 0x82),
-    ('2', 0x3, 0x83),
-    ('3', 0x4, 0x84),
-    ('4', 0x5, 0x85),
-    ('5', 0x6, 0x86),
-    ('6', 0x7, 0x87),
-    ('7', 0x8, 0x88),
-    ('8', 0x9, 0x89),
-    ('9', 0x0A, 0x8A),
-    ('`', 0x29, 0x89),
-    ('-', 0x0C, 0x8C),
-    ('=', 0x0D, 0x8D),
-    ('\\', 0x2B, 0xAB),
-    (' ', 0x39, 0xB9),
-    ('\n', 0x1C, 0x9C),
-    ('[', 0x1A, 0x9A),
-    ('*', 0x37, 0xB7),
-    ('-', 0x4A, 0xCA),
-    ('+', 0x4E, 0xCE),
-    ('.', 0x53, 0xD3),
-    ('0', 0x52, 0xD2),
-    ('1', 0x4F, 0xCF),
-    ('2', 0x50, 0xD0),
-    ('3', 0x51, 0xD1),
-    ('4', 0x4B, 0xCB),
-    ('5', 0x4C, 0xCC),
-    ('6', 0x4D, 0xCD),
-    ('7', 0x47, 0xC7),
-    ('8', 0x48, 0xC8),
-    ('9', 0x49, 0xC9),
-    (']', 0x1B, 0x9B),
-    (';', 0x27, 0xA7),
-    ('\'', 0x28, 0xA8),
-    (',', 0x33, 0xB3),
-    ('.', 0x34, 0xB4),
-    ('/', 0x35, 0xB5),
-]
-
-keymap = [0] * 256
-
-for (ch, pressed, released) in table:
-    keymap[pressed] = ord(ch)
-    keymap[released] = ord(ch) | 0x80
-
-print(keymap)
+#!/usr/bin/env python3
+"""Generates src/dev/kbd/keymaps.rs from keyboard layout descriptions in misc/keymaps.
+
+Layout description is a UTF-8 text file. Empty lines and lines starting with `#` are ignored,
+other lines are one of:
+
+    name <name>                     name used to select the layout, e.g. `de`
+    description <text>              human readable name
+    <Key> <normal> [<shift> [<altgr> [<shift+altgr>]]]
+    dead <accent> <pairs>...
+
+`<Key>` is a variant of `dev::kbd::Key`. Each level is either a single character, `U+XXXX`,
+`-` for no character, or `dead:<accent>` for a dead key. Caps Lock affects keys typing
+a letter on normal level and its upper case with Shift. `dead` lines list what a dead key
+composes with: every pair is a base character followed by the composed one.
+
+Usage: bin/generate_keymap.py > src/dev/kbd/keymaps.rs
+"""
+
+import glob
+import os
+import re
+import sys
+
+ROOT_DIR = os.path.join(os.path.dirname(os.path.abspath(__file__)), os.pardir)
+KEYMAPS_DIR = os.path.join(ROOT_DIR, 'misc', 'keymaps')
+KEYS_RS = os.path.join(ROOT_DIR, 'src', 'dev', 'kbd', 'keys.rs')
+
+LEVELS = 4
+
+
+def known_keys():
+    with open(KEYS_RS, encoding='utf-8') as f:
+        source = f.read()
+    body = re.search(r'pub enum Key \{(.*?)\n\}', source, re.S).group(1)
+    return set(re.findall(r'^    (\w+),$', body, re.M))
+
+
+def parse_char(token, path, line_no):
+    if token.startswith('U+'):
+        return chr(int(token[2:], 16))
+    if len(token) != 1:
+        sys.exit('%s:%d: invalid character %r' % (path, line_no, token))
+    return token
+
+
+def parse_action(token, path, line_no):
+    if token == '-':
+        return None
+    if token.startswith('dead:'):
+        return ('dead', parse_char(token[5:], path, line_no))
+    return ('char', parse_char(token, path, line_no))
+
+
+def parse_keymap(path, keys):
+    keymap = {'name': None, 'description': None, 'mappings': [], 'dead_keys': []}
+
+    with open(path, encoding='utf-8') as f:
+        for line_no, line in enumerate(f, 1):
+            line = line.strip()
+            if not line or line.startswith('#'):
+                continue
+
+            words = line.split()
+            if words[0] in ('name', 'description'):
+                keymap[words[0]] = line[len(words[0]):].strip()
+            elif words[0] == 'dead':
+                accent = parse_char(words[1], path, line_no)
+                pairs = []
+                for pair in words[2:]:
+                    if len(pair) != 2:
+                        sys.exit('%s:%d: invalid pair %r' % (path, line_no, pair))
+                    pairs.append((pair[0], pair[1]))
+                keymap['dead_keys'].append((accent, pairs))
+            elif words[0] in keys:
+                if len(words) > LEVELS + 1:
+                    sys.exit('%s:%d: too many levels' % (path, line_no))
+                levels = [parse_action(word, path, line_no) for word in words[1:]]
+                levels += [None] * (LEVELS - len(levels))
+                keymap['mappings'].append((words[0], levels))
+            else:
+                sys.exit('%s:%d: unknown key %r' % (path, line_no, words[0]))
+
+    if keymap['name'] is None or keymap['description'] is None:
+        sys.exit('%s: name and description are required' % path)
+    return keymap
+
+
+def rust_char(char):
+    if char in ('\\', "'"):
+        return "'\\%s'" % char
+    if ' ' <= char <= '~':
+        return "'%s'" % char
+    return "'\\u{%x}'" % ord(char)
+
+
+def rust_action(action):
+    if action is None:
+        return 'Action::None'
+    kind, char = action
+    if kind == 'dead':
+        return 'Action::Dead(%s)' % rust_char(char)
+    return 'Action::Char(%s)' % rust_char(char)
+
+
+def caps_lock(levels):
+    normal, shift = levels[0], levels[1]
+    if normal is None or shift is None or normal[0] != 'char' or shift[0] != 'char':
+        return False
+    return normal[1].isalpha() and normal[1].upper() == shift[1]
+
+
+def emit(keymaps):
+    out = []
+    out.append('//! Compiled-in keyboard layouts')
+    out.append('//!')
+    out.append('//! Generated by `bin/generate_keymap.py` from `misc/keymaps`, do not edit.')
+    out.append('')
+    out.append('use super::keymap::{Action, DeadKey, Keymap, Mapping};')
+    out.append('use super::keys::Key;')
+    out.append('')
+    out.append('/// All compiled-in layouts, sorted by name')
+    out.append('pub static KEYMAPS: [&Keymap; %d] = [%s];' % (
+        len(keymaps), ', '.join('&%s' % k['name'].upper() for k in keymaps)))
+
+    for keymap in keymaps:
+        out.append('')
+        out.append('pub static %s: Keymap = Keymap {' % keymap['name'].upper())
+        out.append('    name: "%s",' % keymap['name'])
+        out.append('    description: "%s",' % keymap['description'])
+        out.append('    mappings: &[')
+        for key, levels in keymap['mappings']:
+            out.append('        Mapping {')
+            out.append('            key: Key::%s,' % key)
+            out.append('            caps_lock: %s,' % ('true' if caps_lock(levels) else 'false'))
+            out.append('            levels: [')
+            for action in levels:
+                out.append('                %s,' % rust_action(action))
+            out.append('            ],')
+            out.append('        },')
+        out.append('    ],')
+        if keymap['dead_keys']:
+            out.append('    dead_keys: &[')
+            for accent, pairs in keymap['dead_keys']:
+                out.append('        DeadKey {')
+                out.append('            accent: %s,' % rust_char(accent))
+                out.append('            combinations: &[')
+                for base, composed in pairs:
+                    out.append('                (%s, %s),' % (rust_char(base), rust_char(composed)))
+                out.append('            ],')
+                out.append('        },')
+            out.append('    ],')
+        else:
+            out.append('    dead_keys: &[],')
+        out.append('};')
+
+    return '\n'.join(out) + '\n'
+
+
+def main():
+    keys = known_keys()
+    paths = sorted(glob.glob(os.path.join(KEYMAPS_DIR, '*.keymap')))
+    keymaps = [parse_keymap(path, keys) for path in paths]
+    keymaps.sort(key=lambda k: k['name'])
+    sys.stdout.write(emit(keymaps))
+
+
+if __name__ == '__main__':
+    main()
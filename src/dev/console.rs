@@ -0,0 +1,201 @@
+//! Console input
+//!
+//! Kernel console can be driven from any keyboard and any serial terminal at the same time:
+//! [`ConsoleInput`] reads keys from whichever of them delivers first. Navigation keys are
+//! reported as [`ConsoleKey`]s, whether they are pressed on a keyboard or sent by a terminal
+//! as VT100 escape sequence, e.g. `ESC [ A` for the up arrow.
+//!
+//! [`ConsoleInput`]: ./struct.ConsoleInput.html
+//! [`ConsoleKey`]: ./enum.ConsoleKey.html
+
+use alloc::VecDeque;
+
+use dev::input::{self, InputEvent, InputEventKind, InputReader};
+use dev::kbd::{Key, KeyEvent};
+use thread::Interrupted;
+
+/// Number of milliseconds a terminal has to send the rest of escape sequence after `ESC`,
+/// lone `ESC` is the Escape key
+const ESCAPE_TIMEOUT: u64 = 50;
+
+/// Key read from console input
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ConsoleKey {
+    /// Byte of typed text, carriage return sent by terminals on Enter is reported as `\n`.
+    /// Characters outside of ASCII come as consecutive bytes of their UTF-8 encoding.
+    Byte(u8),
+    Up,
+    Down,
+    Right,
+    Left,
+    Home,
+    End,
+    Insert,
+    Delete,
+    PageUp,
+    PageDown,
+}
+
+/// Text input of kernel console
+pub struct ConsoleInput {
+    reader: InputReader,
+    /// Keys which arrived while an escape sequence was being read
+    pending: VecDeque<ConsoleKey>,
+}
+
+impl ConsoleInput {
+    /// Creates console input reading from all keyboards and serial terminals installed
+    /// at the moment.
+    pub fn new() -> ConsoleInput {
+        ConsoleInput {
+            reader: input::console(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Blocks until a key arrives from any source, and returns it.
+    pub fn read_key(&mut self) -> ConsoleKey {
+        loop {
+            if let Some(key) = self.pending.pop_front() {
+                return key;
+            }
+
+            let event = self.reader.wait();
+            self.translate(&event);
+        }
+    }
+
+    /// Blocks until a key arrives from any source and returns it, or until current thread
+    /// gets [interrupted].
+    ///
+    /// [interrupted]: ../../thread/fn.interrupt.html
+    pub fn read_key_interruptible(&mut self) -> Result<ConsoleKey, Interrupted> {
+        loop {
+            if let Some(key) = self.pending.pop_front() {
+                return Ok(key);
+            }
+
+            let event = self.reader.wait_interruptible()?;
+            self.translate(&event);
+        }
+    }
+
+    /// Drops keys which have arrived so far and have not been read, e.g. those typed into
+    /// a program which has exited meanwhile.
+    pub fn discard(&mut self) {
+        self.pending.clear();
+        while self.reader.try_read().is_some() {}
+    }
+
+    /// Queues keys produced by event. Events which do not produce keys, and escape sequences
+    /// which are not recognized, are skipped.
+    fn translate(&mut self, event: &InputEvent) {
+        match event.kind {
+            InputEventKind::Key(ref key) => push_key(&mut self.pending, key),
+            InputEventKind::Byte(b'\x1b') => {
+                // Keys which have arrived while the sequence was being read come after it
+                if let Some(key) = self.read_escape_sequence() {
+                    self.pending.push_front(key);
+                }
+            }
+            InputEventKind::Byte(byte) => self.pending.push_back(translate_byte(byte)),
+            InputEventKind::Mouse(_) => {}
+        }
+    }
+
+    /// Reads the rest of escape sequence a terminal has started with `ESC`, and returns
+    /// the key it stands for. Keyboard keys arriving in the meantime are left pending.
+    fn read_escape_sequence(&mut self) -> Option<ConsoleKey> {
+        match self.next_terminal_byte() {
+            Some(b'[') | Some(b'O') => {}
+            Some(byte) => {
+                self.pending.push_back(translate_byte(byte));
+                return Some(ConsoleKey::Byte(b'\x1b'));
+            }
+            None => return Some(ConsoleKey::Byte(b'\x1b')),
+        }
+
+        // Only the first parameter, like `3` in `ESC [ 3 ~`, is interesting, the rest are
+        // modifiers
+        let mut parameter: Option<u32> = None;
+        let mut first = true;
+        loop {
+            match self.next_terminal_byte()? {
+                digit @ b'0'...b'9' if first => {
+                    let value = parameter.unwrap_or(0).saturating_mul(10);
+                    parameter = Some(value.saturating_add((digit - b'0') as u32));
+                }
+                b'0'...b'9' => {}
+                b';' => first = false,
+                last => return escape_sequence_key(parameter, last),
+            }
+        }
+    }
+
+    /// Waits for the next byte from a terminal, at most `ESCAPE_TIMEOUT` milliseconds.
+    fn next_terminal_byte(&mut self) -> Option<u8> {
+        loop {
+            let event = self.reader.wait_timeout(ESCAPE_TIMEOUT)?;
+            match event.kind {
+                InputEventKind::Byte(byte) => return Some(byte),
+                InputEventKind::Key(ref key) => push_key(&mut self.pending, key),
+                InputEventKind::Mouse(_) => {}
+            }
+        }
+    }
+}
+
+/// Queues keys typed by keyboard key event: navigation key, or UTF-8 bytes of typed character.
+fn push_key(keys: &mut VecDeque<ConsoleKey>, event: &KeyEvent) {
+    if !event.pressed {
+        return;
+    }
+
+    let key = match event.key {
+        Key::Up => ConsoleKey::Up,
+        Key::Down => ConsoleKey::Down,
+        Key::Right => ConsoleKey::Right,
+        Key::Left => ConsoleKey::Left,
+        Key::Home => ConsoleKey::Home,
+        Key::End => ConsoleKey::End,
+        Key::Insert => ConsoleKey::Insert,
+        Key::Delete => ConsoleKey::Delete,
+        Key::PageUp => ConsoleKey::PageUp,
+        Key::PageDown => ConsoleKey::PageDown,
+        _ => {
+            if let Some(char) = event.char {
+                let mut utf8 = [0; 4];
+                keys.extend(char.encode_utf8(&mut utf8).bytes().map(ConsoleKey::Byte));
+            }
+            return;
+        }
+    };
+    keys.push_back(key);
+}
+
+/// Returns key typed by byte received from a terminal, which sends carriage return on Enter.
+fn translate_byte(byte: u8) -> ConsoleKey {
+    match byte {
+        b'\r' => ConsoleKey::Byte(b'\n'),
+        byte => ConsoleKey::Byte(byte),
+    }
+}
+
+/// Returns navigation key VT100 terminal sends escape sequence with given first parameter
+/// and final byte for.
+fn escape_sequence_key(parameter: Option<u32>, last: u8) -> Option<ConsoleKey> {
+    let key = match (parameter, last) {
+        (_, b'A') => ConsoleKey::Up,
+        (_, b'B') => ConsoleKey::Down,
+        (_, b'C') => ConsoleKey::Right,
+        (_, b'D') => ConsoleKey::Left,
+        (_, b'H') | (Some(1), b'~') | (Some(7), b'~') => ConsoleKey::Home,
+        (_, b'F') | (Some(4), b'~') | (Some(8), b'~') => ConsoleKey::End,
+        (Some(2), b'~') => ConsoleKey::Insert,
+        (Some(3), b'~') => ConsoleKey::Delete,
+        (Some(5), b'~') => ConsoleKey::PageUp,
+        (Some(6), b'~') => ConsoleKey::PageDown,
+        _ => return None,
+    };
+    Some(key)
+}
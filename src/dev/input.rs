@@ -0,0 +1,348 @@
+//! Input event subsystem
+//!
+//! Every input device (keyboard, mouse, serial terminal) publishes timestamped
+//! [`InputEvent`]s to its own [`EventQueue`], a ring buffer of fixed capacity. Input devices
+//! expose [`InputDevice`] interface, which gives access to the queue. Reading does
+//! not consume events: each [`InputReader`] keeps its own position in the queues it follows,
+//! so any number of readers see the same events, and none of them can block the device.
+//! Reader which falls behind by more than [`QUEUE_CAPACITY`] events loses the oldest ones,
+//! and counts them.
+//!
+//! Single reader can follow several devices, events are then returned in order of their
+//! timestamps. [`console`] returns reader of all keyboards and serial terminals, which
+//! drives the kernel console.
+//!
+//! ## Examples
+//!
+//! ```
+//! let mut reader = InputReader::new();
+//! reader.follow(kbd.events());
+//! reader.follow(mouse.events());
+//!
+//! loop {
+//!     println!("{:?}", reader.wait());
+//! }
+//! ```
+//!
+//! [`InputEvent`]: ./struct.InputEvent.html
+//! [`EventQueue`]: ./struct.EventQueue.html
+//! [`InputDevice`]: ./trait.InputDevice.html
+//! [`InputReader`]: ./struct.InputReader.html
+//! [`QUEUE_CAPACITY`]: ./constant.QUEUE_CAPACITY.html
+//! [`console`]: ./fn.console.html
+
+use alloc::Vec;
+use alloc::arc::Arc;
+
+use dev;
+use dev::kbd::{Kbd, KeyEvent};
+use dev::mouse::MouseEvent;
+use dev::tty::Tty;
+use executor::{Poll, Stream, Waker};
+use kio::time::Instant;
+use kio::timer::Timer;
+use sync::{IrqSpinLock, WaitQueue, WaiterId};
+use thread::{self, Interrupted};
+
+/// Number of the latest events every queue keeps
+pub const QUEUE_CAPACITY: usize = 256;
+
+/// What has happened on input device
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InputEventKind {
+    /// Key has been pressed or released on keyboard
+    Key(KeyEvent),
+    /// Mouse has moved, or its wheel or button has been used
+    Mouse(MouseEvent),
+    /// Byte has been received by serial terminal
+    Byte(u8),
+}
+
+/// Event published by input device
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct InputEvent {
+    /// When the event has been published
+    pub time: Instant,
+    pub kind: InputEventKind,
+}
+
+/// Interface of devices which publish input events
+pub trait InputDevice: Send + Sync {
+    /// Returns queue the device publishes its events to.
+    fn events(&self) -> &Arc<EventQueue>;
+}
+
+/// Ring buffer of the latest events of single input device
+pub struct EventQueue {
+    ring: IrqSpinLock<Ring>,
+    /// Threads and tasks waiting for new events
+    waiters: WaitQueue,
+}
+
+struct Ring {
+    /// Event with sequence number `n` is stored at `n % QUEUE_CAPACITY`
+    events: Vec<InputEvent>,
+    /// Sequence number of the next event
+    next: u64,
+}
+
+impl Ring {
+    /// Returns sequence number of the oldest event kept.
+    fn oldest(&self) -> u64 {
+        self.next - self.events.len() as u64
+    }
+}
+
+impl EventQueue {
+    pub fn new() -> EventQueue {
+        EventQueue {
+            ring: IrqSpinLock::new(Ring {
+                events: Vec::with_capacity(QUEUE_CAPACITY),
+                next: 0,
+            }),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    /// Stores new event, overwriting the oldest one if the queue is full, and wakes readers.
+    pub fn publish(&self, kind: InputEventKind) {
+        {
+            let mut ring = self.ring.lock();
+            let event = InputEvent {
+                time: Instant::now(),
+                kind,
+            };
+
+            let index = (ring.next % QUEUE_CAPACITY as u64) as usize;
+            if ring.events.len() < QUEUE_CAPACITY {
+                ring.events.push(event);
+            } else {
+                ring.events[index] = event;
+            }
+            ring.next += 1;
+        }
+
+        self.waiters.wake_all();
+    }
+
+    /// Returns queue of threads and tasks waiting for new events.
+    pub fn waiters(&self) -> &WaitQueue {
+        &self.waiters
+    }
+}
+
+impl Default for EventQueue {
+    fn default() -> EventQueue {
+        EventQueue::new()
+    }
+}
+
+/// Position of a reader in single queue
+struct Subscription {
+    queue: Arc<EventQueue>,
+    /// Sequence number of the next event to read
+    next: u64,
+    waiter: Option<WaiterId>,
+}
+
+impl Subscription {
+    /// Returns the next event, without moving past it. Skips events which have been
+    /// overwritten, adding their number to `lost`.
+    fn peek(&mut self, lost: &mut u64) -> Option<InputEvent> {
+        let ring = self.queue.ring.lock();
+        let oldest = ring.oldest();
+        if self.next < oldest {
+            *lost += oldest - self.next;
+            self.next = oldest;
+        }
+
+        if self.next < ring.next {
+            Some(ring.events[(self.next % QUEUE_CAPACITY as u64) as usize])
+        } else {
+            None
+        }
+    }
+
+    fn unregister(&mut self) {
+        if let Some(id) = self.waiter.take() {
+            self.queue.waiters.unregister(id);
+        }
+    }
+}
+
+/// Reader of events from one or more input devices
+///
+/// Reader starts with events published after it has begun to follow a device. It can be used
+/// from a thread, with [`wait`] and [`try_read`], or from an async task as a [`Stream`].
+///
+/// [`wait`]: #method.wait
+/// [`try_read`]: #method.try_read
+/// [`Stream`]: ../../executor/trait.Stream.html
+pub struct InputReader {
+    subscriptions: Vec<Subscription>,
+    /// Number of events overwritten before they could be read
+    lost: u64,
+}
+
+impl InputReader {
+    /// Creates reader which does not follow any device yet.
+    pub fn new() -> InputReader {
+        InputReader {
+            subscriptions: Vec::new(),
+            lost: 0,
+        }
+    }
+
+    /// Starts reading events published to `queue` from now on.
+    pub fn follow(&mut self, queue: &Arc<EventQueue>) {
+        let next = queue.ring.lock().next;
+        self.subscriptions.push(Subscription {
+            queue: queue.clone(),
+            next,
+            waiter: None,
+        });
+    }
+
+    /// Returns the oldest unread event of all followed devices, if there is any.
+    pub fn try_read(&mut self) -> Option<InputEvent> {
+        let lost = &mut self.lost;
+        let (index, event) = self.subscriptions
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, subscription)| {
+                subscription.peek(lost).map(|event| (index, event))
+            })
+            .min_by_key(|&(_, event)| event.time)?;
+
+        self.subscriptions[index].next += 1;
+        Some(event)
+    }
+
+    /// Blocks until any of followed devices publishes an event, and returns it.
+    pub fn wait(&mut self) -> InputEvent {
+        match self.wait_with(false, None) {
+            Ok(Some(event)) => event,
+            Ok(None) | Err(Interrupted) => unreachable!(),
+        }
+    }
+
+    /// Blocks until any of followed devices publishes an event and returns it, or until
+    /// current thread gets [interrupted].
+    ///
+    /// [interrupted]: ../../thread/fn.interrupt.html
+    pub fn wait_interruptible(&mut self) -> Result<InputEvent, Interrupted> {
+        self.wait_with(true, None).map(|event| event.unwrap())
+    }
+
+    /// Blocks until any of followed devices publishes an event and returns it, or until
+    /// `millis` milliseconds pass.
+    pub fn wait_timeout(&mut self, millis: u64) -> Option<InputEvent> {
+        let deadline = Instant::now() + millis;
+        let id = thread::current();
+        let timer = Timer::schedule(deadline, move || thread::wake(id));
+
+        let event = match self.wait_with(false, Some(deadline)) {
+            Ok(event) => event,
+            Err(Interrupted) => unreachable!(),
+        };
+
+        timer.cancel();
+        event
+    }
+
+    /// Waits for event, failing if the thread is interrupted, when `interruptible` is set,
+    /// or returning `None` once `deadline` passes, if there is any.
+    fn wait_with(
+        &mut self,
+        interruptible: bool,
+        deadline: Option<Instant>,
+    ) -> Result<Option<InputEvent>, Interrupted> {
+        loop {
+            if let Some(event) = self.try_read() {
+                return Ok(Some(event));
+            }
+            if interruptible && thread::is_interrupted() {
+                return Err(Interrupted);
+            }
+            if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                return Ok(None);
+            }
+
+            self.register(Waker::for_thread(thread::current()));
+
+            // Event could have been published before we got queued
+            let event = self.try_read();
+            if event.is_none() {
+                thread::block();
+            }
+            self.unregister();
+
+            if let Some(event) = event {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    /// Returns number of events which have been overwritten before the reader got to them.
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+
+    fn register(&mut self, waker: Waker) {
+        for subscription in self.subscriptions.iter_mut() {
+            subscription.waiter = Some(subscription.queue.waiters.register(waker.clone()));
+        }
+    }
+
+    fn unregister(&mut self) {
+        for subscription in self.subscriptions.iter_mut() {
+            subscription.unregister();
+        }
+    }
+}
+
+impl Default for InputReader {
+    fn default() -> InputReader {
+        InputReader::new()
+    }
+}
+
+impl Stream for InputReader {
+    type Item = InputEvent;
+
+    fn poll_next(&mut self, waker: &Waker) -> Poll<Option<InputEvent>> {
+        self.unregister();
+
+        if let Some(event) = self.try_read() {
+            return Poll::Ready(Some(event));
+        }
+
+        self.register(waker.clone());
+
+        // Event could have been published before we registered the waker
+        match self.try_read() {
+            Some(event) => Poll::Ready(Some(event)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for InputReader {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+/// Returns reader following all keyboards and serial terminals installed at the moment.
+pub fn console() -> InputReader {
+    let mut reader = InputReader::new();
+    let devices = dev::mgr::devices_of::<Kbd>()
+        .into_iter()
+        .chain(dev::mgr::devices_of::<Tty>());
+    for device in devices {
+        if let Some(input) = device.interface::<InputDevice>() {
+            reader.follow(input.events());
+        }
+    }
+    reader
+}
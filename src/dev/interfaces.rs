@@ -0,0 +1,117 @@
+//! Interfaces exposed by devices
+//!
+//! Besides its own type, a device can expose any number of interfaces, usually trait objects
+//! like [`InputDevice`] or [`SharedTextVideo`]. Device lists them in [`Device::interfaces`] as
+//! functions turning reference to the device into reference to the interface, and device
+//! manager keeps them in a table indexed by `TypeId` of the interface. Casts are checked, so
+//! a device is never mistaken for another type.
+//!
+//! Interfaces shared with other code, like text video kernel console writes to, are exposed
+//! behind a lock, e.g. as [`SharedTextVideo`]. Such device is cast into the locked form, but
+//! it is found by the trait too, e.g. by `implementing::<TextVideo>()`.
+//!
+//! ## Examples
+//!
+//! ```
+//! impl Device for Tty {
+//!     const CLASS_NAME: &'static str = "tty";
+//!
+//!     fn interfaces(interfaces: &mut Interfaces<Tty>) {
+//!         interfaces.add::<InputDevice>(|tty| tty);
+//!         interfaces.add_locked::<OutputSerial, SharedOutputSerial>(|tty| tty.driver.output());
+//!     }
+//! }
+//!
+//! for device in dev::mgr::implementing::<InputDevice>() {
+//!     reader.follow(device.interface::<InputDevice>().unwrap().events());
+//! }
+//! ```
+//!
+//! [`InputDevice`]: ../input/trait.InputDevice.html
+//! [`SharedTextVideo`]: ../text_video/type.SharedTextVideo.html
+//! [`Device::interfaces`]: ../trait.Device.html#method.interfaces
+
+use alloc::Vec;
+use alloc::boxed::Box;
+use core::any::{Any, TypeId};
+use core::marker::PhantomData;
+
+use dev::Device;
+
+/// Interfaces of device of type `D`, collected by [`Device::interfaces`]
+///
+/// [`Device::interfaces`]: ../trait.Device.html#method.interfaces
+pub struct Interfaces<D> {
+    table: InterfaceTable,
+    device: PhantomData<D>,
+}
+
+impl<D: Device> Interfaces<D> {
+    /// Exposes interface `I` of the device, `cast` turns the device into it.
+    pub fn add<I: ?Sized + 'static>(&mut self, cast: fn(&D) -> &I) {
+        let caster = Caster {
+            cast: Box::new(cast),
+            apply: apply::<D, I>,
+        };
+        self.table.entries.push((TypeId::of::<I>(), Box::new(caster)));
+    }
+
+    /// Exposes interface `I` behind lock `L`, e.g. `TextVideo` as `SharedTextVideo`, `cast`
+    /// turns the device into the latter. The device is found by both `implementing::<I>()`
+    /// and `implementing::<L>()`, but it can only be cast into `L`.
+    pub fn add_locked<I: ?Sized + 'static, L: ?Sized + 'static>(&mut self, cast: fn(&D) -> &L) {
+        self.add(cast);
+        self.table.locked.push(TypeId::of::<I>());
+    }
+}
+
+/// Cast of a device into interface `I`, with type of the device erased
+struct Caster<I: ?Sized + 'static> {
+    /// `fn(&D) -> &I`, where `D` is type of the device
+    cast: Box<Any + Send + Sync>,
+    /// Downcasts `cast` and the device, and applies the former to the latter
+    apply: for<'a> fn(&Any, &'a Any) -> Option<&'a I>,
+}
+
+fn apply<'a, D: Device, I: ?Sized + 'static>(cast: &Any, device: &'a Any) -> Option<&'a I> {
+    let cast = cast.downcast_ref::<fn(&D) -> &I>()?;
+    device.downcast_ref::<D>().map(|device| cast(device))
+}
+
+/// Interfaces of a device, with its type erased
+pub(super) struct InterfaceTable {
+    entries: Vec<(TypeId, Box<Any + Send + Sync>)>,
+    /// Interfaces exposed only behind a lock
+    locked: Vec<TypeId>,
+}
+
+impl InterfaceTable {
+    /// Collects interfaces devices of type `D` expose.
+    pub(super) fn of<D: Device>() -> InterfaceTable {
+        let mut interfaces = Interfaces {
+            table: InterfaceTable {
+                entries: Vec::new(),
+                locked: Vec::new(),
+            },
+            device: PhantomData::<D>,
+        };
+        D::interfaces(&mut interfaces);
+        interfaces.table
+    }
+
+    /// Returns `device` as interface `I`, if it is exposed.
+    pub(super) fn cast<'a, I: ?Sized + 'static>(&self, device: &'a Any) -> Option<&'a I> {
+        let id = TypeId::of::<I>();
+        let &(_, ref caster) = self.entries.iter().find(|&&(entry, _)| entry == id)?;
+
+        let caster: &Any = &**caster;
+        let caster = caster.downcast_ref::<Caster<I>>()?;
+        (caster.apply)(&*caster.cast, device)
+    }
+
+    /// Returns `true` if interface `I` is exposed, directly or behind a lock.
+    pub(super) fn contains<I: ?Sized + 'static>(&self) -> bool {
+        let id = TypeId::of::<I>();
+        self.entries.iter().any(|&(entry, _)| entry == id) || self.locked.contains(&id)
+    }
+}
@@ -0,0 +1,21 @@
+//! Legacy ISA device abstraction
+//!
+//! Devices every PC has at fixed I/O ports, like PS/2 controller or serial ports, cannot be
+//! found by scanning a bus. They are installed as `isa` devices instead, identified with PnP
+//! identifiers, which ACPI uses as hardware IDs (`_HID`) too, so that drivers can match them.
+
+use dev::Device;
+
+/// Legacy device at fixed resources
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct IsaDevice {
+    /// PnP identifier, e.g. `PNP0303` for PS/2 keyboard controller
+    pub id: &'static str,
+    /// The first of I/O ports used by the device. Interrupt lines are fixed too, drivers know
+    /// them, e.g. PS/2 controller raises two of them.
+    pub io_base: u16,
+}
+
+impl Device for IsaDevice {
+    const CLASS_NAME: &'static str = "isa";
+}
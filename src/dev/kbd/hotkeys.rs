@@ -0,0 +1,129 @@
+//! Debug hotkeys
+//!
+//! Hotkeys are handled by keyboard device before key events get published, so they work even
+//! when nothing reads the keyboard, e.g. when the shell hangs:
+//!
+//! - Ctrl+Alt+Del reboots the machine,
+//! - Alt+SysRq (Print Screen) held together with a letter runs one of debug commands, any
+//!   other key shows their list.
+
+use x86_64::registers::control_regs;
+
+use dev;
+use kio;
+use kio::pic;
+use mem;
+use thread;
+
+use super::{Key, KeyEvent, Modifiers};
+
+/// Debug commands run with Alt+SysRq, with keys, descriptions and handlers
+const COMMANDS: [(Key, &str, fn()); 7] = [
+    (Key::B, "reboot", reboot),
+    (Key::C, "crash the kernel", crash),
+    (Key::D, "show devices", show_devices),
+    (Key::I, "show interrupt statistics", show_interrupts),
+    (Key::M, "show memory usage", show_memory),
+    (Key::P, "show registers of waiting threads", show_registers),
+    (Key::T, "show threads", show_threads),
+];
+
+/// Runs hotkey triggered by `event`, `sysrq` tells if SysRq is held. Returns `true` if
+/// the event has been a hotkey, and should not be published.
+pub(super) fn handle(event: &KeyEvent, sysrq: bool) -> bool {
+    let modifiers = event.modifiers;
+    if !event.pressed || !modifiers.alt() {
+        return false;
+    }
+
+    if event.key == Key::Delete && modifiers.ctrl() {
+        reboot();
+    }
+
+    // Modifiers pressed while SysRq is held are not commands
+    if !sysrq || event.key == Key::PrintScreen || Modifiers::held_by(event.key).is_some() {
+        return false;
+    }
+
+    match COMMANDS.iter().find(|&&(key, _, _)| key == event.key) {
+        Some(&(_, _, command)) => command(),
+        None => show_help(),
+    }
+    true
+}
+
+fn show_help() {
+    println!("sysrq: Alt+SysRq with:");
+    for &(key, description, _) in COMMANDS.iter() {
+        println!("  {:?}  {}", key, description);
+    }
+}
+
+fn reboot() {
+    println!("Rebooting...");
+    kio::reboot();
+}
+
+fn crash() {
+    panic!("crash requested with SysRq");
+}
+
+fn show_devices() {
+    for device in dev::mgr::all() {
+        match device.parent() {
+            Some(parent) => println!("{} (on {})", device.name(), parent),
+            None => println!("{}", device.name()),
+        }
+    }
+}
+
+fn show_interrupts() {
+    for (line, &count) in pic::counts().iter().enumerate() {
+        if count != 0 {
+            println!("IRQ{:<2} {:>10}", line, count);
+        }
+    }
+    println!("dropped deferred work: {}", kio::defer::dropped());
+}
+
+fn show_memory() {
+    let stats = mem::stats();
+    println!(
+        "heap: {} of {} KiB used",
+        stats.heap_used / 1024,
+        stats.heap_size / 1024
+    );
+    println!("frames: {} used", stats.frames_used);
+}
+
+/// Prints where threads which are not running resume. The running one is the thread which
+/// drains deferred work, i.e. runs this command, so its registers tell nothing.
+fn show_registers() {
+    println!("   ID STATE    RSP                RIP");
+    for info in thread::list() {
+        let state = format!("{:?}", info.state);
+        match info.saved_context {
+            Some(context) => println!(
+                "{:>5} {:<8} {:#018x} {:#018x}",
+                info.id, state, context.rsp, context.rip
+            ),
+            None => println!("{:>5} {:<8} -", info.id, state),
+        }
+    }
+    println!("CR0={:?}", control_regs::cr0());
+    println!("CR2={:#018x}", control_regs::cr2().0);
+    println!("CR4={:?}", control_regs::cr4());
+}
+
+fn show_threads() {
+    println!("   ID STATE      CPU(ms) NAME");
+    for info in thread::list() {
+        println!(
+            "{:>5} {:<8} {:>9} {}",
+            info.id,
+            format!("{:?}", info.state),
+            info.cpu_time,
+            info.name
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Keyboard layouts
+//!
+//! Keymap tells which characters keys type on four levels: normal, with Shift, with AltGr
+//! (right Alt, or Ctrl and Alt together) and with both. Key may also be a dead key, which types
+//! nothing itself, but puts an accent on the next character. Layouts are described in
+//! `misc/keymaps` and compiled in by `bin/generate_keymap.py`, one of them is active at a time
+//! and used by all keyboards.
+
+use sync::IrqSpinLock;
+
+use super::keymaps::{KEYMAPS, US};
+use super::keys::{Key, Modifiers};
+
+/// What a key does on one level of a keymap
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Action {
+    None,
+    /// Types given character
+    Char(char),
+    /// Puts given accent on the next character
+    Dead(char),
+}
+
+/// Characters typed by a key
+#[derive(Debug)]
+pub struct Mapping {
+    pub key: Key,
+    /// Whether Caps Lock works like Shift for this key
+    pub caps_lock: bool,
+    /// Actions on normal, Shift, AltGr and Shift+AltGr levels
+    pub levels: [Action; 4],
+}
+
+/// Characters a dead key composes with
+#[derive(Debug)]
+pub struct DeadKey {
+    pub accent: char,
+    /// Base character and the composed one
+    pub combinations: &'static [(char, char)],
+}
+
+/// Keyboard layout
+#[derive(Debug)]
+pub struct Keymap {
+    /// Short name used to select the layout, e.g. `us`
+    pub name: &'static str,
+    pub description: &'static str,
+    pub mappings: &'static [Mapping],
+    pub dead_keys: &'static [DeadKey],
+}
+
+impl Keymap {
+    /// Returns action given key does with given modifiers.
+    pub fn action(&self, key: Key, modifiers: Modifiers) -> Action {
+        let mapping = match self.mappings.iter().find(|m| m.key == key) {
+            Some(mapping) => mapping,
+            None => return Action::None,
+        };
+
+        let mut shift = modifiers.shift();
+        if mapping.caps_lock && modifiers.contains(Modifiers::CAPS_LOCK) {
+            shift = !shift;
+        }
+        let altgr = modifiers.contains(Modifiers::RIGHT_ALT)
+            || (modifiers.ctrl() && modifiers.contains(Modifiers::LEFT_ALT));
+
+        mapping.levels[shift as usize + 2 * altgr as usize]
+    }
+
+    /// Returns character typed by dead key with given accent followed by `base`.
+    ///
+    /// Space and the dead key itself type the accent alone.
+    pub fn compose(&self, accent: char, base: char) -> Option<char> {
+        if base == ' ' || base == accent {
+            return Some(accent);
+        }
+
+        self.dead_keys
+            .iter()
+            .find(|dead_key| dead_key.accent == accent)
+            .and_then(|dead_key| {
+                dead_key
+                    .combinations
+                    .iter()
+                    .find(|&&(from, _)| from == base)
+                    .map(|&(_, composed)| composed)
+            })
+    }
+}
+
+/// Translates key press into character typed with the active keymap.
+///
+/// `dead_key` holds accent of dead key pressed before, if any. Enter gives `'\n'`, Backspace
+/// `'\x08'`, Escape `'\x1b'`, and Ctrl with a letter gives corresponding control character.
+/// Keypad digits type only while Num Lock is on.
+pub(super) fn translate(
+    key: Key,
+    modifiers: Modifiers,
+    dead_key: &mut Option<char>,
+) -> Option<char> {
+    if let Some(char) = fixed_char(key, modifiers) {
+        *dead_key = None;
+        return Some(char);
+    }
+
+    let keymap = active();
+    let action = match keymap.action(key, modifiers) {
+        // Control characters are chosen by letter on normal level, as labelled on the key
+        Action::Char(letter) if modifiers.ctrl() && !modifiers.alt() => {
+            match keymap.action(key, Modifiers::empty()) {
+                Action::Char(base @ 'a'...'z') => Action::Char((base as u8 & 0x1f) as char),
+                _ => Action::Char(letter),
+            }
+        }
+        Action::None if key == Key::Space => Action::Char(' '),
+        action => action,
+    };
+
+    match action {
+        Action::None => None,
+        Action::Char(char) => match dead_key.take() {
+            Some(accent) => Some(keymap.compose(accent, char).unwrap_or(char)),
+            None => Some(char),
+        },
+        Action::Dead(accent) => match dead_key.take() {
+            // Pressing dead key twice types the accent
+            Some(previous) => Some(previous),
+            None => {
+                *dead_key = Some(accent);
+                None
+            }
+        },
+    }
+}
+
+/// Returns character typed by key which does not depend on keyboard layout.
+fn fixed_char(key: Key, modifiers: Modifiers) -> Option<char> {
+    let num_lock = modifiers.contains(Modifiers::NUM_LOCK);
+    let char = match key {
+        Key::Enter | Key::KeypadEnter => '\n',
+        Key::Tab => '\t',
+        Key::Backspace => '\x08',
+        Key::Escape => '\x1b',
+        Key::KeypadDivide => '/',
+        Key::KeypadMultiply => '*',
+        Key::KeypadMinus => '-',
+        Key::KeypadPlus => '+',
+        Key::Keypad0 if num_lock => '0',
+        Key::Keypad1 if num_lock => '1',
+        Key::Keypad2 if num_lock => '2',
+        Key::Keypad3 if num_lock => '3',
+        Key::Keypad4 if num_lock => '4',
+        Key::Keypad5 if num_lock => '5',
+        Key::Keypad6 if num_lock => '6',
+        Key::Keypad7 if num_lock => '7',
+        Key::Keypad8 if num_lock => '8',
+        Key::Keypad9 if num_lock => '9',
+        Key::KeypadPeriod if num_lock => '.',
+        _ => return None,
+    };
+    Some(char)
+}
+
+static ACTIVE: IrqSpinLock<&'static Keymap> = IrqSpinLock::new(&US);
+
+/// Returns all compiled-in keymaps.
+pub fn all() -> &'static [&'static Keymap] {
+    &KEYMAPS
+}
+
+/// Returns keymap used by keyboards.
+pub fn active() -> &'static Keymap {
+    *ACTIVE.lock()
+}
+
+/// Makes keymap with given name active. Returns `false` if there is no such keymap.
+pub fn set_active(name: &str) -> bool {
+    match KEYMAPS.iter().find(|keymap| keymap.name == name) {
+        Some(&keymap) => {
+            *ACTIVE.lock() = keymap;
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,2041 @@
+//! Compiled-in keyboard layouts
+//!
+//! Generated by `bin/generate_keymap.py` from `misc/keymaps`, do not edit.
+
+use super::keymap::{Action, DeadKey, Keymap, Mapping};
+use super::keys::Key;
+
+/// All compiled-in layouts, sorted by name
+pub static KEYMAPS: [&Keymap; 4] = [&DE, &FR, &GB, &US];
+
+pub static DE: Keymap = Keymap {
+    name: "de",
+    description: "German (QWERTZ)",
+    mappings: &[
+        Mapping {
+            key: Key::Backquote,
+            caps_lock: false,
+            levels: [
+                Action::Dead('^'),
+                Action::Char('\u{b0}'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit1,
+            caps_lock: false,
+            levels: [
+                Action::Char('1'),
+                Action::Char('!'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit2,
+            caps_lock: false,
+            levels: [
+                Action::Char('2'),
+                Action::Char('"'),
+                Action::Char('\u{b2}'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit3,
+            caps_lock: false,
+            levels: [
+                Action::Char('3'),
+                Action::Char('\u{a7}'),
+                Action::Char('\u{b3}'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit4,
+            caps_lock: false,
+            levels: [
+                Action::Char('4'),
+                Action::Char('$'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit5,
+            caps_lock: false,
+            levels: [
+                Action::Char('5'),
+                Action::Char('%'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit6,
+            caps_lock: false,
+            levels: [
+                Action::Char('6'),
+                Action::Char('&'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit7,
+            caps_lock: false,
+            levels: [
+                Action::Char('7'),
+                Action::Char('/'),
+                Action::Char('{'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit8,
+            caps_lock: false,
+            levels: [
+                Action::Char('8'),
+                Action::Char('('),
+                Action::Char('['),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit9,
+            caps_lock: false,
+            levels: [
+                Action::Char('9'),
+                Action::Char(')'),
+                Action::Char(']'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit0,
+            caps_lock: false,
+            levels: [
+                Action::Char('0'),
+                Action::Char('='),
+                Action::Char('}'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Minus,
+            caps_lock: false,
+            levels: [
+                Action::Char('\u{df}'),
+                Action::Char('?'),
+                Action::Char('\\'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Equal,
+            caps_lock: false,
+            levels: [
+                Action::Dead('\u{b4}'),
+                Action::Dead('`'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Q,
+            caps_lock: true,
+            levels: [
+                Action::Char('q'),
+                Action::Char('Q'),
+                Action::Char('@'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::W,
+            caps_lock: true,
+            levels: [
+                Action::Char('w'),
+                Action::Char('W'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::E,
+            caps_lock: true,
+            levels: [
+                Action::Char('e'),
+                Action::Char('E'),
+                Action::Char('\u{20ac}'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::R,
+            caps_lock: true,
+            levels: [
+                Action::Char('r'),
+                Action::Char('R'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::T,
+            caps_lock: true,
+            levels: [
+                Action::Char('t'),
+                Action::Char('T'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Y,
+            caps_lock: true,
+            levels: [
+                Action::Char('z'),
+                Action::Char('Z'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::U,
+            caps_lock: true,
+            levels: [
+                Action::Char('u'),
+                Action::Char('U'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::I,
+            caps_lock: true,
+            levels: [
+                Action::Char('i'),
+                Action::Char('I'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::O,
+            caps_lock: true,
+            levels: [
+                Action::Char('o'),
+                Action::Char('O'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::P,
+            caps_lock: true,
+            levels: [
+                Action::Char('p'),
+                Action::Char('P'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::LeftBracket,
+            caps_lock: true,
+            levels: [
+                Action::Char('\u{fc}'),
+                Action::Char('\u{dc}'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::RightBracket,
+            caps_lock: false,
+            levels: [
+                Action::Char('+'),
+                Action::Char('*'),
+                Action::Char('~'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Backslash,
+            caps_lock: false,
+            levels: [
+                Action::Char('#'),
+                Action::Char('\''),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::A,
+            caps_lock: true,
+            levels: [
+                Action::Char('a'),
+                Action::Char('A'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::S,
+            caps_lock: true,
+            levels: [
+                Action::Char('s'),
+                Action::Char('S'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::D,
+            caps_lock: true,
+            levels: [
+                Action::Char('d'),
+                Action::Char('D'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::F,
+            caps_lock: true,
+            levels: [
+                Action::Char('f'),
+                Action::Char('F'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::G,
+            caps_lock: true,
+            levels: [
+                Action::Char('g'),
+                Action::Char('G'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::H,
+            caps_lock: true,
+            levels: [
+                Action::Char('h'),
+                Action::Char('H'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::J,
+            caps_lock: true,
+            levels: [
+                Action::Char('j'),
+                Action::Char('J'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::K,
+            caps_lock: true,
+            levels: [
+                Action::Char('k'),
+                Action::Char('K'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::L,
+            caps_lock: true,
+            levels: [
+                Action::Char('l'),
+                Action::Char('L'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Semicolon,
+            caps_lock: true,
+            levels: [
+                Action::Char('\u{f6}'),
+                Action::Char('\u{d6}'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Quote,
+            caps_lock: true,
+            levels: [
+                Action::Char('\u{e4}'),
+                Action::Char('\u{c4}'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::NonUsBackslash,
+            caps_lock: false,
+            levels: [
+                Action::Char('<'),
+                Action::Char('>'),
+                Action::Char('|'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Z,
+            caps_lock: true,
+            levels: [
+                Action::Char('y'),
+                Action::Char('Y'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::X,
+            caps_lock: true,
+            levels: [
+                Action::Char('x'),
+                Action::Char('X'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::C,
+            caps_lock: true,
+            levels: [
+                Action::Char('c'),
+                Action::Char('C'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::V,
+            caps_lock: true,
+            levels: [
+                Action::Char('v'),
+                Action::Char('V'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::B,
+            caps_lock: true,
+            levels: [
+                Action::Char('b'),
+                Action::Char('B'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::N,
+            caps_lock: true,
+            levels: [
+                Action::Char('n'),
+                Action::Char('N'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::M,
+            caps_lock: true,
+            levels: [
+                Action::Char('m'),
+                Action::Char('M'),
+                Action::Char('\u{b5}'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Comma,
+            caps_lock: false,
+            levels: [
+                Action::Char(','),
+                Action::Char(';'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Period,
+            caps_lock: false,
+            levels: [
+                Action::Char('.'),
+                Action::Char(':'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Slash,
+            caps_lock: false,
+            levels: [
+                Action::None,
+                Action::Char('_'),
+                Action::None,
+                Action::None,
+            ],
+        },
+    ],
+    dead_keys: &[
+        DeadKey {
+            accent: '^',
+            combinations: &[
+                ('a', '\u{e2}'),
+                ('e', '\u{ea}'),
+                ('i', '\u{ee}'),
+                ('o', '\u{f4}'),
+                ('u', '\u{fb}'),
+                ('A', '\u{c2}'),
+                ('E', '\u{ca}'),
+                ('I', '\u{ce}'),
+                ('O', '\u{d4}'),
+                ('U', '\u{db}'),
+            ],
+        },
+        DeadKey {
+            accent: '\u{b4}',
+            combinations: &[
+                ('a', '\u{e1}'),
+                ('e', '\u{e9}'),
+                ('i', '\u{ed}'),
+                ('o', '\u{f3}'),
+                ('u', '\u{fa}'),
+                ('y', '\u{fd}'),
+                ('A', '\u{c1}'),
+                ('E', '\u{c9}'),
+                ('I', '\u{cd}'),
+                ('O', '\u{d3}'),
+                ('U', '\u{da}'),
+                ('Y', '\u{dd}'),
+            ],
+        },
+        DeadKey {
+            accent: '`',
+            combinations: &[
+                ('a', '\u{e0}'),
+                ('e', '\u{e8}'),
+                ('i', '\u{ec}'),
+                ('o', '\u{f2}'),
+                ('u', '\u{f9}'),
+                ('A', '\u{c0}'),
+                ('E', '\u{c8}'),
+                ('I', '\u{cc}'),
+                ('O', '\u{d2}'),
+                ('U', '\u{d9}'),
+            ],
+        },
+    ],
+};
+
+pub static FR: Keymap = Keymap {
+    name: "fr",
+    description: "French (AZERTY)",
+    mappings: &[
+        Mapping {
+            key: Key::Backquote,
+            caps_lock: false,
+            levels: [
+                Action::Char('\u{b2}'),
+                Action::None,
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit1,
+            caps_lock: false,
+            levels: [
+                Action::Char('&'),
+                Action::Char('1'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit2,
+            caps_lock: false,
+            levels: [
+                Action::Char('\u{e9}'),
+                Action::Char('2'),
+                Action::Char('~'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit3,
+            caps_lock: false,
+            levels: [
+                Action::Char('"'),
+                Action::Char('3'),
+                Action::Char('#'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit4,
+            caps_lock: false,
+            levels: [
+                Action::Char('\''),
+                Action::Char('4'),
+                Action::Char('{'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit5,
+            caps_lock: false,
+            levels: [
+                Action::Char('('),
+                Action::Char('5'),
+                Action::Char('['),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit6,
+            caps_lock: false,
+            levels: [
+                Action::None,
+                Action::Char('6'),
+                Action::Char('|'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit7,
+            caps_lock: false,
+            levels: [
+                Action::Char('\u{e8}'),
+                Action::Char('7'),
+                Action::Char('`'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit8,
+            caps_lock: false,
+            levels: [
+                Action::Char('_'),
+                Action::Char('8'),
+                Action::Char('\\'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit9,
+            caps_lock: false,
+            levels: [
+                Action::Char('\u{e7}'),
+                Action::Char('9'),
+                Action::Char('^'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit0,
+            caps_lock: false,
+            levels: [
+                Action::Char('\u{e0}'),
+                Action::Char('0'),
+                Action::Char('@'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Minus,
+            caps_lock: false,
+            levels: [
+                Action::Char(')'),
+                Action::Char('\u{b0}'),
+                Action::Char(']'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Equal,
+            caps_lock: false,
+            levels: [
+                Action::Char('='),
+                Action::Char('+'),
+                Action::Char('}'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Q,
+            caps_lock: true,
+            levels: [
+                Action::Char('a'),
+                Action::Char('A'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::W,
+            caps_lock: true,
+            levels: [
+                Action::Char('z'),
+                Action::Char('Z'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::E,
+            caps_lock: true,
+            levels: [
+                Action::Char('e'),
+                Action::Char('E'),
+                Action::Char('\u{20ac}'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::R,
+            caps_lock: true,
+            levels: [
+                Action::Char('r'),
+                Action::Char('R'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::T,
+            caps_lock: true,
+            levels: [
+                Action::Char('t'),
+                Action::Char('T'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Y,
+            caps_lock: true,
+            levels: [
+                Action::Char('y'),
+                Action::Char('Y'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::U,
+            caps_lock: true,
+            levels: [
+                Action::Char('u'),
+                Action::Char('U'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::I,
+            caps_lock: true,
+            levels: [
+                Action::Char('i'),
+                Action::Char('I'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::O,
+            caps_lock: true,
+            levels: [
+                Action::Char('o'),
+                Action::Char('O'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::P,
+            caps_lock: true,
+            levels: [
+                Action::Char('p'),
+                Action::Char('P'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::LeftBracket,
+            caps_lock: false,
+            levels: [
+                Action::Dead('^'),
+                Action::Dead('\u{a8}'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::RightBracket,
+            caps_lock: false,
+            levels: [
+                Action::Char('$'),
+                Action::Char('\u{a3}'),
+                Action::Char('\u{a4}'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Backslash,
+            caps_lock: false,
+            levels: [
+                Action::Char('*'),
+                Action::Char('\u{b5}'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::A,
+            caps_lock: true,
+            levels: [
+                Action::Char('q'),
+                Action::Char('Q'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::S,
+            caps_lock: true,
+            levels: [
+                Action::Char('s'),
+                Action::Char('S'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::D,
+            caps_lock: true,
+            levels: [
+                Action::Char('d'),
+                Action::Char('D'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::F,
+            caps_lock: true,
+            levels: [
+                Action::Char('f'),
+                Action::Char('F'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::G,
+            caps_lock: true,
+            levels: [
+                Action::Char('g'),
+                Action::Char('G'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::H,
+            caps_lock: true,
+            levels: [
+                Action::Char('h'),
+                Action::Char('H'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::J,
+            caps_lock: true,
+            levels: [
+                Action::Char('j'),
+                Action::Char('J'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::K,
+            caps_lock: true,
+            levels: [
+                Action::Char('k'),
+                Action::Char('K'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::L,
+            caps_lock: true,
+            levels: [
+                Action::Char('l'),
+                Action::Char('L'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Semicolon,
+            caps_lock: true,
+            levels: [
+                Action::Char('m'),
+                Action::Char('M'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Quote,
+            caps_lock: false,
+            levels: [
+                Action::Char('\u{f9}'),
+                Action::Char('%'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::NonUsBackslash,
+            caps_lock: false,
+            levels: [
+                Action::Char('<'),
+                Action::Char('>'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Z,
+            caps_lock: true,
+            levels: [
+                Action::Char('w'),
+                Action::Char('W'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::X,
+            caps_lock: true,
+            levels: [
+                Action::Char('x'),
+                Action::Char('X'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::C,
+            caps_lock: true,
+            levels: [
+                Action::Char('c'),
+                Action::Char('C'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::V,
+            caps_lock: true,
+            levels: [
+                Action::Char('v'),
+                Action::Char('V'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::B,
+            caps_lock: true,
+            levels: [
+                Action::Char('b'),
+                Action::Char('B'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::N,
+            caps_lock: true,
+            levels: [
+                Action::Char('n'),
+                Action::Char('N'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::M,
+            caps_lock: false,
+            levels: [
+                Action::Char(','),
+                Action::Char('?'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Comma,
+            caps_lock: false,
+            levels: [
+                Action::Char(';'),
+                Action::Char('.'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Period,
+            caps_lock: false,
+            levels: [
+                Action::Char(':'),
+                Action::Char('/'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Slash,
+            caps_lock: false,
+            levels: [
+                Action::Char('!'),
+                Action::Char('\u{a7}'),
+                Action::None,
+                Action::None,
+            ],
+        },
+    ],
+    dead_keys: &[
+        DeadKey {
+            accent: '^',
+            combinations: &[
+                ('a', '\u{e2}'),
+                ('e', '\u{ea}'),
+                ('i', '\u{ee}'),
+                ('o', '\u{f4}'),
+                ('u', '\u{fb}'),
+                ('A', '\u{c2}'),
+                ('E', '\u{ca}'),
+                ('I', '\u{ce}'),
+                ('O', '\u{d4}'),
+                ('U', '\u{db}'),
+            ],
+        },
+        DeadKey {
+            accent: '\u{a8}',
+            combinations: &[
+                ('a', '\u{e4}'),
+                ('e', '\u{eb}'),
+                ('i', '\u{ef}'),
+                ('o', '\u{f6}'),
+                ('u', '\u{fc}'),
+                ('y', '\u{ff}'),
+                ('A', '\u{c4}'),
+                ('E', '\u{cb}'),
+                ('I', '\u{cf}'),
+                ('O', '\u{d6}'),
+                ('U', '\u{dc}'),
+            ],
+        },
+    ],
+};
+
+pub static GB: Keymap = Keymap {
+    name: "gb",
+    description: "English (UK)",
+    mappings: &[
+        Mapping {
+            key: Key::Backquote,
+            caps_lock: false,
+            levels: [
+                Action::Char('`'),
+                Action::Char('\u{ac}'),
+                Action::Char('\u{a6}'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit1,
+            caps_lock: false,
+            levels: [
+                Action::Char('1'),
+                Action::Char('!'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit2,
+            caps_lock: false,
+            levels: [
+                Action::Char('2'),
+                Action::Char('"'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit3,
+            caps_lock: false,
+            levels: [
+                Action::Char('3'),
+                Action::Char('\u{a3}'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit4,
+            caps_lock: false,
+            levels: [
+                Action::Char('4'),
+                Action::Char('$'),
+                Action::Char('\u{20ac}'),
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit5,
+            caps_lock: false,
+            levels: [
+                Action::Char('5'),
+                Action::Char('%'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit6,
+            caps_lock: false,
+            levels: [
+                Action::Char('6'),
+                Action::Char('^'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit7,
+            caps_lock: false,
+            levels: [
+                Action::Char('7'),
+                Action::Char('&'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit8,
+            caps_lock: false,
+            levels: [
+                Action::Char('8'),
+                Action::Char('*'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit9,
+            caps_lock: false,
+            levels: [
+                Action::Char('9'),
+                Action::Char('('),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit0,
+            caps_lock: false,
+            levels: [
+                Action::Char('0'),
+                Action::Char(')'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Minus,
+            caps_lock: false,
+            levels: [
+                Action::None,
+                Action::Char('_'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Equal,
+            caps_lock: false,
+            levels: [
+                Action::Char('='),
+                Action::Char('+'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Q,
+            caps_lock: true,
+            levels: [
+                Action::Char('q'),
+                Action::Char('Q'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::W,
+            caps_lock: true,
+            levels: [
+                Action::Char('w'),
+                Action::Char('W'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::E,
+            caps_lock: true,
+            levels: [
+                Action::Char('e'),
+                Action::Char('E'),
+                Action::Char('\u{e9}'),
+                Action::Char('\u{c9}'),
+            ],
+        },
+        Mapping {
+            key: Key::R,
+            caps_lock: true,
+            levels: [
+                Action::Char('r'),
+                Action::Char('R'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::T,
+            caps_lock: true,
+            levels: [
+                Action::Char('t'),
+                Action::Char('T'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Y,
+            caps_lock: true,
+            levels: [
+                Action::Char('y'),
+                Action::Char('Y'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::U,
+            caps_lock: true,
+            levels: [
+                Action::Char('u'),
+                Action::Char('U'),
+                Action::Char('\u{fa}'),
+                Action::Char('\u{da}'),
+            ],
+        },
+        Mapping {
+            key: Key::I,
+            caps_lock: true,
+            levels: [
+                Action::Char('i'),
+                Action::Char('I'),
+                Action::Char('\u{ed}'),
+                Action::Char('\u{cd}'),
+            ],
+        },
+        Mapping {
+            key: Key::O,
+            caps_lock: true,
+            levels: [
+                Action::Char('o'),
+                Action::Char('O'),
+                Action::Char('\u{f3}'),
+                Action::Char('\u{d3}'),
+            ],
+        },
+        Mapping {
+            key: Key::P,
+            caps_lock: true,
+            levels: [
+                Action::Char('p'),
+                Action::Char('P'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::LeftBracket,
+            caps_lock: false,
+            levels: [
+                Action::Char('['),
+                Action::Char('{'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::RightBracket,
+            caps_lock: false,
+            levels: [
+                Action::Char(']'),
+                Action::Char('}'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Backslash,
+            caps_lock: false,
+            levels: [
+                Action::Char('#'),
+                Action::Char('~'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::A,
+            caps_lock: true,
+            levels: [
+                Action::Char('a'),
+                Action::Char('A'),
+                Action::Char('\u{e1}'),
+                Action::Char('\u{c1}'),
+            ],
+        },
+        Mapping {
+            key: Key::S,
+            caps_lock: true,
+            levels: [
+                Action::Char('s'),
+                Action::Char('S'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::D,
+            caps_lock: true,
+            levels: [
+                Action::Char('d'),
+                Action::Char('D'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::F,
+            caps_lock: true,
+            levels: [
+                Action::Char('f'),
+                Action::Char('F'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::G,
+            caps_lock: true,
+            levels: [
+                Action::Char('g'),
+                Action::Char('G'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::H,
+            caps_lock: true,
+            levels: [
+                Action::Char('h'),
+                Action::Char('H'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::J,
+            caps_lock: true,
+            levels: [
+                Action::Char('j'),
+                Action::Char('J'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::K,
+            caps_lock: true,
+            levels: [
+                Action::Char('k'),
+                Action::Char('K'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::L,
+            caps_lock: true,
+            levels: [
+                Action::Char('l'),
+                Action::Char('L'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Semicolon,
+            caps_lock: false,
+            levels: [
+                Action::Char(';'),
+                Action::Char(':'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Quote,
+            caps_lock: false,
+            levels: [
+                Action::Char('\''),
+                Action::Char('@'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::NonUsBackslash,
+            caps_lock: false,
+            levels: [
+                Action::Char('\\'),
+                Action::Char('|'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Z,
+            caps_lock: true,
+            levels: [
+                Action::Char('z'),
+                Action::Char('Z'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::X,
+            caps_lock: true,
+            levels: [
+                Action::Char('x'),
+                Action::Char('X'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::C,
+            caps_lock: true,
+            levels: [
+                Action::Char('c'),
+                Action::Char('C'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::V,
+            caps_lock: true,
+            levels: [
+                Action::Char('v'),
+                Action::Char('V'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::B,
+            caps_lock: true,
+            levels: [
+                Action::Char('b'),
+                Action::Char('B'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::N,
+            caps_lock: true,
+            levels: [
+                Action::Char('n'),
+                Action::Char('N'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::M,
+            caps_lock: true,
+            levels: [
+                Action::Char('m'),
+                Action::Char('M'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Comma,
+            caps_lock: false,
+            levels: [
+                Action::Char(','),
+                Action::Char('<'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Period,
+            caps_lock: false,
+            levels: [
+                Action::Char('.'),
+                Action::Char('>'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Slash,
+            caps_lock: false,
+            levels: [
+                Action::Char('/'),
+                Action::Char('?'),
+                Action::None,
+                Action::None,
+            ],
+        },
+    ],
+    dead_keys: &[],
+};
+
+pub static US: Keymap = Keymap {
+    name: "us",
+    description: "English (US)",
+    mappings: &[
+        Mapping {
+            key: Key::Backquote,
+            caps_lock: false,
+            levels: [
+                Action::Char('`'),
+                Action::Char('~'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit1,
+            caps_lock: false,
+            levels: [
+                Action::Char('1'),
+                Action::Char('!'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit2,
+            caps_lock: false,
+            levels: [
+                Action::Char('2'),
+                Action::Char('@'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit3,
+            caps_lock: false,
+            levels: [
+                Action::Char('3'),
+                Action::Char('#'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit4,
+            caps_lock: false,
+            levels: [
+                Action::Char('4'),
+                Action::Char('$'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit5,
+            caps_lock: false,
+            levels: [
+                Action::Char('5'),
+                Action::Char('%'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit6,
+            caps_lock: false,
+            levels: [
+                Action::Char('6'),
+                Action::Char('^'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit7,
+            caps_lock: false,
+            levels: [
+                Action::Char('7'),
+                Action::Char('&'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit8,
+            caps_lock: false,
+            levels: [
+                Action::Char('8'),
+                Action::Char('*'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit9,
+            caps_lock: false,
+            levels: [
+                Action::Char('9'),
+                Action::Char('('),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Digit0,
+            caps_lock: false,
+            levels: [
+                Action::Char('0'),
+                Action::Char(')'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Minus,
+            caps_lock: false,
+            levels: [
+                Action::None,
+                Action::Char('_'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Equal,
+            caps_lock: false,
+            levels: [
+                Action::Char('='),
+                Action::Char('+'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Q,
+            caps_lock: true,
+            levels: [
+                Action::Char('q'),
+                Action::Char('Q'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::W,
+            caps_lock: true,
+            levels: [
+                Action::Char('w'),
+                Action::Char('W'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::E,
+            caps_lock: true,
+            levels: [
+                Action::Char('e'),
+                Action::Char('E'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::R,
+            caps_lock: true,
+            levels: [
+                Action::Char('r'),
+                Action::Char('R'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::T,
+            caps_lock: true,
+            levels: [
+                Action::Char('t'),
+                Action::Char('T'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Y,
+            caps_lock: true,
+            levels: [
+                Action::Char('y'),
+                Action::Char('Y'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::U,
+            caps_lock: true,
+            levels: [
+                Action::Char('u'),
+                Action::Char('U'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::I,
+            caps_lock: true,
+            levels: [
+                Action::Char('i'),
+                Action::Char('I'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::O,
+            caps_lock: true,
+            levels: [
+                Action::Char('o'),
+                Action::Char('O'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::P,
+            caps_lock: true,
+            levels: [
+                Action::Char('p'),
+                Action::Char('P'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::LeftBracket,
+            caps_lock: false,
+            levels: [
+                Action::Char('['),
+                Action::Char('{'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::RightBracket,
+            caps_lock: false,
+            levels: [
+                Action::Char(']'),
+                Action::Char('}'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Backslash,
+            caps_lock: false,
+            levels: [
+                Action::Char('\\'),
+                Action::Char('|'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::A,
+            caps_lock: true,
+            levels: [
+                Action::Char('a'),
+                Action::Char('A'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::S,
+            caps_lock: true,
+            levels: [
+                Action::Char('s'),
+                Action::Char('S'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::D,
+            caps_lock: true,
+            levels: [
+                Action::Char('d'),
+                Action::Char('D'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::F,
+            caps_lock: true,
+            levels: [
+                Action::Char('f'),
+                Action::Char('F'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::G,
+            caps_lock: true,
+            levels: [
+                Action::Char('g'),
+                Action::Char('G'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::H,
+            caps_lock: true,
+            levels: [
+                Action::Char('h'),
+                Action::Char('H'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::J,
+            caps_lock: true,
+            levels: [
+                Action::Char('j'),
+                Action::Char('J'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::K,
+            caps_lock: true,
+            levels: [
+                Action::Char('k'),
+                Action::Char('K'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::L,
+            caps_lock: true,
+            levels: [
+                Action::Char('l'),
+                Action::Char('L'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Semicolon,
+            caps_lock: false,
+            levels: [
+                Action::Char(';'),
+                Action::Char(':'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Quote,
+            caps_lock: false,
+            levels: [
+                Action::Char('\''),
+                Action::Char('"'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::NonUsBackslash,
+            caps_lock: false,
+            levels: [
+                Action::Char('\\'),
+                Action::Char('|'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Z,
+            caps_lock: true,
+            levels: [
+                Action::Char('z'),
+                Action::Char('Z'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::X,
+            caps_lock: true,
+            levels: [
+                Action::Char('x'),
+                Action::Char('X'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::C,
+            caps_lock: true,
+            levels: [
+                Action::Char('c'),
+                Action::Char('C'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::V,
+            caps_lock: true,
+            levels: [
+                Action::Char('v'),
+                Action::Char('V'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::B,
+            caps_lock: true,
+            levels: [
+                Action::Char('b'),
+                Action::Char('B'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::N,
+            caps_lock: true,
+            levels: [
+                Action::Char('n'),
+                Action::Char('N'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::M,
+            caps_lock: true,
+            levels: [
+                Action::Char('m'),
+                Action::Char('M'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Comma,
+            caps_lock: false,
+            levels: [
+                Action::Char(','),
+                Action::Char('<'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Period,
+            caps_lock: false,
+            levels: [
+                Action::Char('.'),
+                Action::Char('>'),
+                Action::None,
+                Action::None,
+            ],
+        },
+        Mapping {
+            key: Key::Slash,
+            caps_lock: false,
+            levels: [
+                Action::Char('/'),
+                Action::Char('?'),
+                Action::None,
+                Action::None,
+            ],
+        },
+    ],
+    dead_keys: &[],
+};
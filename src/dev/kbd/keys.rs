@@ -1,27 +1,203 @@
-static TABLE: [u8; 256] = [
-    0, 0, 49, 50, 51, 52, 53, 54, 55, 56, 57, 48, 45, 61, 0, 0, 113, 119, 101, 114, 116, 121, 117,
-    105, 111, 112, 91, 93, 10, 0, 97, 115, 100, 102, 103, 104, 106, 107, 108, 59, 39, 96, 0, 92,
-    122, 120, 99, 118, 98, 110, 109, 44, 46, 47, 0, 42, 0, 32, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 55, 56, 57, 45, 52, 53, 54, 43, 49, 50, 51, 48, 46, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 177, 178, 179, 180, 181, 182, 183, 224, 185, 176, 173, 189, 0, 0, 241, 247, 229, 242, 244,
-    249, 245, 233, 239, 240, 219, 221, 138, 0, 225, 243, 228, 230, 231, 232, 234, 235, 236, 187,
-    167, 0, 0, 220, 250, 248, 227, 246, 226, 238, 237, 172, 174, 175, 0, 170, 0, 160, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 183, 184, 185, 173, 180, 181, 182, 171, 177, 178, 179, 176, 174, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-];
+//! Keys and key events
+//!
+//! Keyboard drivers report physical keys, named after the character they produce on US layout,
+//! together with press or release. Keyboard device tracks modifier state and turns them into
+//! [`KeyEvent`]s, with characters typed resolved using the active [keymap].
+//!
+//! [`KeyEvent`]: ./struct.KeyEvent.html
+//! [keymap]: ../keymap/index.html
 
+/// Physical key on the keyboard
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-pub struct KeyCode(pub u8);
-
-impl From<KeyCode> for u8 {
-    fn from(key_code: KeyCode) -> u8 {
-        let key = TABLE[key_code.0 as usize];
-        if key & 0b1000_0000 != 0 {
-            0
-        } else {
-            key
+pub enum Key {
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    PrintScreen,
+    ScrollLock,
+    Pause,
+
+    Backquote,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    Digit0,
+    Minus,
+    Equal,
+    Backspace,
+
+    Tab,
+    Q,
+    W,
+    E,
+    R,
+    T,
+    Y,
+    U,
+    I,
+    O,
+    P,
+    LeftBracket,
+    RightBracket,
+    Backslash,
+
+    CapsLock,
+    A,
+    S,
+    D,
+    F,
+    G,
+    H,
+    J,
+    K,
+    L,
+    Semicolon,
+    Quote,
+    Enter,
+
+    LeftShift,
+    /// Additional key next to left Shift on ISO keyboards
+    NonUsBackslash,
+    Z,
+    X,
+    C,
+    V,
+    B,
+    N,
+    M,
+    Comma,
+    Period,
+    Slash,
+    RightShift,
+
+    LeftCtrl,
+    LeftGui,
+    LeftAlt,
+    Space,
+    RightAlt,
+    RightGui,
+    Menu,
+    RightCtrl,
+
+    Insert,
+    Home,
+    PageUp,
+    Delete,
+    End,
+    PageDown,
+    Up,
+    Left,
+    Down,
+    Right,
+
+    NumLock,
+    KeypadDivide,
+    KeypadMultiply,
+    KeypadMinus,
+    KeypadPlus,
+    KeypadEnter,
+    KeypadPeriod,
+    Keypad0,
+    Keypad1,
+    Keypad2,
+    Keypad3,
+    Keypad4,
+    Keypad5,
+    Keypad6,
+    Keypad7,
+    Keypad8,
+    Keypad9,
+
+    /// Key the driver cannot name, with its raw code
+    Unknown(u16),
+}
+
+bitflags! {
+    /// State of modifier keys and lock toggles
+    pub struct Modifiers: u16 {
+        const LEFT_SHIFT =  1 << 0;
+        const RIGHT_SHIFT = 1 << 1;
+        const LEFT_CTRL =   1 << 2;
+        const RIGHT_CTRL =  1 << 3;
+        const LEFT_ALT =    1 << 4;
+        const RIGHT_ALT =   1 << 5;
+        const CAPS_LOCK =   1 << 6;
+        const NUM_LOCK =    1 << 7;
+        const SCROLL_LOCK = 1 << 8;
+
+        /// Either Shift key
+        const SHIFT = Self::LEFT_SHIFT.bits | Self::RIGHT_SHIFT.bits;
+        /// Either Ctrl key
+        const CTRL = Self::LEFT_CTRL.bits | Self::RIGHT_CTRL.bits;
+        /// Either Alt key
+        const ALT = Self::LEFT_ALT.bits | Self::RIGHT_ALT.bits;
+    }
+}
+
+impl Modifiers {
+    /// Returns `true` if any Shift key is held.
+    pub fn shift(&self) -> bool {
+        self.intersects(Modifiers::SHIFT)
+    }
+
+    /// Returns `true` if any Ctrl key is held.
+    pub fn ctrl(&self) -> bool {
+        self.intersects(Modifiers::CTRL)
+    }
+
+    /// Returns `true` if any Alt key is held.
+    pub fn alt(&self) -> bool {
+        self.intersects(Modifiers::ALT)
+    }
+
+    /// Returns modifier held while given key is down, if it is a modifier key.
+    pub fn held_by(key: Key) -> Option<Modifiers> {
+        match key {
+            Key::LeftShift => Some(Modifiers::LEFT_SHIFT),
+            Key::RightShift => Some(Modifiers::RIGHT_SHIFT),
+            Key::LeftCtrl => Some(Modifiers::LEFT_CTRL),
+            Key::RightCtrl => Some(Modifiers::RIGHT_CTRL),
+            Key::LeftAlt => Some(Modifiers::LEFT_ALT),
+            Key::RightAlt => Some(Modifiers::RIGHT_ALT),
+            _ => None,
+        }
+    }
+
+    /// Returns lock toggled by pressing given key, if it is a lock key.
+    pub fn toggled_by(key: Key) -> Option<Modifiers> {
+        match key {
+            Key::CapsLock => Some(Modifiers::CAPS_LOCK),
+            Key::NumLock => Some(Modifiers::NUM_LOCK),
+            Key::ScrollLock => Some(Modifiers::SCROLL_LOCK),
+            _ => None,
         }
     }
 }
+
+/// Key press or release, with modifier state after it has been applied
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct KeyEvent {
+    pub key: Key,
+    /// `false` if the key has been released, keys held down repeat press events
+    pub pressed: bool,
+    pub modifiers: Modifiers,
+    /// Character typed according to the active keymap, `None` for releases and keys which do
+    /// not type anything
+    pub char: Option<char>,
+}
@@ -2,28 +2,126 @@
 
 extern crate alloc;
 
+mod hotkeys;
+pub mod keymap;
+mod keymaps;
 mod keys;
 
-use alloc::VecDeque;
 use alloc::arc::Arc;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
 
-use spin::{Mutex, RwLock};
-use x86_64;
+use spin::Mutex;
 
 use dev::Driver;
 use dev::Device;
+use dev::input::{EventQueue, InputDevice, InputEventKind};
+use dev::interfaces::Interfaces;
+use kio::defer;
+use sync::IrqSpinLock;
 
 pub use self::keys::*;
 
+bitflags! {
+    /// Keyboard indicator lights
+    pub struct Leds: u8 {
+        const SCROLL_LOCK = 1 << 0;
+        const NUM_LOCK =    1 << 1;
+        const CAPS_LOCK =   1 << 2;
+    }
+}
+
+impl Leds {
+    /// Returns lights showing lock state of given modifiers.
+    pub fn from_modifiers(modifiers: Modifiers) -> Leds {
+        let mut leds = Leds::empty();
+        leds.set(Leds::SCROLL_LOCK, modifiers.contains(Modifiers::SCROLL_LOCK));
+        leds.set(Leds::NUM_LOCK, modifiers.contains(Modifiers::NUM_LOCK));
+        leds.set(Leds::CAPS_LOCK, modifiers.contains(Modifiers::CAPS_LOCK));
+        leds
+    }
+}
+
+/// Key repeat settings
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Typematic {
+    /// Milliseconds a key has to be held before it starts repeating
+    pub delay: u32,
+    /// Repeated presses per second
+    pub rate: u32,
+}
+
+/// Reason why keyboard setting could not be changed
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum KbdError {
+    /// The keyboard does not have such setting
+    NotSupported,
+    /// The keyboard cannot use requested value
+    InvalidArgument,
+    /// The keyboard has not accepted command
+    CommandFailed,
+}
+
+impl fmt::Display for KbdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KbdError::NotSupported => write!(f, "not supported"),
+            KbdError::InvalidArgument => write!(f, "invalid argument"),
+            KbdError::CommandFailed => write!(f, "command failed"),
+        }
+    }
+}
+
+/// Interface keyboard drivers provide, settings are not supported by default
+pub trait KbdDriver: Driver<KbdDriverApi> {
+    /// Returns indicator lights which are on.
+    fn leds(&self) -> Leds {
+        Leds::empty()
+    }
+
+    /// Turns indicator lights on and off. They follow lock keys, so the change lasts until
+    /// one of them gets pressed.
+    fn set_leds(&mut self, _leds: Leds) -> Result<(), KbdError> {
+        Err(KbdError::NotSupported)
+    }
+
+    /// Returns key repeat settings.
+    fn typematic(&self) -> Option<Typematic> {
+        None
+    }
+
+    /// Changes key repeat settings, returns the closest ones the keyboard supports, which
+    /// have been applied.
+    fn set_typematic(&mut self, _typematic: Typematic) -> Result<Typematic, KbdError> {
+        Err(KbdError::NotSupported)
+    }
+
+    /// Asks the keyboard which scancode set it uses.
+    fn scancode_set(&mut self) -> Result<u8, KbdError> {
+        Err(KbdError::NotSupported)
+    }
+
+    /// Makes the keyboard use given scancode set.
+    fn set_scancode_set(&mut self, _set: u8) -> Result<(), KbdError> {
+        Err(KbdError::NotSupported)
+    }
+
+    /// Returns number of key reports lost because they came faster than they could
+    /// be processed.
+    fn dropped_keys(&self) -> usize {
+        0
+    }
+}
+
 /// Keyboard device
 pub struct Kbd {
     inner: Arc<KbdInner>,
 }
 
 impl Kbd {
-    pub fn new(driver: &Mutex<Driver<KbdDriverApi>>) -> Kbd {
+    pub fn new(driver: &'static Mutex<KbdDriver>) -> Kbd {
         let kbd = Kbd {
-            inner: Arc::new(KbdInner::new()),
+            inner: Arc::new(KbdInner::new(driver)),
         };
 
         {
@@ -34,42 +132,169 @@ impl Kbd {
         kbd
     }
 
-    /// Synchronously waits for key input from keyboard.
-    pub fn wait(&self) -> KeyCode {
-        self.inner.wait()
+    /// Returns current state of modifier keys and locks.
+    pub fn modifiers(&self) -> Modifiers {
+        self.inner.state.lock().modifiers
+    }
+
+    /// Returns number of key reports dropped because they came faster than they could
+    /// be processed.
+    pub fn dropped_keys(&self) -> usize {
+        self.inner.driver.lock().dropped_keys()
+    }
+
+    /// Returns indicator lights which are on.
+    pub fn leds(&self) -> Leds {
+        self.inner.driver.lock().leds()
+    }
+
+    /// Turns indicator lights on and off, until next lock key press.
+    pub fn set_leds(&self, leds: Leds) -> Result<(), KbdError> {
+        self.inner.driver.lock().set_leds(leds)
+    }
+
+    /// Returns key repeat settings, if the keyboard has them.
+    pub fn typematic(&self) -> Option<Typematic> {
+        self.inner.driver.lock().typematic()
+    }
+
+    /// Changes key repeat settings, returns the closest ones supported, which have been applied.
+    pub fn set_typematic(&self, typematic: Typematic) -> Result<Typematic, KbdError> {
+        self.inner.driver.lock().set_typematic(typematic)
+    }
+
+    /// Returns scancode set the keyboard uses.
+    pub fn scancode_set(&self) -> Result<u8, KbdError> {
+        self.inner.driver.lock().scancode_set()
+    }
+
+    /// Makes the keyboard use given scancode set.
+    pub fn set_scancode_set(&self, set: u8) -> Result<(), KbdError> {
+        self.inner.driver.lock().set_scancode_set(set)
     }
 }
 
 impl Device for Kbd {
     const CLASS_NAME: &'static str = "kbd";
+
+    fn interfaces(interfaces: &mut Interfaces<Kbd>) {
+        interfaces.add::<InputDevice>(|kbd| kbd);
+    }
+
+    fn start(&self) {
+        self.inner.driver.lock().start();
+    }
+
+    fn stop(&self) {
+        self.inner.driver.lock().stop();
+    }
+
+    fn suspend(&self) {
+        self.inner.driver.lock().suspend();
+    }
+
+    fn resume(&self) {
+        self.inner.driver.lock().resume();
+    }
+}
+
+impl InputDevice for Kbd {
+    fn events(&self) -> &Arc<EventQueue> {
+        &self.inner.events
+    }
 }
 
 struct KbdInner {
-    buffer: RwLock<VecDeque<KeyCode>>,
+    driver: &'static Mutex<KbdDriver>,
+    /// Update of indicator lights has been scheduled, and has not started yet
+    leds_scheduled: AtomicBool,
+    events: Arc<EventQueue>,
+    state: IrqSpinLock<KbdState>,
+}
+
+struct KbdState {
+    modifiers: Modifiers,
+    /// Lock keys currently held down, so that key repeat does not toggle them again
+    held_locks: Modifiers,
+    /// Accent of dead key waiting for the next character
+    dead_key: Option<char>,
+    /// SysRq (Print Screen) is held, so that letters run debug hotkeys
+    sysrq: bool,
 }
 
 impl KbdInner {
-    fn new() -> KbdInner {
+    fn new(driver: &'static Mutex<KbdDriver>) -> KbdInner {
         KbdInner {
-            buffer: RwLock::new(VecDeque::new()),
+            driver,
+            leds_scheduled: AtomicBool::new(false),
+            events: Arc::new(EventQueue::new()),
+            state: IrqSpinLock::new(KbdState {
+                // Keypad is used for digits more often than for navigation
+                modifiers: Modifiers::NUM_LOCK,
+                held_locks: Modifiers::empty(),
+                dead_key: None,
+                sysrq: false,
+            }),
         }
     }
 
-    fn wait(&self) -> KeyCode {
-        while self.buffer.read().is_empty() {
-            unsafe {
-                x86_64::instructions::halt();
-            }
-        }
-        self.buffer.write().pop_front().unwrap()
+    /// Makes indicator lights show state of lock keys.
+    fn update_leds(&self) {
+        // Locks toggled from now on need another update
+        self.leds_scheduled.store(false, Ordering::Release);
+
+        let leds = Leds::from_modifiers(self.state.lock().modifiers);
+        let _ = self.driver.lock().set_leds(leds);
     }
 
-    fn push(&self, key: KeyCode) {
-        self.buffer.write().push_back(key);
+    /// Publishes event of a key, unless it is a hotkey. Returns `true` if it has toggled a lock.
+    fn process_key(&self, key: Key, pressed: bool) -> bool {
+        let mut toggled = false;
+        let (event, sysrq) = {
+            let mut state = self.state.lock();
+            if key == Key::PrintScreen {
+                state.sysrq = pressed;
+            }
+            if let Some(modifier) = Modifiers::held_by(key) {
+                state.modifiers.set(modifier, pressed);
+            }
+            if let Some(lock) = Modifiers::toggled_by(key) {
+                if pressed && !state.held_locks.contains(lock) {
+                    state.modifiers.toggle(lock);
+                    toggled = true;
+                }
+                state.held_locks.set(lock, pressed);
+            }
+
+            let modifiers = state.modifiers;
+            let char = if pressed {
+                keymap::translate(key, modifiers, &mut state.dead_key)
+            } else {
+                None
+            };
+
+            let event = KeyEvent {
+                key,
+                pressed,
+                modifiers,
+                char,
+            };
+            (event, state.sysrq)
+        };
+
+        if !hotkeys::handle(&event, sysrq) {
+            self.events.publish(InputEventKind::Key(event));
+        }
+        toggled
     }
 }
 
-unsafe impl Sync for KbdInner {}
+/// Deferred update of indicator lights, `arg` is a strong reference to `KbdInner` turned into
+/// a raw pointer.
+fn update_leds(arg: usize) {
+    let kbd = unsafe { Arc::from_raw(arg as *const KbdInner) };
+    kbd.update_leds();
+}
 
 /// API for keyboard drivers
 pub struct KbdDriverApi {
@@ -83,7 +308,30 @@ impl KbdDriverApi {
 }
 
 impl KbdDriverApi {
-    pub fn process_key(&mut self, key: KeyCode) {
-        self.kbd.push(key);
+    /// Reports key press or release, held keys are reported as repeated presses.
+    ///
+    /// The key event is published right away, so this must not be called from interrupt
+    /// handlers. Drivers receive bytes from hardware in deferred work, which is where they
+    /// report keys from. Indicator lights are updated by the device through
+    /// [`KbdDriver::set_leds`] when a lock key is pressed. That is deferred too, as the caller
+    /// holds the driver locked.
+    ///
+    /// [`KbdDriver::set_leds`]: ./trait.KbdDriver.html#method.set_leds
+    pub fn process_key(&mut self, key: Key, pressed: bool) {
+        let toggled = self.kbd.process_key(key, pressed);
+
+        if toggled && !self.kbd.leds_scheduled.swap(true, Ordering::AcqRel) {
+            let kbd = Arc::into_raw(self.kbd.clone()) as usize;
+            if !defer::schedule(update_leds, kbd) {
+                // Dropping the reference is fine, the driver holds another one
+                drop(unsafe { Arc::from_raw(kbd as *const KbdInner) });
+                self.kbd.leds_scheduled.store(false, Ordering::Release);
+            }
+        }
+    }
+
+    /// Returns current modifier state.
+    pub fn modifiers(&self) -> Modifiers {
+        self.kbd.state.lock().modifiers
     }
 }
@@ -1,20 +1,46 @@
 //! Device manager subsystem
+//!
+//! Manager keeps installed devices and registered drivers. Drivers of bus devices, like PCI
+//! functions or legacy ISA devices, declare which devices they handle with [`DeviceId`] match
+//! tables. Whenever a device is installed, drivers matching it are probed one by one until one
+//! of them takes it, and a newly registered driver is probed with matching devices which have
+//! no driver yet. When a device is uninstalled, the driver which has taken it is told to
+//! release it, so that the driver can be probed again later.
+//!
+//! Devices go through [`Device`] lifecycle hooks: `start` once installed, `suspend` and
+//! `resume` on request, and `stop` when uninstalled. Children are suspended and uninstalled
+//! before their parents, and resumed after them. Uninstalled device is only marked as removed,
+//! and holders of references to it are woken, it is dropped once the last reference goes away.
+//!
+//! Devices are looked up by name, by type with [`devices_of`], or by interface they expose
+//! with [`implementing`], e.g. all devices which are [`InputDevice`]s.
+//!
+//! [`DeviceId`]: ./enum.DeviceId.html
+//! [`Device`]: ../trait.Device.html
+//! [`devices_of`]: ./fn.devices_of.html
+//! [`implementing`]: ./fn.implementing.html
+//! [`InputDevice`]: ../input/trait.InputDevice.html
 
 use alloc::{BTreeMap, String, Vec};
 use alloc::boxed::Box;
 use alloc::arc::Arc;
-use core::mem;
+use core::any::Any;
+use core::fmt;
 
 use hashmap_core::HashMap;
 use lazy_static;
-use spin::RwLock;
+use spin::Mutex;
 
 use dev::Device;
+use dev::interfaces::InterfaceTable;
+use dev::isa::IsaDevice;
+use dev::pci::PciDevice;
+use sync::{IrqSpinLock, WaitQueue};
 
 pub type DeviceName = String;
 
 lazy_static! {
-    static ref INSTANCE: RwLock<DeviceManager> = RwLock::new(DeviceManager::new());
+    static ref INSTANCE: IrqSpinLock<DeviceManager> = IrqSpinLock::new(DeviceManager::new());
 }
 
 pub fn init() {
@@ -26,17 +52,245 @@ pub fn install<D: Device>(device: Box<D>) -> DeviceName {
     do_install(CommonDevice::new(device))
 }
 
+/// Registers new device as a child of another one, e.g. keyboard plugged into PS/2 controller.
+pub fn install_child<D: Device>(parent: &str, device: Box<D>) -> DeviceName {
+    let mut device = CommonDevice::new(device);
+    device.parent = Some(DeviceName::from(parent));
+    do_install(device)
+}
+
 fn do_install(device: CommonDevice) -> DeviceName {
-    INSTANCE.write().install(device)
+    let (device, drivers) = {
+        let mut manager = INSTANCE.lock();
+        let device = manager.install(device);
+        (device, manager.drivers.clone())
+    };
+
+    device.dev.start();
+    bind(&device, &drivers);
+    device.name()
+}
+
+/// Uninstalls device together with its children, which go first, and runs their `stop` hooks.
+/// Holders of references to the devices are notified, see [`CommonDevice::is_removed`].
+/// Returns `false` if there is no such device.
+///
+/// [`CommonDevice::is_removed`]: ./struct.CommonDevice.html#method.is_removed
+pub fn uninstall(name: &str) -> bool {
+    if get_device(name).is_none() {
+        return false;
+    }
+
+    for child in children(name) {
+        uninstall(&child.name());
+    }
+
+    // Device could have been uninstalled meanwhile
+    let device = INSTANCE.lock().remove(name);
+    match device {
+        Some(device) => {
+            let driver = *device.driver.lock();
+            if let Some(driver) = driver {
+                (driver.remove)(&device);
+            }
+            device.remove();
+            println!("dev::mgr: disconnected device {}", name);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Suspends device together with its children, which go first. Returns `false` if there is no
+/// such device.
+pub fn suspend(name: &str) -> bool {
+    let device = match get_device(name) {
+        Some(device) => device,
+        None => return false,
+    };
+
+    for child in children(name) {
+        suspend(&child.name());
+    }
+
+    if device.transition(DeviceState::Running, DeviceState::Suspended) {
+        device.dev.suspend();
+    }
+    true
+}
+
+/// Resumes device together with its children, which go after it. Returns `false` if there is
+/// no such device.
+pub fn resume(name: &str) -> bool {
+    let device = match get_device(name) {
+        Some(device) => device,
+        None => return false,
+    };
+
+    if device.transition(DeviceState::Suspended, DeviceState::Running) {
+        device.dev.resume();
+    }
+
+    for child in children(name) {
+        resume(&child.name());
+    }
+    true
+}
+
+/// Devices a driver handles
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DeviceId {
+    /// PCI function with given vendor and device identifiers
+    Pci { vendor: u16, device: u16 },
+    /// PCI function of given class and subclass, and programming interface if it is given
+    PciClass {
+        class: u8,
+        subclass: u8,
+        prog_if: Option<u8>,
+    },
+    /// Legacy device with given PnP identifier, which ACPI uses as hardware ID too,
+    /// e.g. `PNP0501` for 16550 UART
+    Isa(&'static str),
+}
+
+impl DeviceId {
+    pub fn matches(&self, device: &CommonDevice) -> bool {
+        match *self {
+            DeviceId::Pci {
+                vendor,
+                device: device_id,
+            } => device
+                .try_downcast::<PciDevice>()
+                .map_or(false, |pci| pci.vendor_id == vendor && pci.device_id == device_id),
+            DeviceId::PciClass {
+                class,
+                subclass,
+                prog_if,
+            } => device.try_downcast::<PciDevice>().map_or(false, |pci| {
+                pci.class == class && pci.subclass == subclass
+                    && prog_if.map_or(true, |prog_if| pci.prog_if == prog_if)
+            }),
+            DeviceId::Isa(id) => device
+                .try_downcast::<IsaDevice>()
+                .map_or(false, |isa| isa.id == id),
+        }
+    }
+}
+
+/// Reason why driver has not taken a device
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ProbeError {
+    /// Device is not there, e.g. legacy device which does not respond
+    NotPresent,
+    /// Device has matched, but the driver cannot handle it after all
+    NotSupported,
+    /// Device is there, but could not be initialized
+    Failed(String),
+}
+
+impl fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProbeError::NotPresent => write!(f, "device not present"),
+            ProbeError::NotSupported => write!(f, "device not supported"),
+            ProbeError::Failed(ref reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// Driver of bus devices
+pub struct DriverInfo {
+    pub name: &'static str,
+    /// Devices the driver handles
+    pub ids: &'static [DeviceId],
+    /// Initializes matching device and installs devices it provides as its children.
+    pub probe: fn(&CommonDevice) -> Result<(), ProbeError>,
+    /// Releases device the driver has taken, when it is uninstalled. Children have been
+    /// uninstalled already. Hardware should be left quiet, and the driver ready to be probed
+    /// again.
+    pub remove: fn(&CommonDevice),
+}
+
+impl DriverInfo {
+    pub fn matches(&self, device: &CommonDevice) -> bool {
+        self.ids.iter().any(|id| id.matches(device))
+    }
+}
+
+/// Registers driver, and binds it to matching devices which have no driver yet. Devices
+/// installed later are matched against it too.
+pub fn register_driver(driver: &'static DriverInfo) {
+    let devices = {
+        let mut manager = INSTANCE.lock();
+        manager.drivers.push(driver);
+        manager.all()
+    };
+
+    for device in devices {
+        bind(&device, &[driver]);
+    }
+}
+
+/// Probes drivers matching the device in turn, until one of them takes it.
+fn bind(device: &CommonDevice, drivers: &[&'static DriverInfo]) {
+    let mut bound = device.driver.lock();
+    if bound.is_some() {
+        return;
+    }
+
+    for driver in drivers.iter().filter(|driver| driver.matches(device)) {
+        match (driver.probe)(device) {
+            Ok(()) => {
+                println!("dev::mgr: bound {} to {}", device.name(), driver.name);
+                *bound = Some(*driver);
+                return;
+            }
+            Err(ProbeError::NotPresent) => {}
+            Err(error) => println!("{}: {}: {}", driver.name, device.name(), error),
+        }
+    }
 }
 
 /// Returns already cloned shared reference to device.
 pub fn get_device(name: &str) -> Option<Arc<CommonDevice>> {
-    INSTANCE.read().get(name).map(Arc::clone)
+    INSTANCE.lock().get(name).map(Arc::clone)
 }
 
 pub fn all() -> Vec<Arc<CommonDevice>> {
-    INSTANCE.read().all()
+    INSTANCE.lock().all()
+}
+
+/// Returns devices of type `D`.
+pub fn devices_of<D: Device>() -> Vec<Arc<CommonDevice>> {
+    INSTANCE
+        .lock()
+        .all()
+        .into_iter()
+        .filter(|device| device.is::<D>())
+        .collect()
+}
+
+/// Returns devices exposing interface `I`, e.g. `implementing::<InputDevice>()`.
+///
+/// Interfaces exposed behind a lock are found by either form, e.g. screens are returned both
+/// by `implementing::<TextVideo>()` and `implementing::<SharedTextVideo>()`.
+pub fn implementing<I: ?Sized + 'static>() -> Vec<Arc<CommonDevice>> {
+    INSTANCE
+        .lock()
+        .all()
+        .into_iter()
+        .filter(|device| device.implements::<I>())
+        .collect()
+}
+
+/// Returns devices installed as children of given device.
+pub fn children(parent: &str) -> Vec<Arc<CommonDevice>> {
+    INSTANCE
+        .lock()
+        .all()
+        .into_iter()
+        .filter(|device| device.parent() == Some(parent))
+        .collect()
 }
 
 pub fn parse_device_name(name: &str) -> Option<(&str, usize)> {
@@ -53,20 +307,74 @@ pub fn parse_device_name(name: &str) -> Option<(&str, usize)> {
     None
 }
 
+/// Object safe part of `Device`, lets devices of any class be dropped and run their hooks.
+trait DeviceObject: Send + Sync {
+    fn as_any(&self) -> &Any;
+    fn start(&self);
+    fn stop(&self);
+    fn suspend(&self);
+    fn resume(&self);
+}
+
+impl<D: Device> DeviceObject for D {
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn start(&self) {
+        Device::start(self);
+    }
+
+    fn stop(&self) {
+        Device::stop(self);
+    }
+
+    fn suspend(&self) {
+        Device::suspend(self);
+    }
+
+    fn resume(&self) {
+        Device::resume(self);
+    }
+}
+
+/// Stage of device lifecycle
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DeviceState {
+    Running,
+    Suspended,
+    /// Device has been uninstalled, it only lives until references to it are dropped
+    Removed,
+}
+
 /// Proxy for common fields of device structures.
 pub struct CommonDevice {
     class: &'static str,
     id: usize,
-    dev: Box<u8>,
+    parent: Option<DeviceName>,
+    /// Driver bound to the device by manager
+    driver: Mutex<Option<&'static DriverInfo>>,
+    state: Mutex<DeviceState>,
+    /// Threads and tasks waiting for the device to be uninstalled
+    removal_waiters: WaitQueue,
+    interfaces: InterfaceTable,
+    dev: Box<DeviceObject>,
 }
 
 impl CommonDevice {
     fn new<D: Device>(dev: Box<D>) -> CommonDevice {
         let class = D::CLASS_NAME;
         let id = usize::max_value();
-        // FIXME: Handle Drop
-        let dev: Box<u8> = unsafe { mem::transmute(dev) };
-        CommonDevice { class, id, dev }
+        CommonDevice {
+            class,
+            id,
+            parent: None,
+            driver: Mutex::new(None),
+            state: Mutex::new(DeviceState::Running),
+            removal_waiters: WaitQueue::new(),
+            interfaces: InterfaceTable::of::<D>(),
+            dev,
+        }
     }
 
     pub fn downcast<D: Device>(&self) -> &D {
@@ -81,11 +389,22 @@ impl CommonDevice {
     }
 
     pub fn try_downcast<D: Device>(&self) -> Option<&D> {
-        if self.class != D::CLASS_NAME {
-            return None;
-        }
-        let dev: &D = unsafe { mem::transmute(&*self.dev) };
-        Some(&dev)
+        self.dev.as_any().downcast_ref()
+    }
+
+    /// Returns `true` if the device is of type `D`.
+    pub fn is<D: Device>(&self) -> bool {
+        self.dev.as_any().is::<D>()
+    }
+
+    /// Returns the device as interface `I`, if it exposes it.
+    pub fn interface<I: ?Sized + 'static>(&self) -> Option<&I> {
+        self.interfaces.cast(self.dev.as_any())
+    }
+
+    /// Returns `true` if the device exposes interface `I`.
+    pub fn implements<I: ?Sized + 'static>(&self) -> bool {
+        self.interfaces.contains::<I>()
     }
 
     pub fn class(&self) -> &'static str {
@@ -99,20 +418,78 @@ impl CommonDevice {
     pub fn name(&self) -> DeviceName {
         format!("{}{}", self.class, self.id)
     }
+
+    /// Returns name of device this one is connected to, if any.
+    pub fn parent(&self) -> Option<&str> {
+        self.parent.as_ref().map(|name| name.as_str())
+    }
+
+    /// Returns name of driver bound to the device, if any. Devices installed by their
+    /// drivers directly, like keyboards, have none.
+    pub fn driver(&self) -> Option<&'static str> {
+        self.driver.lock().map(|driver| driver.name)
+    }
+
+    pub fn state(&self) -> DeviceState {
+        *self.state.lock()
+    }
+
+    /// Returns `true` if the device has been uninstalled. Holders of references to it should
+    /// drop them, so that it can be dropped too.
+    pub fn is_removed(&self) -> bool {
+        self.state() == DeviceState::Removed
+    }
+
+    /// Returns queue of threads and tasks waiting for the device to be uninstalled, all of
+    /// them are woken when it happens.
+    pub fn removal_waiters(&self) -> &WaitQueue {
+        &self.removal_waiters
+    }
+
+    /// Blocks until the device is uninstalled.
+    pub fn wait_removed(&self) {
+        self.removal_waiters.wait_until(|| self.is_removed());
+    }
+
+    /// Changes state from `from` to `to`, returns `false` if the device is in another state.
+    fn transition(&self, from: DeviceState, to: DeviceState) -> bool {
+        let mut state = self.state.lock();
+        if *state != from {
+            return false;
+        }
+        *state = to;
+        true
+    }
+
+    /// Marks the device as removed, stops it and notifies holders of references.
+    fn remove(&self) {
+        {
+            let mut state = self.state.lock();
+            if *state == DeviceState::Removed {
+                return;
+            }
+            *state = DeviceState::Removed;
+        }
+
+        self.dev.stop();
+        self.removal_waiters.wake_all();
+    }
 }
 
 struct DeviceManager {
     classes: HashMap<&'static str, DeviceClassEntry>,
+    drivers: Vec<&'static DriverInfo>,
 }
 
 impl DeviceManager {
     fn new() -> DeviceManager {
         DeviceManager {
             classes: HashMap::new(),
+            drivers: Vec::new(),
         }
     }
 
-    fn install(&mut self, device: CommonDevice) -> DeviceName {
+    fn install(&mut self, device: CommonDevice) -> Arc<CommonDevice> {
         self.classes
             .entry(device.class())
             .or_insert_with(|| DeviceClassEntry::new())
@@ -120,7 +497,7 @@ impl DeviceManager {
     }
 
     fn get(&self, name: &str) -> Option<&Arc<CommonDevice>> {
-        let (class, id) = parse_device_name(&name).expect("expected valid device name");
+        let (class, id) = parse_device_name(&name)?;
 
         if let Some(devs) = self.classes.get(class) {
             devs.get(id)
@@ -129,6 +506,11 @@ impl DeviceManager {
         }
     }
 
+    fn remove(&mut self, name: &str) -> Option<Arc<CommonDevice>> {
+        let (class, id) = parse_device_name(&name)?;
+        self.classes.get_mut(class)?.devices.remove(&id)
+    }
+
     fn all(&self) -> Vec<Arc<CommonDevice>> {
         self.classes
             .values()
@@ -151,7 +533,7 @@ impl DeviceClassEntry {
         }
     }
 
-    fn install(&mut self, device: CommonDevice) -> DeviceName {
+    fn install(&mut self, device: CommonDevice) -> Arc<CommonDevice> {
         let id = self.last_id;
         self.last_id += 1;
 
@@ -160,12 +542,16 @@ impl DeviceClassEntry {
 
         let dev_name = dev.name();
 
-        let r = self.devices.insert(id, Arc::new(dev));
-        assert!(r.is_none());
+        match dev.parent() {
+            Some(parent) => println!("dev::mgr: connected device {} to {}", dev_name, parent),
+            None => println!("dev::mgr: connected device {}", dev_name),
+        }
 
-        println!("dev::mgr: connected device {}", dev_name);
+        let dev = Arc::new(dev);
+        let r = self.devices.insert(id, Arc::clone(&dev));
+        assert!(r.is_none());
 
-        dev_name
+        dev
     }
 
     fn get(&self, id: usize) -> Option<&Arc<CommonDevice>> {
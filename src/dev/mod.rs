@@ -2,15 +2,69 @@
 
 pub mod mgr;
 
+pub mod console;
+pub mod input;
+pub mod interfaces;
+pub mod isa;
 pub mod kbd;
+pub mod mouse;
 pub mod output_serial;
+pub mod pci;
+pub mod ps2;
 pub mod text_video;
+pub mod tty;
+
+use core::any::Any;
+
+use self::interfaces::Interfaces;
 
 /// Devices are required to be externally immutable.
-pub trait Device: Send + Sync {
+///
+/// Besides its own type, device may expose interfaces shared by devices of different types,
+/// see [`interfaces`]. Lifecycle hooks are called by device manager, devices usually pass them
+/// to their drivers. They do nothing by default.
+///
+/// [`interfaces`]: ./interfaces/index.html
+pub trait Device: Any + Send + Sync {
+    /// Name of device class, which device names are made of, e.g. `kbd` for `kbd0`
     const CLASS_NAME: &'static str;
+
+    /// Adds interfaces the device exposes to `interfaces`. There are none by default.
+    fn interfaces(_interfaces: &mut Interfaces<Self>)
+    where
+        Self: Sized,
+    {
+    }
+
+    /// Called once the device is installed and named.
+    fn start(&self) {}
+
+    /// Called when the device is uninstalled, after its children. Hardware should be left
+    /// quiet, the device is dropped once the last reference to it goes away.
+    fn stop(&self) {}
+
+    /// Called when the device is suspended, after its children.
+    fn suspend(&self) {}
+
+    /// Called when the device is resumed, before its children.
+    fn resume(&self) {}
 }
 
+/// Driver behind a device abstraction. Lifecycle hooks other than `init` do nothing by
+/// default.
 pub trait Driver<D>: Send + Sync {
+    /// Connects the driver to the device, which it controls through `api`.
     fn init(&mut self, api: D);
+
+    /// Called once the device is installed.
+    fn start(&mut self) {}
+
+    /// Called when the device is uninstalled. Driver should silence hardware and drop `api`.
+    fn stop(&mut self) {}
+
+    /// Called when the device is suspended, hardware should stop reporting events.
+    fn suspend(&mut self) {}
+
+    /// Called when the device is resumed.
+    fn resume(&mut self) {}
 }
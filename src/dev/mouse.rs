@@ -0,0 +1,148 @@
+//! Universal mouse device abstraction
+//!
+//! Mouse drivers report state of the device: relative motion, wheel movement and buttons
+//! held. Mouse device turns it into [`MouseEvent`]s, one for motion, wheel, and every button
+//! pressed or released, and publishes them to its event queue.
+//!
+//! [`MouseEvent`]: ./enum.MouseEvent.html
+
+use alloc::arc::Arc;
+
+use spin::Mutex;
+
+use dev::{Device, Driver};
+use dev::input::{EventQueue, InputDevice, InputEventKind};
+use dev::interfaces::Interfaces;
+use sync::IrqSpinLock;
+
+bitflags! {
+    /// Mouse buttons
+    pub struct MouseButtons: u8 {
+        const LEFT =   1 << 0;
+        const RIGHT =  1 << 1;
+        const MIDDLE = 1 << 2;
+    }
+}
+
+/// Change of mouse state
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MouseEvent {
+    /// Mouse has moved, `dx` grows to the right and `dy` downwards
+    Motion { dx: i32, dy: i32 },
+    /// Wheel has been turned, positive values are towards the user
+    Wheel(i32),
+    /// Button has been pressed or released
+    Button { button: MouseButtons, pressed: bool },
+}
+
+/// Mouse device
+pub struct Mouse {
+    driver: &'static Mutex<Driver<MouseDriverApi>>,
+    inner: Arc<MouseInner>,
+}
+
+impl Mouse {
+    pub fn new(driver: &'static Mutex<Driver<MouseDriverApi>>) -> Mouse {
+        let mouse = Mouse {
+            driver,
+            inner: Arc::new(MouseInner::new()),
+        };
+
+        {
+            let api = MouseDriverApi::new(&mouse.inner);
+            driver.lock().init(api);
+        }
+
+        mouse
+    }
+
+    /// Returns buttons currently held.
+    pub fn buttons(&self) -> MouseButtons {
+        *self.inner.buttons.lock()
+    }
+}
+
+impl Device for Mouse {
+    const CLASS_NAME: &'static str = "mouse";
+
+    fn interfaces(interfaces: &mut Interfaces<Mouse>) {
+        interfaces.add::<InputDevice>(|mouse| mouse);
+    }
+
+    fn start(&self) {
+        self.driver.lock().start();
+    }
+
+    fn stop(&self) {
+        self.driver.lock().stop();
+    }
+
+    fn suspend(&self) {
+        self.driver.lock().suspend();
+    }
+
+    fn resume(&self) {
+        self.driver.lock().resume();
+    }
+}
+
+impl InputDevice for Mouse {
+    fn events(&self) -> &Arc<EventQueue> {
+        &self.inner.events
+    }
+}
+
+struct MouseInner {
+    events: Arc<EventQueue>,
+    buttons: IrqSpinLock<MouseButtons>,
+}
+
+impl MouseInner {
+    fn new() -> MouseInner {
+        MouseInner {
+            events: Arc::new(EventQueue::new()),
+            buttons: IrqSpinLock::new(MouseButtons::empty()),
+        }
+    }
+
+    fn process_state(&self, dx: i32, dy: i32, wheel: i32, buttons: MouseButtons) {
+        let publish = |event| self.events.publish(InputEventKind::Mouse(event));
+
+        if dx != 0 || dy != 0 {
+            publish(MouseEvent::Motion { dx, dy });
+        }
+        if wheel != 0 {
+            publish(MouseEvent::Wheel(wheel));
+        }
+
+        let mut held = self.buttons.lock();
+        let changed = *held ^ buttons;
+        for &button in [MouseButtons::LEFT, MouseButtons::RIGHT, MouseButtons::MIDDLE].iter() {
+            if changed.contains(button) {
+                publish(MouseEvent::Button {
+                    button,
+                    pressed: buttons.contains(button),
+                });
+            }
+        }
+        *held = buttons;
+    }
+}
+
+/// API for mouse drivers
+pub struct MouseDriverApi {
+    mouse: Arc<MouseInner>,
+}
+
+impl MouseDriverApi {
+    fn new(mouse: &Arc<MouseInner>) -> MouseDriverApi {
+        MouseDriverApi {
+            mouse: mouse.clone(),
+        }
+    }
+
+    /// Reports motion since the last report, and buttons held now.
+    pub fn process_state(&mut self, dx: i32, dy: i32, wheel: i32, buttons: MouseButtons) {
+        self.mouse.process_state(dx, dy, wheel, buttons);
+    }
+}
@@ -1,5 +1,16 @@
 use core::fmt;
 
+use sync::IrqSpinLock;
+
+/// Output serial shared between threads, the form in which devices expose it
+///
+/// Devices exposing it are found by `implementing::<OutputSerial>()` too, but they are cast
+/// into `SharedOutputSerial` only. Locked trait object is written to with
+/// [`OutputSerialWriter::new`].
+///
+/// [`OutputSerialWriter::new`]: ./struct.OutputSerialWriter.html#method.new
+pub type SharedOutputSerial = IrqSpinLock<OutputSerial + Send>;
+
 /// Common interface for output serials for messaging text
 pub trait OutputSerial {
     /// Print single ASCII character
@@ -19,9 +30,14 @@ pub trait OutputSerial {
     }
 
     /// Returns wrapper that implements `fmt::Write` trait
+    ///
+    /// Only available for concrete types, use [`OutputSerialWriter::new`] for trait objects.
+    ///
+    /// [`OutputSerialWriter::new`]: ./struct.OutputSerialWriter.html#method.new
     fn writer<'a, 'b>(&'a mut self) -> OutputSerialWriter<'b, Self>
     where
         'a: 'b,
+        Self: Sized,
     {
         OutputSerialWriter(self)
     }
@@ -31,9 +47,20 @@ pub struct OutputSerialWriter<'a, T>(&'a mut T)
 where
     T: 'a + OutputSerial + ?Sized;
 
+impl<'a, T> OutputSerialWriter<'a, T>
+where
+    T: 'a + OutputSerial + ?Sized,
+{
+    /// Wraps output serial of any type, including trait objects like locked
+    /// `SharedOutputSerial`.
+    pub fn new(serial: &'a mut T) -> OutputSerialWriter<'a, T> {
+        OutputSerialWriter(serial)
+    }
+}
+
 impl<'a, T> fmt::Write for OutputSerialWriter<'a, T>
 where
-    T: 'a + OutputSerial,
+    T: 'a + OutputSerial + ?Sized,
 {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         self.0.put_str(s);
@@ -0,0 +1,400 @@
+//! PCI device abstraction
+//!
+//! Every function found on PCI buses is installed as a separate `pci` device, which describes
+//! it as decoded from its configuration space header: identifiers, class, base address
+//! registers, interrupt and capabilities. Drivers use [`PciDevice::read_config`] and
+//! [`PciDevice::write_config`] to access the rest of configuration space.
+//!
+//! [`PciDevice::read_config`]: ./struct.PciDevice.html#method.read_config
+//! [`PciDevice::write_config`]: ./struct.PciDevice.html#method.write_config
+
+use alloc::Vec;
+use core::fmt;
+
+use dev::Device;
+
+/// Vendor identifier read from function which does not exist
+pub const NO_VENDOR: u16 = 0xffff;
+
+const REG_ID: u16 = 0x00;
+const REG_COMMAND_STATUS: u16 = 0x04;
+const REG_CLASS: u16 = 0x08;
+const REG_HEADER: u16 = 0x0c;
+const REG_BAR0: u16 = 0x10;
+const REG_BUSES: u16 = 0x18;
+const REG_CAPABILITIES: u16 = 0x34;
+const REG_INTERRUPT: u16 = 0x3c;
+
+const COMMAND_IO_SPACE: u32 = 1 << 0;
+const COMMAND_MEMORY_SPACE: u32 = 1 << 1;
+/// Status bit telling that capability list is present, in upper half of the register
+const STATUS_CAPABILITIES: u32 = 1 << (16 + 4);
+
+const HEADER_TYPE_MASK: u8 = 0x7f;
+const HEADER_MULTIFUNCTION: u8 = 0x80;
+
+const BAR_IO: u32 = 1 << 0;
+const BAR_64_BIT: u32 = 0b10 << 1;
+const BAR_PREFETCHABLE: u32 = 1 << 3;
+
+/// Upper bound of capability list length, in case it loops
+const MAX_CAPABILITIES: usize = 48;
+
+/// Location of PCI function
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct PciAddress {
+    /// Segment group, there is more than one only on large machines with ECAM
+    pub segment: u16,
+    pub bus: u8,
+    /// Device on the bus, 0-31
+    pub device: u8,
+    /// Function of the device, 0-7
+    pub function: u8,
+}
+
+impl fmt::Display for PciAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04x}:{:02x}:{:02x}.{}",
+            self.segment, self.bus, self.device, self.function
+        )
+    }
+}
+
+/// Way of accessing configuration space of PCI functions, provided by bus drivers
+pub trait PciConfigAccess: Send + Sync {
+    /// Reads 32 bit register at given offset, which has to be aligned to 4 bytes.
+    fn read(&self, address: PciAddress, offset: u16) -> u32;
+
+    /// Writes 32 bit register at given offset, which has to be aligned to 4 bytes.
+    fn write(&self, address: PciAddress, offset: u16, value: u32);
+
+    /// Returns size of configuration space of every function, 256 or 4096 bytes.
+    fn config_size(&self) -> u16;
+}
+
+/// Region decoded by base address register
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Bar {
+    /// Memory mapped registers or memory
+    Memory {
+        address: u64,
+        size: u64,
+        prefetchable: bool,
+        /// The register takes two slots, as address is 64 bit wide
+        wide: bool,
+    },
+    /// I/O ports
+    Io { port: u32, size: u32 },
+}
+
+/// Interrupt pin used by PCI function
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InterruptPin {
+    IntA,
+    IntB,
+    IntC,
+    IntD,
+}
+
+impl fmt::Display for InterruptPin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let letter = match *self {
+            InterruptPin::IntA => 'A',
+            InterruptPin::IntB => 'B',
+            InterruptPin::IntC => 'C',
+            InterruptPin::IntD => 'D',
+        };
+        write!(f, "INT{}", letter)
+    }
+}
+
+/// Entry of capability list
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Capability {
+    /// Capability identifier, e.g. `0x05` for MSI
+    pub id: u8,
+    /// Offset of capability structure in configuration space
+    pub offset: u8,
+}
+
+impl Capability {
+    /// Returns name of the capability, if it is a known one.
+    pub fn name(&self) -> Option<&'static str> {
+        let name = match self.id {
+            0x01 => "Power Management",
+            0x05 => "MSI",
+            0x09 => "Vendor Specific",
+            0x0d => "PCI Bridge Subsystem Vendor ID",
+            0x10 => "PCI Express",
+            0x11 => "MSI-X",
+            0x12 => "SATA",
+            0x13 => "Advanced Features",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+/// Layout of configuration space header
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum HeaderType {
+    /// Ordinary function, with six base address registers
+    General,
+    /// PCI-to-PCI bridge, with two base address registers and secondary bus
+    Bridge { secondary_bus: u8 },
+    /// PCI-to-CardBus bridge
+    CardBus,
+}
+
+/// PCI function
+pub struct PciDevice {
+    address: PciAddress,
+    access: &'static PciConfigAccess,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+    pub revision: u8,
+    pub header_type: HeaderType,
+    /// Base address registers, indexed by register number. Registers which are not
+    /// implemented, and upper halves of 64 bit ones, are `None`.
+    pub bars: [Option<Bar>; 6],
+    /// Line of interrupt controller the function is routed to, as set up by firmware
+    pub interrupt_line: u8,
+    pub interrupt_pin: Option<InterruptPin>,
+    pub capabilities: Vec<Capability>,
+}
+
+impl PciDevice {
+    /// Creates device of function at given address, decoding its configuration space header.
+    ///
+    /// Sizes of regions are probed by writing base address registers, so memory and I/O
+    /// decoding of the function is turned off for a moment. Host bridges and VGA devices
+    /// are left decoding, as system memory or the console text buffer is behind them.
+    pub fn new(address: PciAddress, access: &'static PciConfigAccess) -> PciDevice {
+        let read = |offset| access.read(address, offset);
+
+        let id = read(REG_ID);
+        let class = read(REG_CLASS);
+        let header = header_type(access, address);
+        let interrupt = read(REG_INTERRUPT);
+
+        let mut device = PciDevice {
+            address,
+            access,
+            vendor_id: id as u16,
+            device_id: (id >> 16) as u16,
+            class: (class >> 24) as u8,
+            subclass: (class >> 16) as u8,
+            prog_if: (class >> 8) as u8,
+            revision: class as u8,
+            header_type: header,
+            bars: [None; 6],
+            interrupt_line: interrupt as u8,
+            interrupt_pin: match (interrupt >> 8) as u8 {
+                1 => Some(InterruptPin::IntA),
+                2 => Some(InterruptPin::IntB),
+                3 => Some(InterruptPin::IntC),
+                4 => Some(InterruptPin::IntD),
+                _ => None,
+            },
+            capabilities: Vec::new(),
+        };
+
+        let bar_count = match header {
+            HeaderType::General => 6,
+            HeaderType::Bridge { .. } => 2,
+            HeaderType::CardBus => 0,
+        };
+        device.probe_bars(bar_count);
+        device.read_capabilities();
+        device
+    }
+
+    /// Decodes base address registers and probes sizes of their regions.
+    fn probe_bars(&mut self, count: usize) {
+        let command = self.read_config(REG_COMMAND_STATUS);
+        let keep_decoding = self.is_always_decoding();
+        if !keep_decoding {
+            self.write_config(
+                REG_COMMAND_STATUS,
+                command & !(COMMAND_IO_SPACE | COMMAND_MEMORY_SPACE) & 0xffff,
+            );
+        }
+
+        let mut index = 0;
+        while index < count {
+            let offset = REG_BAR0 + index as u16 * 4;
+            let value = self.read_config(offset);
+            // Writing ones returns mask of address bits, the lowest one set is region size
+            let mask = self.probe_register(offset, value);
+
+            if value & BAR_IO != 0 {
+                let mask = mask & !0b11 & 0xffff;
+                if mask != 0 {
+                    self.bars[index] = Some(Bar::Io {
+                        port: value & !0b11,
+                        size: mask & mask.wrapping_neg(),
+                    });
+                }
+                index += 1;
+                continue;
+            }
+
+            let wide = value & BAR_64_BIT != 0 && index + 1 < count;
+            let mut address = (value & !0xf) as u64;
+            let mut mask = (mask & !0xf) as u64;
+            if wide {
+                let high_offset = offset + 4;
+                let high = self.read_config(high_offset);
+                address |= (high as u64) << 32;
+                mask |= (self.probe_register(high_offset, high) as u64) << 32;
+            }
+
+            if mask != 0 {
+                self.bars[index] = Some(Bar::Memory {
+                    address,
+                    size: mask & mask.wrapping_neg(),
+                    prefetchable: value & BAR_PREFETCHABLE != 0,
+                    wide,
+                });
+            }
+            index += if wide { 2 } else { 1 };
+        }
+
+        if !keep_decoding {
+            self.write_config(REG_COMMAND_STATUS, command & 0xffff);
+        }
+    }
+
+    /// Returns `true` if memory and I/O decoding of the function must never be turned off:
+    /// host bridges may cut off system memory, and VGA devices the legacy text buffer.
+    fn is_always_decoding(&self) -> bool {
+        match (self.class, self.subclass) {
+            // Host bridge
+            (0x06, 0x00) => true,
+            // VGA compatible controller, or VGA compatible device from before class codes
+            (0x03, 0x00) => self.prog_if == 0x00,
+            (0x00, 0x01) => true,
+            _ => false,
+        }
+    }
+
+    /// Writes all ones to a register and returns what has been read back, then restores it.
+    fn probe_register(&self, offset: u16, value: u32) -> u32 {
+        self.write_config(offset, 0xffff_ffff);
+        let mask = self.read_config(offset);
+        self.write_config(offset, value);
+        mask
+    }
+
+    fn read_capabilities(&mut self) {
+        if self.read_config(REG_COMMAND_STATUS) & STATUS_CAPABILITIES == 0 {
+            return;
+        }
+
+        let mut offset = self.read_config(REG_CAPABILITIES) as u8 & !0b11;
+        while offset != 0 && self.capabilities.len() < MAX_CAPABILITIES {
+            let header = self.read_config(offset as u16);
+            self.capabilities.push(Capability {
+                id: header as u8,
+                offset,
+            });
+            offset = (header >> 8) as u8 & !0b11;
+        }
+    }
+
+    pub fn address(&self) -> PciAddress {
+        self.address
+    }
+
+    /// Reads 32 bit configuration register at given offset, aligned to 4 bytes.
+    pub fn read_config(&self, offset: u16) -> u32 {
+        self.access.read(self.address, offset)
+    }
+
+    /// Writes 32 bit configuration register at given offset, aligned to 4 bytes.
+    pub fn write_config(&self, offset: u16, value: u32) {
+        self.access.write(self.address, offset, value)
+    }
+
+    /// Returns size of configuration space, 4096 bytes if extended space is accessible.
+    pub fn config_size(&self) -> u16 {
+        self.access.config_size()
+    }
+
+    /// Returns human readable name of device class.
+    pub fn class_name(&self) -> &'static str {
+        class_name(self.class, self.subclass)
+    }
+}
+
+impl Device for PciDevice {
+    const CLASS_NAME: &'static str = "pci";
+}
+
+/// Returns vendor identifier of function at given address, or [`NO_VENDOR`] if there is
+/// no such function.
+///
+/// [`NO_VENDOR`]: ./constant.NO_VENDOR.html
+pub fn vendor_id(access: &PciConfigAccess, address: PciAddress) -> u16 {
+    access.read(address, REG_ID) as u16
+}
+
+/// Returns layout of configuration space header of function at given address.
+pub fn header_type(access: &PciConfigAccess, address: PciAddress) -> HeaderType {
+    let header = (access.read(address, REG_HEADER) >> 16) as u8;
+    match header & HEADER_TYPE_MASK {
+        0x01 => HeaderType::Bridge {
+            secondary_bus: (access.read(address, REG_BUSES) >> 8) as u8,
+        },
+        0x02 => HeaderType::CardBus,
+        _ => HeaderType::General,
+    }
+}
+
+/// Returns `true` if device at given address, which has to be function 0, has more functions.
+pub fn is_multifunction(access: &PciConfigAccess, address: PciAddress) -> bool {
+    (access.read(address, REG_HEADER) >> 16) as u8 & HEADER_MULTIFUNCTION != 0
+}
+
+/// Returns human readable name of PCI class and subclass.
+pub fn class_name(class: u8, subclass: u8) -> &'static str {
+    match (class, subclass) {
+        (0x00, 0x01) => "VGA compatible unclassified device",
+        (0x00, _) => "Unclassified device",
+        (0x01, 0x00) => "SCSI storage controller",
+        (0x01, 0x01) => "IDE interface",
+        (0x01, 0x05) => "ATA controller",
+        (0x01, 0x06) => "SATA controller",
+        (0x01, 0x08) => "Non-Volatile memory controller",
+        (0x01, _) => "Mass storage controller",
+        (0x02, 0x00) => "Ethernet controller",
+        (0x02, _) => "Network controller",
+        (0x03, 0x00) => "VGA compatible controller",
+        (0x03, _) => "Display controller",
+        (0x04, 0x01) => "Multimedia audio controller",
+        (0x04, 0x03) => "Audio device",
+        (0x04, _) => "Multimedia controller",
+        (0x05, _) => "Memory controller",
+        (0x06, 0x00) => "Host bridge",
+        (0x06, 0x01) => "ISA bridge",
+        (0x06, 0x04) => "PCI bridge",
+        (0x06, _) => "Bridge",
+        (0x07, 0x00) => "Serial controller",
+        (0x07, _) => "Communication controller",
+        (0x08, _) => "System peripheral",
+        (0x09, _) => "Input device controller",
+        (0x0c, 0x03) => "USB controller",
+        (0x0c, 0x05) => "SMBus",
+        (0x0c, _) => "Serial bus controller",
+        (0x0d, _) => "Wireless controller",
+        (0x10, _) => "Encryption controller",
+        (0x11, _) => "Signal processing controller",
+        (0xff, _) => "Unassigned class",
+        _ => "Unknown class",
+    }
+}
@@ -0,0 +1,168 @@
+//! PS/2 controller device abstraction
+//!
+//! PS/2 controller has up to two ports, the first one usually has a keyboard plugged in and
+//! the second one (auxiliary) a mouse. Drivers of these devices talk to them through
+//! [`Ps2Channel`], which sends commands and waits for acknowledgement, resending commands
+//! the device asks for again. Bytes devices send on their own are passed to receiver function
+//! attached to the port.
+//!
+//! [`Ps2Channel`]: ./struct.Ps2Channel.html
+
+use core::fmt;
+
+use dev::Device;
+
+/// Device acknowledged a command
+pub const ACK: u8 = 0xfa;
+/// Device asks for the last byte again
+pub const RESEND: u8 = 0xfe;
+
+/// Port of PS/2 controller
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Ps2Port {
+    /// Keyboard port
+    First,
+    /// Auxiliary port, used by mice
+    Second,
+}
+
+impl Ps2Port {
+    /// Returns index of the port, 0 or 1.
+    pub fn index(&self) -> usize {
+        match *self {
+            Ps2Port::First => 0,
+            Ps2Port::Second => 1,
+        }
+    }
+}
+
+impl fmt::Display for Ps2Port {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "port {}", self.index() + 1)
+    }
+}
+
+/// Reason why PS/2 controller or command has failed
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Ps2Error {
+    /// Controller or device has not responded in time
+    Timeout,
+    /// Device has kept asking to resend a command byte
+    TooManyResends,
+    /// Device has answered command byte with something else than acknowledgement
+    UnexpectedResponse(u8),
+    /// Controller self-test has returned given value instead of `0x55`
+    SelfTestFailed(u8),
+    /// Port interface test has returned given error code
+    PortTestFailed(u8),
+    /// There is no working port
+    NoSuchPort,
+    /// The port does not support requested operation
+    NotSupported,
+}
+
+impl fmt::Display for Ps2Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Ps2Error::Timeout => write!(f, "timeout"),
+            Ps2Error::TooManyResends => write!(f, "too many resend requests"),
+            Ps2Error::UnexpectedResponse(byte) => write!(f, "unexpected response {:#04x}", byte),
+            Ps2Error::SelfTestFailed(byte) => write!(f, "self-test failed ({:#04x})", byte),
+            Ps2Error::PortTestFailed(code) => write!(f, "port test failed ({:#04x})", code),
+            Ps2Error::NoSuchPort => write!(f, "no such port"),
+            Ps2Error::NotSupported => write!(f, "not supported"),
+        }
+    }
+}
+
+/// Interface PS/2 controller drivers provide
+pub trait Ps2ControllerDriver: Send + Sync {
+    /// Returns `true` if given port exists and has passed the interface test.
+    fn has_port(&self, port: Ps2Port) -> bool;
+
+    /// Sends command bytes to device on given port, waiting for acknowledgement of each of
+    /// them, and then reads `response.len()` bytes of response.
+    fn command(&self, port: Ps2Port, bytes: &[u8], response: &mut [u8]) -> Result<(), Ps2Error>;
+
+    /// Makes bytes received from device on given port, other than command responses, to be
+    /// passed to `receiver`. Receiver is called outside of interrupt context.
+    fn attach(&self, port: Ps2Port, receiver: fn(u8));
+
+    /// Turns translation of scancodes received on given port to set 1 on or off.
+    fn set_translation(&self, port: Ps2Port, enabled: bool) -> Result<(), Ps2Error>;
+
+    /// Returns number of bytes received on given port which have been dropped, because they
+    /// came faster than they could be passed to receiver.
+    fn dropped(&self, port: Ps2Port) -> usize;
+}
+
+/// PS/2 controller device
+pub struct Ps2Controller {
+    driver: &'static Ps2ControllerDriver,
+}
+
+impl Ps2Controller {
+    pub fn new(driver: &'static Ps2ControllerDriver) -> Ps2Controller {
+        Ps2Controller { driver }
+    }
+
+    /// Returns `true` if given port exists and works.
+    pub fn has_port(&self, port: Ps2Port) -> bool {
+        self.driver.has_port(port)
+    }
+
+    /// Returns channel to device on given port, if the port works.
+    pub fn channel(&self, port: Ps2Port) -> Option<Ps2Channel> {
+        if self.driver.has_port(port) {
+            Some(Ps2Channel {
+                driver: self.driver,
+                port,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl Device for Ps2Controller {
+    const CLASS_NAME: &'static str = "ps2ctl";
+}
+
+/// Command channel to device plugged into PS/2 port
+#[derive(Copy, Clone)]
+pub struct Ps2Channel {
+    driver: &'static Ps2ControllerDriver,
+    port: Ps2Port,
+}
+
+impl Ps2Channel {
+    pub fn port(&self) -> Ps2Port {
+        self.port
+    }
+
+    /// Sends command bytes, each of them has to be acknowledged by the device.
+    pub fn command(&self, bytes: &[u8]) -> Result<(), Ps2Error> {
+        self.driver.command(self.port, bytes, &mut [])
+    }
+
+    /// Sends command bytes and reads response of given length.
+    pub fn query(&self, bytes: &[u8], response: &mut [u8]) -> Result<(), Ps2Error> {
+        self.driver.command(self.port, bytes, response)
+    }
+
+    /// Makes bytes sent by the device on its own to be passed to `receiver`.
+    pub fn attach(&self, receiver: fn(u8)) {
+        self.driver.attach(self.port, receiver);
+    }
+
+    /// Turns translation of scancodes sent by the device to set 1 on or off.
+    pub fn set_translation(&self, enabled: bool) -> Result<(), Ps2Error> {
+        self.driver.set_translation(self.port, enabled)
+    }
+
+    /// Returns number of bytes sent by the device which have been dropped before they got
+    /// to receiver.
+    pub fn dropped(&self) -> usize {
+        self.driver.dropped(self.port)
+    }
+}
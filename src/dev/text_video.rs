@@ -1,4 +1,13 @@
-use super::output_serial::OutputSerial;
+use super::Device;
+use super::interfaces::Interfaces;
+use super::output_serial::{OutputSerial, SharedOutputSerial};
+use sync::IrqSpinLock;
+
+/// Text video shared between threads, the form in which devices expose it
+///
+/// Devices exposing it are found by `implementing::<TextVideo>()` too, but they are cast
+/// into `SharedTextVideo` only.
+pub type SharedTextVideo = IrqSpinLock<TextVideo + Send>;
 
 /// Represents single text color
 #[allow(dead_code)]
@@ -87,3 +96,36 @@ pub trait TextVideo: OutputSerial {
     /// Clears buffer with current style
     fn clear(&mut self);
 }
+
+/// Screen showing text
+///
+/// Exposes its text video both as [`SharedTextVideo`] and as [`SharedOutputSerial`].
+///
+/// [`SharedTextVideo`]: ./type.SharedTextVideo.html
+/// [`SharedOutputSerial`]: ../output_serial/type.SharedOutputSerial.html
+pub struct TextScreen {
+    video: &'static SharedTextVideo,
+    output: &'static SharedOutputSerial,
+}
+
+impl TextScreen {
+    pub fn new<T: TextVideo + Send + 'static>(video: &'static IrqSpinLock<T>) -> TextScreen {
+        TextScreen {
+            video,
+            output: video,
+        }
+    }
+
+    pub fn video(&self) -> &SharedTextVideo {
+        self.video
+    }
+}
+
+impl Device for TextScreen {
+    const CLASS_NAME: &'static str = "screen";
+
+    fn interfaces(interfaces: &mut Interfaces<TextScreen>) {
+        interfaces.add_locked::<TextVideo, SharedTextVideo>(|screen| screen.video);
+        interfaces.add_locked::<OutputSerial, SharedOutputSerial>(|screen| screen.output);
+    }
+}
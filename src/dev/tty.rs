@@ -0,0 +1,143 @@
+//! Serial terminal device abstraction
+
+use alloc::arc::Arc;
+use core::fmt;
+
+use dev::Device;
+use dev::input::{EventQueue, InputDevice, InputEventKind};
+use dev::interfaces::Interfaces;
+use dev::output_serial::{OutputSerial, SharedOutputSerial};
+
+/// Reason why line settings could not be changed
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TtyError {
+    /// The hardware can not run at requested baud rate
+    UnsupportedBaudRate(u32),
+}
+
+impl fmt::Display for TtyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TtyError::UnsupportedBaudRate(baud) => write!(f, "unsupported baud rate {}", baud),
+        }
+    }
+}
+
+/// Interface serial line drivers provide to terminal devices
+pub trait TtyDriver: Send + Sync {
+    /// Transmits bytes, blocking until all of them are handed over to hardware.
+    fn write(&self, bytes: &[u8]);
+
+    /// Returns current line speed in bits per second.
+    fn baud_rate(&self) -> u32;
+
+    /// Changes line speed.
+    fn set_baud_rate(&self, baud: u32) -> Result<(), TtyError>;
+
+    /// Connects the driver to the device, received bytes are passed to `api`.
+    fn attach(&self, api: TtyDriverApi);
+
+    /// Returns the line as output serial, for code printing text through `OutputSerial`.
+    fn output(&self) -> &SharedOutputSerial;
+
+    /// Called once the device is installed.
+    fn start(&self) {}
+
+    /// Called when the device is uninstalled, the driver should stop receiving and drop `api`.
+    fn stop(&self) {}
+
+    /// Called when the device is suspended, the driver should stop receiving.
+    fn suspend(&self) {}
+
+    /// Called when the device is resumed.
+    fn resume(&self) {}
+}
+
+/// Serial terminal device
+pub struct Tty {
+    driver: &'static TtyDriver,
+    inner: Arc<TtyInner>,
+}
+
+impl Tty {
+    pub fn new(driver: &'static TtyDriver) -> Tty {
+        let inner = Arc::new(TtyInner::new());
+        driver.attach(TtyDriverApi::new(&inner));
+
+        Tty { driver, inner }
+    }
+
+    /// Writes raw bytes to the line.
+    pub fn write(&self, bytes: &[u8]) {
+        self.driver.write(bytes);
+    }
+
+    /// Returns current line speed in bits per second.
+    pub fn baud_rate(&self) -> u32 {
+        self.driver.baud_rate()
+    }
+
+    /// Changes line speed.
+    pub fn set_baud_rate(&self, baud: u32) -> Result<(), TtyError> {
+        self.driver.set_baud_rate(baud)
+    }
+}
+
+impl Device for Tty {
+    const CLASS_NAME: &'static str = "tty";
+
+    fn interfaces(interfaces: &mut Interfaces<Tty>) {
+        interfaces.add::<InputDevice>(|tty| tty);
+        interfaces.add_locked::<OutputSerial, SharedOutputSerial>(|tty| tty.driver.output());
+    }
+
+    fn start(&self) {
+        self.driver.start();
+    }
+
+    fn stop(&self) {
+        self.driver.stop();
+    }
+
+    fn suspend(&self) {
+        self.driver.suspend();
+    }
+
+    fn resume(&self) {
+        self.driver.resume();
+    }
+}
+
+impl InputDevice for Tty {
+    fn events(&self) -> &Arc<EventQueue> {
+        &self.inner.events
+    }
+}
+
+struct TtyInner {
+    events: Arc<EventQueue>,
+}
+
+impl TtyInner {
+    fn new() -> TtyInner {
+        TtyInner {
+            events: Arc::new(EventQueue::new()),
+        }
+    }
+}
+
+/// API for serial line drivers
+pub struct TtyDriverApi {
+    tty: Arc<TtyInner>,
+}
+
+impl TtyDriverApi {
+    fn new(tty: &Arc<TtyInner>) -> TtyDriverApi {
+        TtyDriverApi { tty: tty.clone() }
+    }
+
+    /// Stores byte received from the line.
+    pub fn receive(&mut self, byte: u8) {
+        self.tty.events.publish(InputEventKind::Byte(byte));
+    }
+}
@@ -0,0 +1,100 @@
+//! ACPI table discovery
+//!
+//! Firmware describes hardware which cannot be detected otherwise in ACPI tables. Root System
+//! Description Pointer is found in BIOS read-only memory area, it points to RSDT (or XSDT since
+//! ACPI 2.0), which in turn lists all other tables. Tables are identity mapped when they are
+//! looked up, and stay mapped.
+
+use core::slice;
+
+use mem;
+use mem::paging::EntryFlags;
+
+/// BIOS read-only memory area, searched for RSDP
+const BIOS_AREA_START: usize = 0xe0000;
+const BIOS_AREA_END: usize = 0x100000;
+
+const RSDP_SIGNATURE: &[u8] = b"RSD PTR ";
+/// Size of RSDP of ACPI 1.0, later revisions extend it
+const RSDP_V1_SIZE: usize = 20;
+const RSDP_V2_SIZE: usize = 36;
+
+/// Size of header common to all description tables
+pub const SDT_HEADER_SIZE: usize = 36;
+
+/// Returns description table with given signature, e.g. `b"MCFG"`, including its header.
+///
+/// Returns `None` if there is no such table, or ACPI is not supported.
+pub fn find_table(signature: &[u8; 4]) -> Option<&'static [u8]> {
+    let (root, entry_size) = match find_rsdp()? {
+        Root::Rsdt(address) => (map_table(address)?, 4),
+        Root::Xsdt(address) => (map_table(address)?, 8),
+    };
+
+    root[SDT_HEADER_SIZE..]
+        .chunks(entry_size)
+        .filter(|entry| entry.len() == entry_size)
+        .map(|entry| read_le(entry, 0, entry_size) as usize)
+        .filter_map(map_table)
+        .find(|table| &table[..4] == signature)
+}
+
+/// Reads little endian number of `size` bytes at `offset`.
+pub fn read_le(bytes: &[u8], offset: usize, size: usize) -> u64 {
+    bytes[offset..offset + size]
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| value << 8 | byte as u64)
+}
+
+enum Root {
+    Rsdt(usize),
+    Xsdt(usize),
+}
+
+fn find_rsdp() -> Option<Root> {
+    let area_size = BIOS_AREA_END - BIOS_AREA_START;
+    let start = mem::map_physical(BIOS_AREA_START, area_size, EntryFlags::NO_EXECUTE)?;
+    let area = unsafe { slice::from_raw_parts(start as *const u8, area_size) };
+
+    // RSDP is aligned to 16 bytes
+    let rsdp = (0..area_size / 16)
+        .map(|index| &area[index * 16..])
+        .find(|rsdp| {
+            rsdp.len() >= RSDP_V1_SIZE && rsdp.starts_with(RSDP_SIGNATURE)
+                && checksum_valid(&rsdp[..RSDP_V1_SIZE])
+        })?;
+
+    let revision = rsdp[15];
+    if revision >= 2 && rsdp.len() >= RSDP_V2_SIZE && checksum_valid(&rsdp[..RSDP_V2_SIZE]) {
+        let xsdt = read_le(rsdp, 24, 8) as usize;
+        if xsdt != 0 {
+            return Some(Root::Xsdt(xsdt));
+        }
+    }
+    Some(Root::Rsdt(read_le(rsdp, 16, 4) as usize))
+}
+
+/// Maps table at given physical address, and returns it if its checksum is valid.
+fn map_table(address: usize) -> Option<&'static [u8]> {
+    let header = mem::map_physical(address, SDT_HEADER_SIZE, EntryFlags::NO_EXECUTE)?;
+    let header = unsafe { slice::from_raw_parts(header as *const u8, SDT_HEADER_SIZE) };
+
+    let length = read_le(header, 4, 4) as usize;
+    if length < SDT_HEADER_SIZE {
+        return None;
+    }
+
+    let table = mem::map_physical(address, length, EntryFlags::NO_EXECUTE)?;
+    let table = unsafe { slice::from_raw_parts(table as *const u8, length) };
+    if checksum_valid(table) {
+        Some(table)
+    } else {
+        None
+    }
+}
+
+/// Checks that all bytes of a structure sum up to zero.
+fn checksum_valid(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) == 0
+}
@@ -1 +1,46 @@
+//! VGA driver
+//!
+//! Only text mode is supported. Kernel console uses the text buffer since early boot, the
+//! driver is bound to VGA compatible PCI function (class `03:00`, programming interface `00`)
+//! to install it as `screen` device too.
+
 pub mod text_buffer;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use dev;
+use dev::mgr::{CommonDevice, DeviceId, DriverInfo, ProbeError};
+use dev::text_video::TextScreen;
+
+use self::text_buffer::VGA_TEXT_VIDEO;
+
+pub static DRIVER: DriverInfo = DriverInfo {
+    name: "vga",
+    ids: &[
+        DeviceId::PciClass {
+            class: 0x03,
+            subclass: 0x00,
+            prog_if: Some(0x00),
+        },
+    ],
+    probe,
+    remove,
+};
+
+/// Set while the text buffer is installed
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+fn probe(device: &CommonDevice) -> Result<(), ProbeError> {
+    // There is a single legacy text buffer, even with more adapters
+    if INSTALLED.swap(true, Ordering::AcqRel) {
+        return Err(ProbeError::NotSupported);
+    }
+
+    dev::mgr::install_child(&device.name(), box TextScreen::new(&VGA_TEXT_VIDEO));
+    Ok(())
+}
+
+fn remove(_device: &CommonDevice) {
+    // The screen child has been uninstalled, another adapter may install the buffer now
+    INSTALLED.store(false, Ordering::Release);
+}
@@ -3,12 +3,12 @@
 use core::mem;
 use core::ptr::Unique;
 
-use spin::Mutex;
 use volatile::Volatile;
 
 use dev::output_serial::OutputSerial;
 use dev::text_video::{Cursor, TextColor, TextStyle, TextVideo};
 use kio::port::Port;
+use sync::IrqSpinLock;
 
 pub const VGA_TEXT_BUFFER_ADDR: usize = 0xb8000;
 
@@ -166,6 +166,11 @@ impl VgaTextVideo {
     fn raw_put_byte(&mut self, char: u8) {
         if char == b'\n' {
             self.newline();
+        } else if char == b'\x08' {
+            // Backspace only moves the cursor, like on terminals
+            if self.cursor.col > 0 {
+                self.cursor.col -= 1;
+            }
         } else {
             let screen_char = ScreenChar {
                 char,
@@ -311,7 +316,7 @@ impl TextVideo for VgaTextVideo {
 }
 
 /// The instance of VGA text buffer
-pub static VGA_TEXT_VIDEO: Mutex<VgaTextVideo> = Mutex::new(VgaTextVideo {
+pub static VGA_TEXT_VIDEO: IrqSpinLock<VgaTextVideo> = IrqSpinLock::new(VgaTextVideo {
     current_style: Style::new(Color::LightGray, Color::Black),
     cursor: Cursor::zero(),
     buffer: unsafe { Unique::new_unchecked(VGA_TEXT_BUFFER_ADDR as *mut _) },
@@ -1,68 +1,206 @@
 //! Keyboard driver for PCs and ATs
+//!
+//! Keyboard is plugged into the first port of PS/2 controller. It uses scancode set 2, which
+//! the controller translates to set 1. Set 1 can be selected too, translation is then turned
+//! off. Either way the driver decodes set 1.
 
 use spin::Mutex;
-use x86_64::structures::idt::ExceptionStackFrame;
 
-use kio::idt::register_interrupt;
-use kio::pic;
-use kio::port::UnsafePort;
 use dev::{self, Driver};
-use dev::kbd::{Kbd, KbdDriverApi, KeyCode};
+use dev::kbd::{Kbd, KbdDriver, KbdDriverApi, KbdError, Leds, Typematic};
+use dev::ps2::{Ps2Channel, Ps2Error};
+use drv::hid::scancode::Set1Decoder;
 
-const IRQ: u8 = 33;
+/// Sets indicator lights, followed by `Leds` bits
+const CMD_SET_LEDS: u8 = 0xed;
+/// Gets or sets scancode set, followed by 0 to get, or set number
+const CMD_SCANCODE_SET: u8 = 0xf0;
+/// Sets repeat rate and delay, followed by their encoding
+const CMD_SET_TYPEMATIC: u8 = 0xf3;
+/// Makes keyboard send scancodes, it may be disabled after controller initialization
+const CMD_ENABLE_SCANNING: u8 = 0xf4;
+/// Makes keyboard stop sending scancodes
+const CMD_DISABLE_SCANNING: u8 = 0xf5;
 
-const DATA_PORT: UnsafePort<u8> = unsafe { UnsafePort::new(0x60) };
+/// Repeat settings keyboards start with
+const DEFAULT_TYPEMATIC: Typematic = Typematic {
+    delay: 500,
+    rate: 11,
+};
 
 static ATKBD: Mutex<AtkbdDriver> = Mutex::new(AtkbdDriver::uninitialized());
 
-pub fn init() {
-    ATKBD.lock().start();
-    dev::mgr::install(box Kbd::new(&ATKBD));
+/// Installs keyboard connected to PS/2 controller device `controller` through `channel`.
+pub fn init(controller: &str, channel: Ps2Channel) {
+    ATKBD.lock().connect(channel);
+    dev::mgr::install_child(controller, box Kbd::new(&ATKBD));
 }
 
 pub struct AtkbdDriver {
     kbd: Option<KbdDriverApi>,
+    channel: Option<Ps2Channel>,
+    decoder: Set1Decoder,
+    /// Bits of `Leds` which are on
+    leds: u8,
+    typematic: Typematic,
 }
 
 impl AtkbdDriver {
     const fn uninitialized() -> AtkbdDriver {
-        AtkbdDriver { kbd: None }
+        AtkbdDriver {
+            kbd: None,
+            channel: None,
+            decoder: Set1Decoder::new(),
+            leds: 0,
+            typematic: DEFAULT_TYPEMATIC,
+        }
+    }
+
+    fn connect(&mut self, channel: Ps2Channel) {
+        channel.attach(process_scancode);
+        self.channel = Some(channel);
+        self.set_scanning(true);
     }
 
-    fn start(&mut self) {
-        unsafe {
-            register_interrupt(IRQ, handle_irq);
-            pic::enable(IRQ);
+    fn set_scanning(&self, enabled: bool) {
+        let (command, action) = if enabled {
+            (CMD_ENABLE_SCANNING, "enable")
+        } else {
+            (CMD_DISABLE_SCANNING, "disable")
+        };
+        if let Err(error) = self.command(&[command], &mut []) {
+            println!("atkbd: cannot {} scanning: {}", action, error);
         }
     }
 
     fn process_scancode(&mut self, scancode: u8) {
-        let key = parse_scancode(scancode);
-        if let Some(ref mut kbd) = self.kbd {
-            kbd.process_key(key);
+        if let Some((key, pressed)) = self.decoder.decode(scancode) {
+            if let Some(ref mut kbd) = self.kbd {
+                kbd.process_key(key, pressed);
+            }
         }
     }
+
+    fn command(&self, bytes: &[u8], response: &mut [u8]) -> Result<(), KbdError> {
+        let channel = self.channel.as_ref().ok_or(KbdError::NotSupported)?;
+        channel.query(bytes, response).map_err(command_failed)
+    }
 }
 
 impl Driver<KbdDriverApi> for AtkbdDriver {
     fn init(&mut self, api: KbdDriverApi) {
+        let leds = Leds::from_modifiers(api.modifiers());
         self.kbd = Some(api);
+        let _ = self.set_leds(leds);
+    }
+
+    fn stop(&mut self) {
+        self.set_scanning(false);
+        self.kbd = None;
+        // Keys held now will not be released
+        self.decoder = Set1Decoder::new();
+    }
+
+    fn suspend(&mut self) {
+        self.set_scanning(false);
+    }
+
+    fn resume(&mut self) {
+        self.set_scanning(true);
     }
 }
 
-fn process_scancode(scancode: u8) {
-    let mut atkbd = ATKBD.lock();
-    atkbd.process_scancode(scancode);
+impl KbdDriver for AtkbdDriver {
+    fn leds(&self) -> Leds {
+        Leds::from_bits_truncate(self.leds)
+    }
+
+    fn set_leds(&mut self, leds: Leds) -> Result<(), KbdError> {
+        self.command(&[CMD_SET_LEDS, leds.bits()], &mut [])?;
+        self.leds = leds.bits();
+        Ok(())
+    }
+
+    fn typematic(&self) -> Option<Typematic> {
+        Some(self.typematic)
+    }
+
+    fn set_typematic(&mut self, typematic: Typematic) -> Result<Typematic, KbdError> {
+        let (code, applied) = encode_typematic(typematic);
+        self.command(&[CMD_SET_TYPEMATIC, code], &mut [])?;
+        self.typematic = applied;
+        Ok(applied)
+    }
+
+    fn scancode_set(&mut self) -> Result<u8, KbdError> {
+        let mut response = [0];
+        self.command(&[CMD_SCANCODE_SET, 0], &mut response)?;
+
+        // Response is translated like scancodes, if translation is on
+        match response[0] {
+            1 | 0x43 => Ok(1),
+            2 | 0x41 => Ok(2),
+            3 | 0x3f => Ok(3),
+            other => Err(command_failed(Ps2Error::UnexpectedResponse(other))),
+        }
+    }
+
+    fn set_scancode_set(&mut self, set: u8) -> Result<(), KbdError> {
+        // Set 2 is decoded after translation to set 1, set 3 cannot be decoded
+        let translation = match set {
+            1 => false,
+            2 => true,
+            3 => return Err(KbdError::NotSupported),
+            _ => return Err(KbdError::InvalidArgument),
+        };
+
+        self.command(&[CMD_SCANCODE_SET, set], &mut [])?;
+        let channel = self.channel.as_ref().ok_or(KbdError::NotSupported)?;
+        channel.set_translation(translation).map_err(command_failed)?;
+
+        // Partial scancode received before the switch means nothing now
+        self.decoder = Set1Decoder::new();
+        Ok(())
+    }
+
+    fn dropped_keys(&self) -> usize {
+        // Every scancode byte dropped by the controller loses a key report
+        self.channel.as_ref().map_or(0, |channel| channel.dropped())
+    }
 }
 
-fn parse_scancode(scancode: u8) -> KeyCode {
-    KeyCode(scancode)
+/// Returns the closest repeat settings keyboard supports, encoded and decoded.
+///
+/// Delay is 250, 500, 750 or 1000 milliseconds. Repeat period is `(8 + A) * 2^B * 4.17`
+/// milliseconds, where A and B are 3 and 2 bit numbers, so rates range from 2 to 30 per second.
+fn encode_typematic(typematic: Typematic) -> (u8, Typematic) {
+    let delay_code = (typematic.delay.max(250).min(1000) + 125) / 250 - 1;
+
+    let requested = typematic.rate as u64 * 1000;
+    let (rate_code, rate) = (0..32u64)
+        .map(|code| {
+            let period_us = (8 + (code & 0b111)) * (1 << (code >> 3)) * 4167;
+            // Repeats per 1000 seconds
+            (code, 1_000_000_000 / period_us)
+        })
+        .min_by_key(|&(_, rate)| (rate as i64 - requested as i64).abs())
+        .unwrap();
+
+    let code = (delay_code as u8) << 5 | rate_code as u8;
+    let applied = Typematic {
+        delay: (delay_code + 1) * 250,
+        rate: ((rate + 500) / 1000) as u32,
+    };
+    (code, applied)
 }
 
-extern "x86-interrupt" fn handle_irq(_stack_frame: &mut ExceptionStackFrame) {
-    let scancode = unsafe { DATA_PORT.read() };
-    process_scancode(scancode);
-    unsafe {
-        pic::eoi(IRQ);
-    }
+fn command_failed(error: Ps2Error) -> KbdError {
+    println!("atkbd: {}", error);
+    KbdError::CommandFailed
+}
+
+/// Receives bytes sent by keyboard, called outside of interrupt context.
+fn process_scancode(scancode: u8) {
+    let mut atkbd = ATKBD.lock();
+    atkbd.process_scancode(scancode);
 }
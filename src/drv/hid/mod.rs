@@ -1 +1,3 @@
 pub mod atkbd;
+pub mod psmouse;
+pub mod scancode;
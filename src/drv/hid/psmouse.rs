@@ -0,0 +1,180 @@
+//! PS/2 mouse driver
+//!
+//! Mouse is plugged into the second (auxiliary) port of PS/2 controller and raises IRQ12.
+//! Standard mice send 3 byte packets with buttons and motion. IntelliMouse compatible ones
+//! switch to 4 byte packets with wheel movement after a magic sequence of sample rates.
+
+use spin::Mutex;
+
+use dev::{self, Driver};
+use dev::mouse::{Mouse, MouseButtons, MouseDriverApi};
+use dev::ps2::{Ps2Channel, Ps2Error};
+
+/// Sets sample rate, followed by number of samples per second
+const CMD_SET_SAMPLE_RATE: u8 = 0xf3;
+/// Returns device identifier
+const CMD_GET_ID: u8 = 0xf2;
+/// Starts sending packets
+const CMD_ENABLE_REPORTING: u8 = 0xf4;
+/// Stops sending packets
+const CMD_DISABLE_REPORTING: u8 = 0xf5;
+/// Restores default settings and disables reporting
+const CMD_SET_DEFAULTS: u8 = 0xf6;
+
+/// Identifier of standard mouse
+const ID_STANDARD: u8 = 0x00;
+/// Identifier of mouse with wheel
+const ID_INTELLIMOUSE: u8 = 0x03;
+/// Identifier of mouse with wheel and 5 buttons, it sends wheel like IntelliMouse
+const ID_INTELLIMOUSE_EXPLORER: u8 = 0x04;
+
+/// Sample rates which switch IntelliMouse to 4 byte packets
+const INTELLIMOUSE_SEQUENCE: [u8; 3] = [200, 100, 80];
+/// Samples per second mouse is left with
+const SAMPLE_RATE: u8 = 100;
+
+/// Bit always set in the first byte of a packet
+const PACKET_ALWAYS_ONE: u8 = 1 << 3;
+const PACKET_X_SIGN: u8 = 1 << 4;
+const PACKET_Y_SIGN: u8 = 1 << 5;
+const PACKET_X_OVERFLOW: u8 = 1 << 6;
+const PACKET_Y_OVERFLOW: u8 = 1 << 7;
+const PACKET_BUTTONS: u8 = 0b111;
+
+static PSMOUSE: Mutex<PsMouseDriver> = Mutex::new(PsMouseDriver::uninitialized());
+
+/// Detects mouse connected to PS/2 controller device `controller` through `channel`, and
+/// installs it.
+pub fn init(controller: &str, channel: Ps2Channel) {
+    if let Err(error) = PSMOUSE.lock().connect(channel) {
+        println!("psmouse: {}", error);
+        return;
+    }
+    dev::mgr::install_child(controller, box Mouse::new(&PSMOUSE));
+}
+
+pub struct PsMouseDriver {
+    mouse: Option<MouseDriverApi>,
+    channel: Option<Ps2Channel>,
+    /// Number of bytes in a packet, 3 or 4 with wheel
+    packet_size: usize,
+    packet: [u8; 4],
+    /// Number of bytes of the current packet received so far
+    received: usize,
+}
+
+impl PsMouseDriver {
+    const fn uninitialized() -> PsMouseDriver {
+        PsMouseDriver {
+            mouse: None,
+            channel: None,
+            packet_size: 3,
+            packet: [0; 4],
+            received: 0,
+        }
+    }
+
+    fn connect(&mut self, channel: Ps2Channel) -> Result<(), Ps2Error> {
+        channel.command(&[CMD_SET_DEFAULTS])?;
+
+        for &rate in INTELLIMOUSE_SEQUENCE.iter() {
+            channel.command(&[CMD_SET_SAMPLE_RATE, rate])?;
+        }
+        let mut id = [0];
+        channel.query(&[CMD_GET_ID], &mut id)?;
+
+        self.packet_size = match id[0] {
+            ID_STANDARD => 3,
+            ID_INTELLIMOUSE | ID_INTELLIMOUSE_EXPLORER => 4,
+            // Most likely a keyboard
+            other => return Err(Ps2Error::UnexpectedResponse(other)),
+        };
+
+        channel.command(&[CMD_SET_SAMPLE_RATE, SAMPLE_RATE])?;
+        channel.attach(process_byte);
+        self.channel = Some(channel);
+        channel.command(&[CMD_ENABLE_REPORTING])
+    }
+
+    fn set_reporting(&mut self, enabled: bool) {
+        let (command, action) = if enabled {
+            (CMD_ENABLE_REPORTING, "enable")
+        } else {
+            (CMD_DISABLE_REPORTING, "disable")
+        };
+        if let Some(channel) = self.channel {
+            if let Err(error) = channel.command(&[command]) {
+                println!("psmouse: cannot {} reporting: {}", action, error);
+            }
+        }
+        // Bytes of interrupted packet are not coming
+        self.received = 0;
+    }
+
+    fn process_byte(&mut self, byte: u8) {
+        // The first byte is recognized by the bit which is always set, so that a lost byte
+        // does not shift all following packets
+        if self.received == 0 && byte & PACKET_ALWAYS_ONE == 0 {
+            return;
+        }
+
+        self.packet[self.received] = byte;
+        self.received += 1;
+        if self.received == self.packet_size {
+            self.received = 0;
+            self.process_packet();
+        }
+    }
+
+    fn process_packet(&mut self) {
+        let flags = self.packet[0];
+
+        // Motion is 9 bit two's complement, with sign in the first byte. Overflowed motion is
+        // garbage, but buttons in the same packet are still valid.
+        let (dx, dy) = if flags & (PACKET_X_OVERFLOW | PACKET_Y_OVERFLOW) != 0 {
+            (0, 0)
+        } else {
+            (
+                self.packet[1] as i32 - if flags & PACKET_X_SIGN != 0 { 0x100 } else { 0 },
+                self.packet[2] as i32 - if flags & PACKET_Y_SIGN != 0 { 0x100 } else { 0 },
+            )
+        };
+        // Wheel movement is 4 bit two's complement
+        let wheel = if self.packet_size == 4 {
+            ((self.packet[3] << 4) as i8 >> 4) as i32
+        } else {
+            0
+        };
+        let buttons = MouseButtons::from_bits_truncate(flags & PACKET_BUTTONS);
+
+        if let Some(ref mut mouse) = self.mouse {
+            // PS/2 mice count vertical motion upwards
+            mouse.process_state(dx, -dy, wheel, buttons);
+        }
+    }
+}
+
+impl Driver<MouseDriverApi> for PsMouseDriver {
+    fn init(&mut self, api: MouseDriverApi) {
+        self.mouse = Some(api);
+    }
+
+    fn stop(&mut self) {
+        self.set_reporting(false);
+        self.mouse = None;
+    }
+
+    fn suspend(&mut self) {
+        self.set_reporting(false);
+    }
+
+    fn resume(&mut self) {
+        self.set_reporting(true);
+    }
+}
+
+/// Receives bytes sent by mouse, called outside of interrupt context.
+fn process_byte(byte: u8) {
+    let mut psmouse = PSMOUSE.lock();
+    psmouse.process_byte(byte);
+}
@@ -0,0 +1,197 @@
+//! Scancode decoding
+//!
+//! PC keyboard controllers translate whatever the keyboard sends into scancode set 1 by default.
+//! In set 1 every key has one byte code, released keys have bit 7 set. Keys added with
+//! the enhanced keyboard are prefixed with `0xe0`, and Pause sends a fixed six byte sequence
+//! starting with `0xe1` and no release.
+
+use dev::kbd::Key;
+
+/// Code sent after `0xe0` to fake Shift press or release around some extended keys
+const FAKE_LEFT_SHIFT: u8 = 0x2a;
+const FAKE_RIGHT_SHIFT: u8 = 0x36;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum State {
+    Normal,
+    /// `0xe0` has been received
+    Extended,
+    /// Inside Pause sequence, given number of bytes remains
+    Pause(u8),
+}
+
+/// State machine turning scancode set 1 bytes into key presses and releases
+pub struct Set1Decoder {
+    state: State,
+}
+
+impl Set1Decoder {
+    pub const fn new() -> Set1Decoder {
+        Set1Decoder {
+            state: State::Normal,
+        }
+    }
+
+    /// Feeds next byte from keyboard, returns key and whether it has been pressed once
+    /// a complete scancode has been received.
+    pub fn decode(&mut self, byte: u8) -> Option<(Key, bool)> {
+        // Keyboard error or buffer overrun, whatever sequence was in progress is lost
+        if byte == 0x00 || byte == 0xff {
+            self.state = State::Normal;
+            return None;
+        }
+
+        let code = byte & 0x7f;
+        let pressed = byte & 0x80 == 0;
+
+        match self.state {
+            State::Normal => match byte {
+                0xe0 => {
+                    self.state = State::Extended;
+                    None
+                }
+                0xe1 => {
+                    self.state = State::Pause(5);
+                    None
+                }
+                // Command responses are not keys
+                0xfa | 0xfe => None,
+                _ => Some((normal_key(code), pressed)),
+            },
+            State::Extended => {
+                self.state = State::Normal;
+                if code == FAKE_LEFT_SHIFT || code == FAKE_RIGHT_SHIFT {
+                    None
+                } else {
+                    Some((extended_key(code), pressed))
+                }
+            }
+            State::Pause(1) => {
+                self.state = State::Normal;
+                Some((Key::Pause, true))
+            }
+            State::Pause(remaining) => {
+                self.state = State::Pause(remaining - 1);
+                None
+            }
+        }
+    }
+}
+
+fn normal_key(code: u8) -> Key {
+    match code {
+        0x01 => Key::Escape,
+        0x02 => Key::Digit1,
+        0x03 => Key::Digit2,
+        0x04 => Key::Digit3,
+        0x05 => Key::Digit4,
+        0x06 => Key::Digit5,
+        0x07 => Key::Digit6,
+        0x08 => Key::Digit7,
+        0x09 => Key::Digit8,
+        0x0a => Key::Digit9,
+        0x0b => Key::Digit0,
+        0x0c => Key::Minus,
+        0x0d => Key::Equal,
+        0x0e => Key::Backspace,
+        0x0f => Key::Tab,
+        0x10 => Key::Q,
+        0x11 => Key::W,
+        0x12 => Key::E,
+        0x13 => Key::R,
+        0x14 => Key::T,
+        0x15 => Key::Y,
+        0x16 => Key::U,
+        0x17 => Key::I,
+        0x18 => Key::O,
+        0x19 => Key::P,
+        0x1a => Key::LeftBracket,
+        0x1b => Key::RightBracket,
+        0x1c => Key::Enter,
+        0x1d => Key::LeftCtrl,
+        0x1e => Key::A,
+        0x1f => Key::S,
+        0x20 => Key::D,
+        0x21 => Key::F,
+        0x22 => Key::G,
+        0x23 => Key::H,
+        0x24 => Key::J,
+        0x25 => Key::K,
+        0x26 => Key::L,
+        0x27 => Key::Semicolon,
+        0x28 => Key::Quote,
+        0x29 => Key::Backquote,
+        0x2a => Key::LeftShift,
+        0x2b => Key::Backslash,
+        0x2c => Key::Z,
+        0x2d => Key::X,
+        0x2e => Key::C,
+        0x2f => Key::V,
+        0x30 => Key::B,
+        0x31 => Key::N,
+        0x32 => Key::M,
+        0x33 => Key::Comma,
+        0x34 => Key::Period,
+        0x35 => Key::Slash,
+        0x36 => Key::RightShift,
+        0x37 => Key::KeypadMultiply,
+        0x38 => Key::LeftAlt,
+        0x39 => Key::Space,
+        0x3a => Key::CapsLock,
+        0x3b => Key::F1,
+        0x3c => Key::F2,
+        0x3d => Key::F3,
+        0x3e => Key::F4,
+        0x3f => Key::F5,
+        0x40 => Key::F6,
+        0x41 => Key::F7,
+        0x42 => Key::F8,
+        0x43 => Key::F9,
+        0x44 => Key::F10,
+        0x45 => Key::NumLock,
+        0x46 => Key::ScrollLock,
+        0x47 => Key::Keypad7,
+        0x48 => Key::Keypad8,
+        0x49 => Key::Keypad9,
+        0x4a => Key::KeypadMinus,
+        0x4b => Key::Keypad4,
+        0x4c => Key::Keypad5,
+        0x4d => Key::Keypad6,
+        0x4e => Key::KeypadPlus,
+        0x4f => Key::Keypad1,
+        0x50 => Key::Keypad2,
+        0x51 => Key::Keypad3,
+        0x52 => Key::Keypad0,
+        0x53 => Key::KeypadPeriod,
+        // Sent by the same key instead of `e0 37` while Alt is held (SysRq)
+        0x54 => Key::PrintScreen,
+        0x56 => Key::NonUsBackslash,
+        0x57 => Key::F11,
+        0x58 => Key::F12,
+        _ => Key::Unknown(code as u16),
+    }
+}
+
+fn extended_key(code: u8) -> Key {
+    match code {
+        0x1c => Key::KeypadEnter,
+        0x1d => Key::RightCtrl,
+        0x35 => Key::KeypadDivide,
+        0x37 => Key::PrintScreen,
+        0x38 => Key::RightAlt,
+        0x47 => Key::Home,
+        0x48 => Key::Up,
+        0x49 => Key::PageUp,
+        0x4b => Key::Left,
+        0x4d => Key::Right,
+        0x4f => Key::End,
+        0x50 => Key::Down,
+        0x51 => Key::PageDown,
+        0x52 => Key::Insert,
+        0x53 => Key::Delete,
+        0x5b => Key::LeftGui,
+        0x5c => Key::RightGui,
+        0x5d => Key::Menu,
+        _ => Key::Unknown(0xe000 | code as u16),
+    }
+}
@@ -0,0 +1,40 @@
+//! Legacy device enumeration
+//!
+//! Legacy devices cannot be detected reliably without interpreting ACPI namespace, so devices
+//! of a standard PC are installed as `isa` devices unconditionally. Their drivers check if they
+//! are really there when probing them.
+
+use dev;
+use dev::isa::IsaDevice;
+
+const LEGACY_DEVICES: [IsaDevice; 5] = [
+    // PS/2 keyboard controller, with mouse on its second port
+    IsaDevice {
+        id: "PNP0303",
+        io_base: 0x60,
+    },
+    // COM1-COM4
+    IsaDevice {
+        id: "PNP0501",
+        io_base: 0x3f8,
+    },
+    IsaDevice {
+        id: "PNP0501",
+        io_base: 0x2f8,
+    },
+    IsaDevice {
+        id: "PNP0501",
+        io_base: 0x3e8,
+    },
+    IsaDevice {
+        id: "PNP0501",
+        io_base: 0x2e8,
+    },
+];
+
+/// Installs legacy devices.
+pub fn init() {
+    for device in LEGACY_DEVICES.iter() {
+        dev::mgr::install(box *device);
+    }
+}
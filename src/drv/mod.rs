@@ -1,4 +1,36 @@
 //! Drivers
+//!
+//! Drivers of bus devices are listed in [`DRIVERS`], and device manager binds them to devices
+//! found when buses are enumerated. Other drivers are started by these, e.g. PS/2 controller
+//! driver starts keyboard and mouse drivers for its ports.
+//!
+//! [`DRIVERS`]: ./static.DRIVERS.html
 
+pub mod acpi;
 pub mod gfx;
 pub mod hid;
+pub mod isa;
+pub mod pci;
+pub mod ps2;
+pub mod serial;
+
+use dev::mgr::{self, DriverInfo};
+
+/// Drivers bound to matching bus devices
+pub static DRIVERS: [&DriverInfo; 3] = [
+    &gfx::vga::DRIVER,
+    &ps2::i8042::DRIVER,
+    &serial::uart16550::DRIVER,
+];
+
+/// Registers drivers and enumerates buses, so that devices found get bound to them.
+///
+/// **Device manager is required to be initialized.**
+pub fn init() {
+    for &driver in DRIVERS.iter() {
+        mgr::register_driver(driver);
+    }
+
+    isa::init();
+    pci::init();
+}
@@ -0,0 +1,127 @@
+//! Enhanced configuration access mechanism
+//!
+//! Configuration space of every function is memory mapped as a separate 4 KiB page. Regions
+//! covering ranges of buses are listed in ACPI MCFG table. Pages are identity mapped when they
+//! are first accessed.
+
+use alloc::Vec;
+use core::ptr;
+
+use dev::pci::{PciAddress, PciConfigAccess};
+use drv::acpi;
+use mem;
+use mem::paging::{EntryFlags, PAGE_SIZE};
+
+/// Configuration space size of single function
+const CONFIG_SIZE: u16 = 4096;
+
+/// Offset of the first region in MCFG table, after reserved field
+const MCFG_REGIONS_OFFSET: usize = acpi::SDT_HEADER_SIZE + 8;
+const MCFG_REGION_SIZE: usize = 16;
+
+/// Memory region of single segment group
+#[derive(Debug, Copy, Clone)]
+pub struct Region {
+    /// Physical address of bus 0 configuration space, even if the region starts at a later
+    /// bus (MCFG base address)
+    pub base: usize,
+    pub segment: u16,
+    pub start_bus: u8,
+    pub end_bus: u8,
+}
+
+impl Region {
+    fn contains(&self, address: PciAddress) -> bool {
+        address.segment == self.segment && address.bus >= self.start_bus
+            && address.bus <= self.end_bus
+    }
+
+    fn config_address(&self, address: PciAddress) -> usize {
+        self.base
+            + ((address.bus as usize) << 20 | (address.device as usize) << 15
+                | (address.function as usize) << 12)
+    }
+
+    /// Returns `true` if configuration spaces of all buses in the region can be mapped.
+    fn is_mappable(&self) -> bool {
+        let start = self.base + ((self.start_bus as usize) << 20);
+        let size = ((self.end_bus - self.start_bus) as usize + 1) << 20;
+        mem::can_map_physical(start, size)
+    }
+}
+
+pub struct Ecam {
+    regions: Vec<Region>,
+}
+
+impl Ecam {
+    /// Reads regions from ACPI MCFG table, returns `None` if there is no such table, it is
+    /// empty, or some region lies where it cannot be identity mapped.
+    pub fn from_acpi() -> Option<Ecam> {
+        let mcfg = acpi::find_table(b"MCFG")?;
+        if mcfg.len() < MCFG_REGIONS_OFFSET {
+            return None;
+        }
+
+        let regions: Vec<Region> = mcfg[MCFG_REGIONS_OFFSET..]
+            .chunks(MCFG_REGION_SIZE)
+            .filter(|entry| entry.len() == MCFG_REGION_SIZE)
+            .map(|entry| Region {
+                base: acpi::read_le(entry, 0, 8) as usize,
+                segment: acpi::read_le(entry, 8, 2) as u16,
+                start_bus: entry[10],
+                end_bus: entry[11],
+            })
+            .filter(|region| region.start_bus <= region.end_bus)
+            .collect();
+
+        if regions.is_empty() {
+            return None;
+        }
+
+        if let Some(region) = regions.iter().find(|region| !region.is_mappable()) {
+            println!(
+                "pci: ECAM region at {:#x} cannot be mapped, falling back to ports",
+                region.base
+            );
+            return None;
+        }
+
+        Some(Ecam { regions })
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// Returns pointer to register of given function, mapping its configuration space if needed.
+    fn register(&self, address: PciAddress, offset: u16) -> Option<*mut u32> {
+        if offset >= CONFIG_SIZE {
+            return None;
+        }
+
+        let region = self.regions.iter().find(|region| region.contains(address))?;
+        let flags = EntryFlags::WRITABLE | EntryFlags::NO_CACHE | EntryFlags::NO_EXECUTE;
+        let config = mem::map_physical(region.config_address(address), PAGE_SIZE, flags)?;
+        Some((config + (offset as usize & !0b11)) as *mut u32)
+    }
+}
+
+impl PciConfigAccess for Ecam {
+    fn read(&self, address: PciAddress, offset: u16) -> u32 {
+        match self.register(address, offset) {
+            Some(register) => unsafe { ptr::read_volatile(register) },
+            None => !0,
+        }
+    }
+
+    fn write(&self, address: PciAddress, offset: u16, value: u32) {
+        if let Some(register) = self.register(address, offset) {
+            unsafe { ptr::write_volatile(register, value) };
+        }
+    }
+
+    fn config_size(&self) -> u16 {
+        CONFIG_SIZE
+    }
+}
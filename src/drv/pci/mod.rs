@@ -0,0 +1,137 @@
+//! PCI bus driver
+//!
+//! Configuration space is accessed through memory mapped regions listed in ACPI MCFG table
+//! (ECAM) if firmware provides it, and through legacy ports `0xcf8`/`0xcfc` otherwise. Buses
+//! are enumerated recursively, starting with the root bus of every segment and following
+//! PCI-to-PCI bridges, and every function found is installed as a `pci` device.
+
+pub mod ecam;
+pub mod ports;
+
+use alloc::Vec;
+
+use spin::Once;
+
+use dev;
+use dev::pci::{self, HeaderType, PciAddress, PciConfigAccess, PciDevice, NO_VENDOR};
+
+use self::ecam::Ecam;
+use self::ports::ConfigPorts;
+
+const DEVICES_PER_BUS: u8 = 32;
+const FUNCTIONS_PER_DEVICE: u8 = 8;
+
+static PORTS: ConfigPorts = ConfigPorts::new();
+static ECAM: Once<Ecam> = Once::new();
+
+/// Enumerates all PCI buses and installs found functions as devices.
+pub fn init() {
+    let (access, roots): (&'static PciConfigAccess, Vec<(u16, u8)>) = match Ecam::from_acpi() {
+        Some(ecam) => {
+            let ecam = ECAM.call_once(|| ecam);
+            let roots = ecam.regions()
+                .iter()
+                .map(|region| (region.segment, region.start_bus))
+                .collect();
+            (ecam, roots)
+        }
+        None => (&PORTS, vec![(0, 0)]),
+    };
+
+    let mut scanner = Scanner {
+        access,
+        scanned: [false; 256],
+        found: Vec::new(),
+    };
+    for &(segment, bus) in roots.iter() {
+        scanner.scan_root(segment, bus);
+        // Buses of different segments are numbered independently
+        scanner.scanned = [false; 256];
+    }
+
+    let mechanism = if access.config_size() > 256 { "ECAM" } else { "ports" };
+    println!(
+        "pci: found {} functions using {}",
+        scanner.found.len(),
+        mechanism
+    );
+
+    for address in scanner.found {
+        dev::mgr::install(box PciDevice::new(address, access));
+    }
+}
+
+struct Scanner {
+    access: &'static PciConfigAccess,
+    /// Buses already scanned in current segment, in case bridges are misconfigured
+    scanned: [bool; 256],
+    found: Vec<PciAddress>,
+}
+
+impl Scanner {
+    /// Scans buses behind host bridge at the start of a segment.
+    fn scan_root(&mut self, segment: u16, bus: u8) {
+        let host = PciAddress {
+            segment,
+            bus,
+            device: 0,
+            function: 0,
+        };
+
+        // Each function of multifunction host bridge controls a separate bus
+        if pci::is_multifunction(self.access, host) {
+            for function in 0..FUNCTIONS_PER_DEVICE {
+                let address = PciAddress { function, ..host };
+                if pci::vendor_id(self.access, address) != NO_VENDOR {
+                    self.scan_bus(segment, bus.wrapping_add(function));
+                }
+            }
+        } else {
+            self.scan_bus(segment, bus);
+        }
+    }
+
+    fn scan_bus(&mut self, segment: u16, bus: u8) {
+        if self.scanned[bus as usize] {
+            return;
+        }
+        self.scanned[bus as usize] = true;
+
+        for device in 0..DEVICES_PER_BUS {
+            self.scan_device(PciAddress {
+                segment,
+                bus,
+                device,
+                function: 0,
+            });
+        }
+    }
+
+    fn scan_device(&mut self, address: PciAddress) {
+        if pci::vendor_id(self.access, address) == NO_VENDOR {
+            return;
+        }
+
+        let functions = if pci::is_multifunction(self.access, address) {
+            FUNCTIONS_PER_DEVICE
+        } else {
+            1
+        };
+        for function in 0..functions {
+            self.scan_function(PciAddress { function, ..address });
+        }
+    }
+
+    fn scan_function(&mut self, address: PciAddress) {
+        if pci::vendor_id(self.access, address) == NO_VENDOR {
+            return;
+        }
+
+        self.found.push(address);
+        if let HeaderType::Bridge { secondary_bus } = pci::header_type(self.access, address) {
+            if secondary_bus != 0 {
+                self.scan_bus(address.segment, secondary_bus);
+            }
+        }
+    }
+}
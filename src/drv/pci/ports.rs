@@ -0,0 +1,66 @@
+//! Legacy configuration access mechanism
+//!
+//! Address of a register is written to port `0xcf8`, and the register is then read or written
+//! through port `0xcfc`. Only the first 256 bytes of configuration space of segment 0 can be
+//! reached this way.
+
+use dev::pci::{PciAddress, PciConfigAccess};
+use kio::port::UnsafePort;
+use sync::IrqSpinLock;
+
+const ADDRESS_PORT: UnsafePort<u32> = unsafe { UnsafePort::new(0xcf8) };
+const DATA_PORT: UnsafePort<u32> = unsafe { UnsafePort::new(0xcfc) };
+
+/// Bit which makes the address valid
+const ADDRESS_ENABLE: u32 = 1 << 31;
+
+/// Configuration space size reachable through ports
+const CONFIG_SIZE: u16 = 256;
+
+pub struct ConfigPorts {
+    /// Guards address and data port pair
+    lock: IrqSpinLock<()>,
+}
+
+impl ConfigPorts {
+    pub const fn new() -> ConfigPorts {
+        ConfigPorts {
+            lock: IrqSpinLock::new(()),
+        }
+    }
+}
+
+impl PciConfigAccess for ConfigPorts {
+    fn read(&self, address: PciAddress, offset: u16) -> u32 {
+        if address.segment != 0 || offset >= CONFIG_SIZE {
+            return !0;
+        }
+
+        let _guard = self.lock.lock();
+        unsafe {
+            ADDRESS_PORT.write(register_address(address, offset));
+            DATA_PORT.read()
+        }
+    }
+
+    fn write(&self, address: PciAddress, offset: u16, value: u32) {
+        if address.segment != 0 || offset >= CONFIG_SIZE {
+            return;
+        }
+
+        let _guard = self.lock.lock();
+        unsafe {
+            ADDRESS_PORT.write(register_address(address, offset));
+            DATA_PORT.write(value);
+        }
+    }
+
+    fn config_size(&self) -> u16 {
+        CONFIG_SIZE
+    }
+}
+
+fn register_address(address: PciAddress, offset: u16) -> u32 {
+    ADDRESS_ENABLE | (address.bus as u32) << 16 | (address.device as u32) << 11
+        | (address.function as u32) << 8 | (offset as u32 & 0xfc)
+}
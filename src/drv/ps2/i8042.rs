@@ -0,0 +1,555 @@
+//! Intel 8042 PS/2 controller driver
+//!
+//! Controller is initialized from scratch instead of trusting firmware: both ports are
+//! disabled, the output buffer is flushed, controller and port self-tests are run, and then
+//! working ports are enabled with interrupts and scancode translation to set 1. Controller is
+//! bound to `isa` device `PNP0303` and installed as `ps2ctl` device, keyboard on the first port
+//! and mouse on the second one as its children. Once they are uninstalled together with the
+//! `isa` device, both ports are disabled and their interrupts masked, so that the controller
+//! can be probed again.
+//!
+//! Every byte received raises IRQ1 (first port) or IRQ12 (second port). The handler only reads
+//! it, pushes it to a lock-free ring of its port and schedules deferred work, which drains the
+//! rings and passes bytes to receivers attached to the ports. Commands are sent with both
+//! interrupts masked, and responses are read by polling status register. Bytes arriving in
+//! the meantime which are not responses, like scancodes of keys being typed, are passed to
+//! receivers as if they have raised interrupts.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use spin::Mutex;
+use x86_64::structures::idt::ExceptionStackFrame;
+
+use dev;
+use dev::mgr::{CommonDevice, DeviceId, DriverInfo, ProbeError};
+use dev::ps2::{Ps2Controller, Ps2ControllerDriver, Ps2Error, Ps2Port, ACK, RESEND};
+use drv::hid::{atkbd, psmouse};
+use kio::defer;
+use kio::idt::register_interrupt;
+use kio::pic;
+use kio::port::UnsafePort;
+use sync::{self, IrqSpinLock, RingConsumer, RingProducer};
+
+/// Interrupt of the first port (IRQ1)
+const IRQ_FIRST: u8 = 33;
+/// Interrupt of the second port (IRQ12)
+const IRQ_SECOND: u8 = 44;
+
+const DATA_PORT: UnsafePort<u8> = unsafe { UnsafePort::new(0x60) };
+/// Status register (read), command register (write)
+const STATUS_PORT: UnsafePort<u8> = unsafe { UnsafePort::new(0x64) };
+
+/// Output buffer holds a byte for the CPU
+const STATUS_OUTPUT_FULL: u8 = 1 << 0;
+/// Input buffer holds a byte the controller has not taken yet
+const STATUS_INPUT_FULL: u8 = 1 << 1;
+/// Byte in output buffer comes from the second port
+const STATUS_SECOND_PORT: u8 = 1 << 5;
+
+const CMD_READ_CONFIG: u8 = 0x20;
+const CMD_WRITE_CONFIG: u8 = 0x60;
+const CMD_DISABLE_SECOND: u8 = 0xa7;
+const CMD_ENABLE_SECOND: u8 = 0xa8;
+const CMD_TEST_SECOND: u8 = 0xa9;
+const CMD_SELF_TEST: u8 = 0xaa;
+const CMD_TEST_FIRST: u8 = 0xab;
+const CMD_DISABLE_FIRST: u8 = 0xad;
+const CMD_ENABLE_FIRST: u8 = 0xae;
+/// Next byte written to data port goes to the second port
+const CMD_WRITE_SECOND: u8 = 0xd4;
+
+const CONFIG_FIRST_IRQ: u8 = 1 << 0;
+const CONFIG_SECOND_IRQ: u8 = 1 << 1;
+const CONFIG_FIRST_CLOCK_DISABLED: u8 = 1 << 4;
+const CONFIG_SECOND_CLOCK_DISABLED: u8 = 1 << 5;
+const CONFIG_TRANSLATION: u8 = 1 << 6;
+
+/// Controller self-test passed
+const SELF_TEST_OK: u8 = 0x55;
+/// Port interface test passed
+const PORT_TEST_OK: u8 = 0x00;
+
+/// Number of bytes received on one port waiting to be passed to receiver, newer ones are dropped
+const RECEIVE_BUFFER_SIZE: usize = 128;
+
+/// Number of status register reads before giving up, each takes about a microsecond
+const TIMEOUT_POLLS: usize = 100_000;
+/// Number of times a command byte is sent again on device request
+const MAX_RESENDS: usize = 3;
+/// Number of bytes, e.g. scancodes of keys being typed, which may arrive from a device before
+/// it acknowledges command byte
+const MAX_BYTES_BEFORE_ACK: usize = 16;
+
+static CONTROLLER: I8042 = I8042::new();
+
+pub static DRIVER: DriverInfo = DriverInfo {
+    name: "i8042",
+    ids: &[DeviceId::Isa("PNP0303")],
+    probe,
+    remove,
+};
+
+/// Initializes the controller and installs it with devices plugged into it.
+fn probe(device: &CommonDevice) -> Result<(), ProbeError> {
+    CONTROLLER.init_receive_buffers();
+
+    let ports = CONTROLLER
+        .state
+        .lock()
+        .init()
+        .map_err(|error| ProbeError::Failed(format!("{}", error)))?;
+
+    unsafe {
+        register_interrupt(IRQ_FIRST, handle_irq_first);
+        register_interrupt(IRQ_SECOND, handle_irq_second);
+    }
+    CONTROLLER.unmask_irqs(ports);
+
+    let controller = Ps2Controller::new(&CONTROLLER);
+    let keyboard = controller.channel(Ps2Port::First);
+    let mouse = controller.channel(Ps2Port::Second);
+    let name = dev::mgr::install_child(&device.name(), box controller);
+
+    if let Some(channel) = keyboard {
+        atkbd::init(&name, channel);
+    }
+    if let Some(channel) = mouse {
+        psmouse::init(&name, channel);
+    }
+    Ok(())
+}
+
+/// Silences the controller once devices plugged into it are uninstalled, so that it can be
+/// probed again.
+fn remove(_device: &CommonDevice) {
+    CONTROLLER.shutdown();
+}
+
+/// PS/2 controller driver
+pub struct I8042 {
+    /// Serializes access to the controller
+    state: Mutex<State>,
+    /// Functions receiving bytes from devices, by port index
+    receivers: IrqSpinLock<[Option<fn(u8)>; 2]>,
+    /// Received bytes waiting to be passed to receivers, by port index
+    buffers: [ReceiveBuffer; 2],
+    /// Passing bytes to receivers has been scheduled, and has not started yet
+    scheduled: AtomicBool,
+}
+
+/// Bytes received from device on one port
+///
+/// Bytes are pushed by interrupt handlers, which run with interrupts disabled and so never
+/// interrupt each other, and by commands, which run with both port interrupts masked. There
+/// is thus a single producer at any moment, and pushing takes no lock.
+struct ReceiveBuffer {
+    producer: UnsafeCell<Option<RingProducer<u8>>>,
+    /// Popping end, used by deferred work
+    consumer: Mutex<Option<RingConsumer<u8>>>,
+    /// Number of bytes dropped because the buffer was full
+    dropped: AtomicUsize,
+}
+
+// Producer is only used by one context at a time, see above
+unsafe impl Sync for ReceiveBuffer {}
+
+impl ReceiveBuffer {
+    const fn new() -> ReceiveBuffer {
+        ReceiveBuffer {
+            producer: UnsafeCell::new(None),
+            consumer: Mutex::new(None),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Appends byte, returns `false` if it has been dropped.
+    ///
+    /// **Must be called from interrupt handler of the controller, or with its interrupts
+    /// masked.**
+    unsafe fn push(&self, byte: u8) -> bool {
+        let pushed = match *self.producer.get() {
+            Some(ref mut producer) => producer.push(byte),
+            None => false,
+        };
+        if !pushed {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        pushed
+    }
+
+    fn pop(&self) -> Option<u8> {
+        match *self.consumer.lock() {
+            Some(ref mut consumer) => consumer.pop(),
+            None => None,
+        }
+    }
+}
+
+struct State {
+    /// Working ports, by index
+    ports: [bool; 2],
+}
+
+impl I8042 {
+    const fn new() -> I8042 {
+        I8042 {
+            state: Mutex::new(State {
+                ports: [false, false],
+            }),
+            receivers: IrqSpinLock::new([None, None]),
+            buffers: [ReceiveBuffer::new(), ReceiveBuffer::new()],
+            scheduled: AtomicBool::new(false),
+        }
+    }
+
+    /// Allocates buffers of received bytes, unless they exist already. Masks port interrupts,
+    /// which are unmasked once the controller is initialized.
+    fn init_receive_buffers(&self) {
+        unsafe {
+            pic::disable(IRQ_FIRST);
+            pic::disable(IRQ_SECOND);
+        }
+
+        for buffer in self.buffers.iter() {
+            let mut consumer = buffer.consumer.lock();
+            if consumer.is_none() {
+                let (producer, new_consumer) = sync::spsc_ring(RECEIVE_BUFFER_SIZE, 0);
+                // Interrupt handlers cannot run now
+                unsafe { *buffer.producer.get() = Some(producer) };
+                *consumer = Some(new_consumer);
+            }
+        }
+    }
+
+    /// Queues byte received from device for its receiver. Never blocks nor allocates.
+    ///
+    /// **Must be called from interrupt handler, or with port interrupts masked.**
+    unsafe fn receive(&self, port: Ps2Port, byte: u8) {
+        if !self.buffers[port.index()].push(byte) {
+            return;
+        }
+
+        if !self.scheduled.swap(true, Ordering::AcqRel) && !defer::schedule(process_bytes, 0) {
+            self.scheduled.store(false, Ordering::Release);
+        }
+    }
+
+    /// Masks port interrupts, disables both ports and forgets receivers. Bytes still waiting
+    /// in the buffer are dropped by deferred work, as they have no receivers.
+    fn shutdown(&self) {
+        let mut state = self.state.lock();
+        unsafe {
+            pic::disable(IRQ_FIRST);
+            pic::disable(IRQ_SECOND);
+        }
+
+        // Controller may be gone already, there is nothing to do about errors
+        let _ = write_command(CMD_DISABLE_FIRST);
+        let _ = write_command(CMD_DISABLE_SECOND);
+        state.ports = [false, false];
+        *self.receivers.lock() = [None, None];
+    }
+
+    /// Runs `f` with exclusive access to the controller and port interrupts masked, so that
+    /// responses are not taken by interrupt handlers.
+    fn with_port<R>(
+        &self,
+        port: Ps2Port,
+        f: impl FnOnce() -> Result<R, Ps2Error>,
+    ) -> Result<R, Ps2Error> {
+        let state = self.state.lock();
+        if !state.ports[port.index()] {
+            return Err(Ps2Error::NoSuchPort);
+        }
+
+        unsafe {
+            pic::disable(IRQ_FIRST);
+            pic::disable(IRQ_SECOND);
+        }
+
+        let result = f();
+
+        self.unmask_irqs(state.ports);
+        result
+    }
+
+    fn unmask_irqs(&self, ports: [bool; 2]) {
+        unsafe {
+            if ports[0] {
+                pic::enable(IRQ_FIRST);
+            }
+            if ports[1] {
+                pic::enable(IRQ_SECOND);
+            }
+        }
+    }
+}
+
+impl Ps2ControllerDriver for I8042 {
+    fn has_port(&self, port: Ps2Port) -> bool {
+        self.state.lock().ports[port.index()]
+    }
+
+    fn command(&self, port: Ps2Port, bytes: &[u8], response: &mut [u8]) -> Result<(), Ps2Error> {
+        self.with_port(port, || run_command(port, bytes, response))
+    }
+
+    fn attach(&self, port: Ps2Port, receiver: fn(u8)) {
+        self.receivers.lock()[port.index()] = Some(receiver);
+    }
+
+    fn dropped(&self, port: Ps2Port) -> usize {
+        self.buffers[port.index()].dropped.load(Ordering::Relaxed)
+    }
+
+    fn set_translation(&self, port: Ps2Port, enabled: bool) -> Result<(), Ps2Error> {
+        // Controller translates only bytes coming from the first port
+        if port != Ps2Port::First {
+            return Err(Ps2Error::NotSupported);
+        }
+
+        self.with_port(port, || {
+            forward_pending();
+            let mut config = read_config()?;
+            if enabled {
+                config |= CONFIG_TRANSLATION;
+            } else {
+                config &= !CONFIG_TRANSLATION;
+            }
+            write_config(config)
+        })
+    }
+}
+
+impl State {
+    /// Resets controller configuration and tests it, returns which ports work.
+    fn init(&mut self) -> Result<[bool; 2], Ps2Error> {
+        write_command(CMD_DISABLE_FIRST)?;
+        write_command(CMD_DISABLE_SECOND)?;
+        flush();
+
+        let mut config = read_config()?;
+        config &= !(CONFIG_FIRST_IRQ | CONFIG_SECOND_IRQ | CONFIG_TRANSLATION);
+        write_config(config)?;
+
+        write_command(CMD_SELF_TEST)?;
+        match read_data()? {
+            SELF_TEST_OK => {}
+            result => return Err(Ps2Error::SelfTestFailed(result)),
+        }
+        // Self-test may reset the controller
+        write_config(config)?;
+
+        // Clock of the second port gets enabled only if there is one
+        let dual = config & CONFIG_SECOND_CLOCK_DISABLED != 0 && {
+            write_command(CMD_ENABLE_SECOND)?;
+            let enabled = read_config()? & CONFIG_SECOND_CLOCK_DISABLED == 0;
+            write_command(CMD_DISABLE_SECOND)?;
+            enabled
+        };
+
+        let mut ports = [false, false];
+        ports[0] = test_port(Ps2Port::First, CMD_TEST_FIRST);
+        ports[1] = dual && test_port(Ps2Port::Second, CMD_TEST_SECOND);
+
+        if ports[0] {
+            write_command(CMD_ENABLE_FIRST)?;
+            config &= !CONFIG_FIRST_CLOCK_DISABLED;
+            config |= CONFIG_FIRST_IRQ | CONFIG_TRANSLATION;
+        }
+        if ports[1] {
+            write_command(CMD_ENABLE_SECOND)?;
+            config &= !CONFIG_SECOND_CLOCK_DISABLED;
+            config |= CONFIG_SECOND_IRQ;
+        }
+        write_config(config)?;
+
+        self.ports = ports;
+        Ok(ports)
+    }
+}
+
+/// Runs interface test of a port, returns `true` if it has passed.
+fn test_port(port: Ps2Port, command: u8) -> bool {
+    let result = write_command(command).and_then(|_| read_data());
+    match result {
+        Ok(PORT_TEST_OK) => true,
+        Ok(code) => {
+            println!("i8042: {}: {}", port, Ps2Error::PortTestFailed(code));
+            false
+        }
+        Err(error) => {
+            println!("i8042: {}: {}", port, error);
+            false
+        }
+    }
+}
+
+/// Sends command to device and reads its response, interrupts have to be masked.
+fn run_command(port: Ps2Port, bytes: &[u8], response: &mut [u8]) -> Result<(), Ps2Error> {
+    // Bytes which have arrived so far are not responses
+    forward_pending();
+
+    for &byte in bytes {
+        send_byte(port, byte)?;
+    }
+    for slot in response.iter_mut() {
+        *slot = read_from(port)?;
+    }
+    Ok(())
+}
+
+/// Sends byte to device and waits for acknowledgement, resending it if asked to. Other bytes
+/// the device sends in the meantime are passed to its receiver.
+fn send_byte(port: Ps2Port, byte: u8) -> Result<(), Ps2Error> {
+    'resend: for _ in 0..MAX_RESENDS + 1 {
+        if port == Ps2Port::Second {
+            write_command(CMD_WRITE_SECOND)?;
+        }
+        write_data(byte)?;
+
+        let mut last = 0;
+        for _ in 0..MAX_BYTES_BEFORE_ACK {
+            match read_from(port)? {
+                ACK => return Ok(()),
+                RESEND => continue 'resend,
+                other => {
+                    schedule_receive(port, other);
+                    last = other;
+                }
+            }
+        }
+        return Err(Ps2Error::UnexpectedResponse(last));
+    }
+
+    Err(Ps2Error::TooManyResends)
+}
+
+/// Waits for byte from given port, bytes from the other port are passed to its receiver.
+fn read_from(port: Ps2Port) -> Result<u8, Ps2Error> {
+    loop {
+        let (from, byte) = read_data_with_port()?;
+        if from == port {
+            return Ok(byte);
+        }
+        schedule_receive(from, byte);
+    }
+}
+
+/// Passes bytes waiting in output buffer to receivers.
+fn forward_pending() {
+    unsafe {
+        while STATUS_PORT.read() & STATUS_OUTPUT_FULL != 0 {
+            let (from, byte) = read_available();
+            schedule_receive(from, byte);
+        }
+    }
+}
+
+/// Queues byte for receiver of the port.
+///
+/// **Must be called from interrupt handler, or with port interrupts masked, like commands
+/// are run.**
+fn schedule_receive(port: Ps2Port, byte: u8) {
+    unsafe { CONTROLLER.receive(port, byte) };
+}
+
+/// Deferred part of IRQ handling, passes received bytes to receivers of their ports.
+fn process_bytes(_: usize) {
+    // Bytes pushed from now on need another run
+    CONTROLLER.scheduled.store(false, Ordering::Release);
+
+    for (index, buffer) in CONTROLLER.buffers.iter().enumerate() {
+        while let Some(byte) = buffer.pop() {
+            let receiver = CONTROLLER.receivers.lock()[index];
+            if let Some(receiver) = receiver {
+                receiver(byte);
+            }
+        }
+    }
+}
+
+fn read_config() -> Result<u8, Ps2Error> {
+    write_command(CMD_READ_CONFIG)?;
+    read_data()
+}
+
+fn write_config(config: u8) -> Result<(), Ps2Error> {
+    write_command(CMD_WRITE_CONFIG)?;
+    write_data(config)
+}
+
+fn write_command(command: u8) -> Result<(), Ps2Error> {
+    wait_status(|status| status & STATUS_INPUT_FULL == 0)?;
+    unsafe {
+        STATUS_PORT.write(command);
+    }
+    Ok(())
+}
+
+fn write_data(byte: u8) -> Result<(), Ps2Error> {
+    wait_status(|status| status & STATUS_INPUT_FULL == 0)?;
+    unsafe {
+        DATA_PORT.write(byte);
+    }
+    Ok(())
+}
+
+fn read_data() -> Result<u8, Ps2Error> {
+    read_data_with_port().map(|(_, byte)| byte)
+}
+
+/// Waits for byte in output buffer, returns it with port it comes from.
+fn read_data_with_port() -> Result<(Ps2Port, u8), Ps2Error> {
+    wait_status(|status| status & STATUS_OUTPUT_FULL != 0)?;
+    Ok(unsafe { read_available() })
+}
+
+/// Reads byte from output buffer, which must be full.
+unsafe fn read_available() -> (Ps2Port, u8) {
+    let port = if STATUS_PORT.read() & STATUS_SECOND_PORT != 0 {
+        Ps2Port::Second
+    } else {
+        Ps2Port::First
+    };
+    (port, DATA_PORT.read())
+}
+
+/// Discards bytes waiting in output buffer.
+fn flush() {
+    unsafe {
+        while STATUS_PORT.read() & STATUS_OUTPUT_FULL != 0 {
+            DATA_PORT.read();
+        }
+    }
+}
+
+fn wait_status(condition: impl Fn(u8) -> bool) -> Result<(), Ps2Error> {
+    for _ in 0..TIMEOUT_POLLS {
+        if condition(unsafe { STATUS_PORT.read() }) {
+            return Ok(());
+        }
+    }
+    Err(Ps2Error::Timeout)
+}
+
+/// Reads byte which has raised an interrupt and queues it for receiver.
+fn handle_irq(irq: u8) {
+    unsafe {
+        // Interrupt may have been latched while masked, after the byte was read by a command
+        if STATUS_PORT.read() & STATUS_OUTPUT_FULL != 0 {
+            let (from, byte) = read_available();
+            schedule_receive(from, byte);
+        }
+        pic::eoi(irq);
+    }
+}
+
+extern "x86-interrupt" fn handle_irq_first(_stack_frame: &mut ExceptionStackFrame) {
+    handle_irq(IRQ_FIRST);
+}
+
+extern "x86-interrupt" fn handle_irq_second(_stack_frame: &mut ExceptionStackFrame) {
+    handle_irq(IRQ_SECOND);
+}
@@ -0,0 +1,3 @@
+//! PS/2 controller drivers
+
+pub mod i8042;
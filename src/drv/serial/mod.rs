@@ -0,0 +1,3 @@
+//! Serial line drivers
+
+pub mod uart16550;
@@ -0,0 +1,543 @@
+//! National Semiconductor 16550 UART driver
+//!
+//! Drives standard PC serial ports COM1-COM4 at 8 data bits, no parity and 1 stop bit. Driver
+//! is bound to `isa` devices `PNP0501`, and every port which passes the loopback self-test is
+//! installed as a `tty` device. COM1 also mirrors
+//! kernel console output from early boot on, so that logs can be captured from headless QEMU
+//! running with `-serial stdio`.
+//!
+//! Received bytes raise IRQ4 (COM1, COM3) or IRQ3 (COM2, COM4). The handler drains receive
+//! FIFOs of all ports sharing the line into lock-free rings, one per port, and schedules
+//! deferred work passing the bytes to `tty` devices, once per port until it runs.
+
+use core::cell::UnsafeCell;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use spin::Mutex;
+use x86_64::structures::idt::{ExceptionStackFrame, HandlerFunc};
+
+use dev;
+use dev::isa::IsaDevice;
+use dev::mgr::{CommonDevice, DeviceId, DriverInfo, ProbeError};
+use dev::output_serial::{OutputSerial, SharedOutputSerial};
+use dev::tty::{Tty, TtyDriver, TtyDriverApi, TtyError};
+use kio::defer;
+use kio::idt::register_interrupt;
+use kio::pic;
+use kio::port::UnsafePort;
+use sync::{self, IrqSpinLock, RingConsumer, RingProducer};
+
+/// Interrupt of COM1 and COM3 (IRQ4)
+const IRQ_COM1_COM3: u8 = 36;
+/// Interrupt of COM2 and COM4 (IRQ3)
+const IRQ_COM2_COM4: u8 = 35;
+
+/// Frequency of UART clock divided by 16, the highest supported baud rate
+const MAX_BAUD_RATE: u32 = 115_200;
+
+/// Baud rate ports are programmed with on initialization
+pub const DEFAULT_BAUD_RATE: u32 = 115_200;
+
+/// Receive buffer (read), transmit holding register (write), divisor latch low byte with DLAB
+const DATA: u16 = 0;
+/// Interrupt enable register, divisor latch high byte with DLAB
+const INTERRUPT_ENABLE: u16 = 1;
+/// Interrupt identification register (read), FIFO control register (write)
+const FIFO_CONTROL: u16 = 2;
+const INTERRUPT_IDENTIFICATION: u16 = 2;
+const LINE_CONTROL: u16 = 3;
+const MODEM_CONTROL: u16 = 4;
+const LINE_STATUS: u16 = 5;
+const SCRATCH: u16 = 7;
+
+/// Interrupt when received data is available
+const IER_RECEIVE: u8 = 1 << 0;
+
+/// Divisor latch access bit
+const LCR_DLAB: u8 = 1 << 7;
+/// 8 data bits, no parity, 1 stop bit
+const LCR_8N1: u8 = 0b0000_0011;
+
+const FCR_ENABLE: u8 = 1 << 0;
+const FCR_CLEAR_RECEIVE: u8 = 1 << 1;
+const FCR_CLEAR_TRANSMIT: u8 = 1 << 2;
+/// Receive interrupt is raised when 14 bytes are waiting
+const FCR_TRIGGER_14: u8 = 0b11 << 6;
+
+/// Both bits are set if FIFOs are enabled and working, which is not the case for 16450
+const IIR_FIFO_ENABLED: u8 = 0b11 << 6;
+
+/// Size of transmit FIFO of 16550A
+const TRANSMIT_FIFO_SIZE: usize = 16;
+
+/// Number of received bytes waiting to be passed to `tty` device, newer ones are dropped
+const RECEIVE_BUFFER_SIZE: usize = 256;
+
+const MCR_DTR: u8 = 1 << 0;
+const MCR_RTS: u8 = 1 << 1;
+const MCR_OUT1: u8 = 1 << 2;
+/// Connects UART interrupt line to the PIC
+const MCR_OUT2: u8 = 1 << 3;
+const MCR_LOOPBACK: u8 = 1 << 4;
+
+const LSR_DATA_READY: u8 = 1 << 0;
+const LSR_TRANSMIT_EMPTY: u8 = 1 << 5;
+
+/// Byte sent to itself by the loopback self-test
+const SELF_TEST_BYTE: u8 = 0xae;
+/// Number of line status polls after which the self-test gives up
+const SELF_TEST_TIMEOUT: usize = 10_000;
+
+/// Reason why serial port could not be initialized
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// There is no UART at the port address
+    NotPresent,
+    /// Byte sent in loopback mode has not come back
+    SelfTestFailed,
+    /// Requested baud rate is not an integral fraction of `MAX_BAUD_RATE`
+    UnsupportedBaudRate(u32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NotPresent => write!(f, "no UART present"),
+            Error::SelfTestFailed => write!(f, "loopback self-test failed"),
+            Error::UnsupportedBaudRate(baud) => write!(f, "unsupported baud rate {}", baud),
+        }
+    }
+}
+
+/// Single 16550 UART
+pub struct Uart16550 {
+    base: u16,
+    baud_rate: u32,
+    /// Set if 16-byte FIFOs are available
+    fifo: bool,
+    /// Number of bytes transmitter can take without checking line status
+    transmit_free: usize,
+    /// Output is silently dropped until the port is initialized
+    initialized: bool,
+}
+
+impl Uart16550 {
+    /// Creates driver of UART at given I/O port base, which has to be initialized before use.
+    pub const fn new(base: u16) -> Uart16550 {
+        Uart16550 {
+            base,
+            baud_rate: 0,
+            fifo: false,
+            transmit_free: 0,
+            initialized: false,
+        }
+    }
+
+    /// Detects the UART, programs line settings, enables FIFOs and runs the loopback
+    /// self-test. Interrupts of the UART are left disabled.
+    pub fn init(&mut self, baud: u32) -> Result<(), Error> {
+        unsafe {
+            // Scratch register is the only one keeping arbitrary values
+            self.port(SCRATCH).write(0x5a);
+            if self.port(SCRATCH).read() != 0x5a {
+                return Err(Error::NotPresent);
+            }
+
+            self.port(INTERRUPT_ENABLE).write(0);
+        }
+
+        self.set_baud_rate(baud)?;
+
+        unsafe {
+            self.port(FIFO_CONTROL)
+                .write(FCR_ENABLE | FCR_CLEAR_RECEIVE | FCR_CLEAR_TRANSMIT | FCR_TRIGGER_14);
+            self.fifo = self.port(INTERRUPT_IDENTIFICATION).read() & IIR_FIFO_ENABLED
+                == IIR_FIFO_ENABLED;
+        }
+        self.transmit_free = 0;
+
+        self.loopback_test()?;
+
+        unsafe {
+            self.port(MODEM_CONTROL).write(MCR_DTR | MCR_RTS | MCR_OUT1 | MCR_OUT2);
+        }
+
+        self.initialized = true;
+        Ok(())
+    }
+
+    /// Programs divisor latch for given line speed, line is set to 8N1.
+    pub fn set_baud_rate(&mut self, baud: u32) -> Result<(), Error> {
+        if baud == 0 || MAX_BAUD_RATE % baud != 0 {
+            return Err(Error::UnsupportedBaudRate(baud));
+        }
+        let divisor = (MAX_BAUD_RATE / baud) as u16;
+
+        unsafe {
+            self.port(LINE_CONTROL).write(LCR_DLAB);
+            self.port(DATA).write((divisor & 0xff) as u8);
+            self.port(INTERRUPT_ENABLE).write((divisor >> 8) as u8);
+            self.port(LINE_CONTROL).write(LCR_8N1);
+        }
+
+        self.baud_rate = baud;
+        Ok(())
+    }
+
+    /// Enables interrupt on received data. It reaches the PIC only after `init`, which sets
+    /// `OUT2` modem control bit.
+    pub fn enable_receive_interrupt(&mut self) {
+        unsafe { self.port(INTERRUPT_ENABLE).write(IER_RECEIVE) };
+    }
+
+    /// Disables all interrupts of the UART.
+    pub fn disable_interrupts(&mut self) {
+        unsafe { self.port(INTERRUPT_ENABLE).write(0) };
+    }
+
+    /// Returns I/O port base of the UART.
+    pub fn base(&self) -> u16 {
+        self.base
+    }
+
+    /// Returns current line speed in bits per second.
+    pub fn baud_rate(&self) -> u32 {
+        self.baud_rate
+    }
+
+    /// Returns `true` if the UART has working FIFOs, i.e. it is 16550A or newer.
+    pub fn has_fifo(&self) -> bool {
+        self.fifo
+    }
+
+    /// Sends a byte to itself with modem control loopback enabled, and checks it comes back.
+    /// Leaves loopback mode enabled.
+    fn loopback_test(&mut self) -> Result<(), Error> {
+        unsafe {
+            self.port(MODEM_CONTROL).write(MCR_LOOPBACK | MCR_RTS | MCR_OUT1 | MCR_OUT2);
+            self.port(DATA).write(SELF_TEST_BYTE);
+
+            for _ in 0..SELF_TEST_TIMEOUT {
+                if self.port(LINE_STATUS).read() & LSR_DATA_READY != 0 {
+                    return if self.port(DATA).read() == SELF_TEST_BYTE {
+                        Ok(())
+                    } else {
+                        Err(Error::SelfTestFailed)
+                    };
+                }
+            }
+        }
+
+        Err(Error::SelfTestFailed)
+    }
+
+    /// Returns received byte, if there is any waiting.
+    pub fn try_read_byte(&mut self) -> Option<u8> {
+        // Line status of missing UART reads as all ones
+        if !self.initialized {
+            return None;
+        }
+
+        unsafe {
+            if self.port(LINE_STATUS).read() & LSR_DATA_READY != 0 {
+                Some(self.port(DATA).read())
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Writes byte to transmitter, returns `false` if it has no room for it.
+    fn try_write_raw_byte(&mut self, byte: u8) -> bool {
+        if self.transmit_free == 0 {
+            // Transmit FIFO is empty together with holding register, so it can take
+            // the whole FIFO worth of bytes at once
+            if unsafe { self.port(LINE_STATUS).read() } & LSR_TRANSMIT_EMPTY == 0 {
+                return false;
+            }
+            self.transmit_free = if self.fifo { TRANSMIT_FIFO_SIZE } else { 1 };
+        }
+
+        unsafe { self.port(DATA).write(byte) };
+        self.transmit_free -= 1;
+        true
+    }
+
+    fn write_raw_byte(&mut self, byte: u8) {
+        while !self.try_write_raw_byte(byte) {}
+    }
+
+    fn port(&self, register: u16) -> UnsafePort<u8> {
+        unsafe { UnsafePort::new(self.base + register) }
+    }
+}
+
+impl OutputSerial for Uart16550 {
+    /// Sends byte, `\n` is sent as `\r\n` as terminals expect.
+    fn put_byte(&mut self, byte: u8) {
+        if !self.initialized {
+            return;
+        }
+
+        if byte == b'\n' {
+            self.write_raw_byte(b'\r');
+        }
+        self.write_raw_byte(byte);
+    }
+}
+
+/// Serial port, driving `tty` device
+pub struct SerialPort {
+    uart: IrqSpinLock<Uart16550>,
+    /// Interrupt vector the port raises
+    irq: u8,
+    /// `tty` device receiving input, once the port is installed
+    tty: IrqSpinLock<Option<TtyDriverApi>>,
+    /// Pushing end of received bytes buffer, used only by the interrupt handler, which never
+    /// interrupts itself
+    received: UnsafeCell<Option<RingProducer<u8>>>,
+    /// Popping end of received bytes buffer, used by deferred work
+    pending: Mutex<Option<RingConsumer<u8>>>,
+    /// Passing bytes to `tty` device has been scheduled, and has not started yet
+    scheduled: AtomicBool,
+}
+
+// Producer is only touched by the interrupt handler, or before receive interrupt is enabled
+unsafe impl Sync for SerialPort {}
+
+impl SerialPort {
+    const fn new(base: u16, irq: u8) -> SerialPort {
+        SerialPort {
+            uart: IrqSpinLock::new(Uart16550::new(base)),
+            irq,
+            tty: IrqSpinLock::new(None),
+            received: UnsafeCell::new(None),
+            pending: Mutex::new(None),
+            scheduled: AtomicBool::new(false),
+        }
+    }
+
+    /// Allocates buffer of received bytes, unless it exists already.
+    ///
+    /// **Receive interrupt of the port has to be disabled.**
+    unsafe fn init_receive_buffer(&self) {
+        let mut pending = self.pending.lock();
+        if pending.is_none() {
+            let (producer, consumer) = sync::spsc_ring(RECEIVE_BUFFER_SIZE, 0);
+            *self.received.get() = Some(producer);
+            *pending = Some(consumer);
+        }
+    }
+
+    /// Sends bytes, `\n` is sent as `\r\n`. The lock is taken for each byte separately, so that
+    /// interrupts are not held off and receive handler can run while the line is busy.
+    fn write(&self, bytes: &[u8]) {
+        if !self.uart.lock().initialized {
+            return;
+        }
+
+        for &byte in bytes {
+            if byte == b'\n' {
+                self.write_raw_byte(b'\r');
+            }
+            self.write_raw_byte(byte);
+        }
+    }
+
+    fn write_raw_byte(&self, byte: u8) {
+        while !self.uart.lock().try_write_raw_byte(byte) {}
+    }
+
+    /// Moves bytes from receive FIFO to the buffer, bytes which do not fit are dropped, and
+    /// schedules passing them to `tty` device.
+    ///
+    /// **Must only be called by the interrupt handler.**
+    unsafe fn drain_receive_fifo(&self, index: usize) {
+        let mut received = false;
+        {
+            let producer = &mut *self.received.get();
+            let mut uart = self.uart.lock();
+            while let Some(byte) = uart.try_read_byte() {
+                if let Some(ref mut producer) = *producer {
+                    received |= producer.push(byte);
+                }
+            }
+        }
+
+        if received && !self.scheduled.swap(true, Ordering::AcqRel)
+            && !defer::schedule(process_bytes, index)
+        {
+            self.scheduled.store(false, Ordering::Release);
+        }
+    }
+}
+
+impl TtyDriver for SerialPort {
+    fn write(&self, bytes: &[u8]) {
+        SerialPort::write(self, bytes);
+    }
+
+    fn baud_rate(&self) -> u32 {
+        self.uart.lock().baud_rate()
+    }
+
+    fn set_baud_rate(&self, baud: u32) -> Result<(), TtyError> {
+        self.uart
+            .lock()
+            .set_baud_rate(baud)
+            .map_err(|_| TtyError::UnsupportedBaudRate(baud))
+    }
+
+    fn attach(&self, api: TtyDriverApi) {
+        *self.tty.lock() = Some(api);
+    }
+
+    fn output(&self) -> &SharedOutputSerial {
+        &self.uart
+    }
+
+    fn stop(&self) {
+        self.uart.lock().disable_interrupts();
+        *self.tty.lock() = None;
+    }
+
+    fn suspend(&self) {
+        self.uart.lock().disable_interrupts();
+    }
+
+    fn resume(&self) {
+        self.uart.lock().enable_receive_interrupt();
+    }
+}
+
+/// COM1-COM4
+static PORTS: [SerialPort; 4] = [
+    SerialPort::new(0x3f8, IRQ_COM1_COM3),
+    SerialPort::new(0x2f8, IRQ_COM2_COM4),
+    SerialPort::new(0x3e8, IRQ_COM1_COM3),
+    SerialPort::new(0x2e8, IRQ_COM2_COM4),
+];
+
+/// Port mirroring kernel console output, COM1
+const CONSOLE: usize = 0;
+
+/// Initializes COM1, so that kernel console output gets mirrored to it. Does nothing if
+/// there is no working COM1.
+///
+/// No other subsystem is required to be initialized yet.
+pub fn init_console() {
+    let _ = PORTS[CONSOLE].uart.lock().init(DEFAULT_BAUD_RATE);
+}
+
+/// Writes formatted text to console port, if it is initialized.
+pub fn console_write_fmt(args: fmt::Arguments) {
+    struct Console;
+
+    impl Write for Console {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            PORTS[CONSOLE].write(s.as_bytes());
+            Ok(())
+        }
+    }
+
+    Console.write_fmt(args).expect("UART: console write failure");
+}
+
+/// Forcibly unlocks console port.
+///
+/// **This function is only meant to be used in panic and fatal exception handlers.**
+pub unsafe fn force_unlock_console() {
+    PORTS[CONSOLE].uart.force_unlock();
+}
+
+pub static DRIVER: DriverInfo = DriverInfo {
+    name: "uart16550",
+    ids: &[DeviceId::Isa("PNP0501")],
+    probe,
+    remove,
+};
+
+/// Initializes serial port, installs it as `tty` device and enables its receive interrupt.
+/// Console port initialized by [`init_console`] is not reset.
+///
+/// [`init_console`]: ./fn.init_console.html
+fn probe(device: &CommonDevice) -> Result<(), ProbeError> {
+    let io_base = device.downcast::<IsaDevice>().io_base;
+    let port = PORTS
+        .iter()
+        .find(|port| port.uart.lock().base() == io_base)
+        .ok_or(ProbeError::NotSupported)?;
+
+    {
+        let mut uart = port.uart.lock();
+        if !uart.initialized {
+            uart.init(DEFAULT_BAUD_RATE).map_err(|error| match error {
+                Error::NotPresent => ProbeError::NotPresent,
+                error => ProbeError::Failed(format!("{}", error)),
+            })?;
+        }
+    }
+
+    // Receive interrupt is enabled only below
+    unsafe { port.init_receive_buffer() };
+    dev::mgr::install_child(&device.name(), box Tty::new(port));
+    port.uart.lock().enable_receive_interrupt();
+
+    let handler: HandlerFunc = if port.irq == IRQ_COM1_COM3 {
+        handle_irq_com1_com3
+    } else {
+        handle_irq_com2_com4
+    };
+    unsafe {
+        register_interrupt(port.irq, handler);
+        pic::enable(port.irq);
+    }
+    Ok(())
+}
+
+/// Nothing is left to do, stopping the `tty` child has masked receive interrupt of the port
+/// already. The interrupt line stays enabled, other ports may share it.
+fn remove(_device: &CommonDevice) {}
+
+/// Deferred part of IRQ handling, passes received bytes to `tty` device of port with index
+/// `arg`.
+fn process_bytes(arg: usize) {
+    let port = &PORTS[arg];
+    // Bytes pushed from now on need another run
+    port.scheduled.store(false, Ordering::Release);
+
+    loop {
+        let byte = match *port.pending.lock() {
+            Some(ref mut consumer) => consumer.pop(),
+            None => None,
+        };
+        let byte = match byte {
+            Some(byte) => byte,
+            None => break,
+        };
+
+        if let Some(ref mut tty) = *port.tty.lock() {
+            tty.receive(byte);
+        }
+    }
+}
+
+/// Drains receive FIFOs of all ports raising given interrupt.
+fn handle_irq(irq: u8) {
+    for (index, port) in PORTS.iter().enumerate().filter(|&(_, port)| port.irq == irq) {
+        unsafe { port.drain_receive_fifo(index) };
+    }
+
+    unsafe {
+        pic::eoi(irq);
+    }
+}
+
+extern "x86-interrupt" fn handle_irq_com1_com3(_stack_frame: &mut ExceptionStackFrame) {
+    handle_irq(IRQ_COM1_COM3);
+}
+
+extern "x86-interrupt" fn handle_irq_com2_com4(_stack_frame: &mut ExceptionStackFrame) {
+    handle_irq(IRQ_COM2_COM4);
+}
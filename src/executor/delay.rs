@@ -0,0 +1,53 @@
+use kio::time::Instant;
+use kio::timer::Timer;
+
+use super::{Future, Poll, Waker};
+
+/// Future which completes after given point in time.
+pub struct Delay {
+    deadline: Instant,
+    timer: Option<Timer>,
+}
+
+impl Delay {
+    /// Creates future which completes at `deadline`.
+    pub fn until(deadline: Instant) -> Delay {
+        Delay {
+            deadline,
+            timer: None,
+        }
+    }
+
+    /// Creates future which completes after `millis` milliseconds.
+    pub fn millis(millis: u64) -> Delay {
+        Delay::until(Instant::now() + millis)
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(&mut self, waker: &Waker) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        // Re-arm timer, task could have been moved to another waker
+        if let Some(timer) = self.timer.take() {
+            timer.cancel();
+        }
+
+        let waker = waker.clone();
+        self.timer = Some(Timer::schedule(self.deadline, move || waker.wake()));
+
+        Poll::Pending
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        if let Some(timer) = self.timer.take() {
+            timer.cancel();
+        }
+    }
+}
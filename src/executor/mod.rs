@@ -0,0 +1,200 @@
+//! Async executor
+//!
+//! Alternative to blocking threads: tasks are futures which are polled by the executor when
+//! they can make progress. Future which cannot complete yet returns `Pending` and stores the
+//! [`Waker`] it has been given, whoever changes its state (possibly an interrupt handler) calls
+//! [`Waker::wake`], which puts the task back in the ready queue.
+//!
+//! Spawned tasks are run by [`run`], which is the idle loop of the boot thread. When there
+//! are no ready tasks, it halts CPU until next interrupt arrives.
+//!
+//! ## Examples
+//!
+//! ```
+//! executor::spawn(Delay::millis(1000));
+//!
+//! let mut events = input::console();
+//! while let Some(event) = executor::block_on(events.next()) {
+//!     println!("{:?}", event);
+//! }
+//! ```
+//!
+//! [`Waker`]: ./struct.Waker.html
+//! [`Waker::wake`]: ./struct.Waker.html#method.wake
+//! [`run`]: ./fn.run.html
+
+mod delay;
+mod waker;
+
+use alloc::{BTreeMap, VecDeque};
+use alloc::arc::Arc;
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use kio;
+use sync::IrqSpinLock;
+use thread;
+
+pub use self::delay::Delay;
+pub use self::waker::{Wake, Waker};
+
+/// Result of polling a future or a stream
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Poll<T> {
+    /// The value is ready
+    Ready(T),
+    /// The value is not ready yet, waker will be called when it is worth polling again
+    Pending,
+}
+
+/// Asynchronous computation producing single value
+pub trait Future {
+    type Output;
+
+    /// Attempts to resolve the future to its final value.
+    ///
+    /// If the value is not ready yet, returns `Pending` and arranges for `waker` to be called
+    /// when the future can make progress. Only the waker from the latest call has to be woken.
+    ///
+    /// Future should not be polled again after it has returned `Ready`.
+    fn poll(&mut self, waker: &Waker) -> Poll<Self::Output>;
+}
+
+impl<F: Future + ?Sized> Future for Box<F> {
+    type Output = F::Output;
+
+    fn poll(&mut self, waker: &Waker) -> Poll<F::Output> {
+        (**self).poll(waker)
+    }
+}
+
+/// Asynchronous sequence of values
+pub trait Stream {
+    type Item;
+
+    /// Attempts to pull out next value of the stream.
+    ///
+    /// Returns `Ready(None)` when the stream has finished, waker is handled same way as
+    /// in [`Future::poll`].
+    ///
+    /// [`Future::poll`]: ./trait.Future.html#tymethod.poll
+    fn poll_next(&mut self, waker: &Waker) -> Poll<Option<Self::Item>>;
+
+    /// Returns future resolving to next value of the stream.
+    fn next(&mut self) -> Next<Self>
+    where
+        Self: Sized,
+    {
+        Next { stream: self }
+    }
+}
+
+/// Future returned by [`Stream::next`]
+///
+/// [`Stream::next`]: ./trait.Stream.html#method.next
+pub struct Next<'a, S: 'a> {
+    stream: &'a mut S,
+}
+
+impl<'a, S: Stream> Future for Next<'a, S> {
+    type Output = Option<S::Item>;
+
+    fn poll(&mut self, waker: &Waker) -> Poll<Option<S::Item>> {
+        self.stream.poll_next(waker)
+    }
+}
+
+/// Unique identifier of a spawned task
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct TaskId(usize);
+
+type Task = Box<Future<Output = ()> + Send>;
+
+lazy_static! {
+    /// Tasks which are not being polled at the moment
+    static ref TASKS: IrqSpinLock<BTreeMap<TaskId, Task>> = IrqSpinLock::new(BTreeMap::new());
+    /// Tasks which have been woken up
+    static ref READY: IrqSpinLock<VecDeque<TaskId>> = IrqSpinLock::new(VecDeque::new());
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+struct TaskWaker(TaskId);
+
+impl Wake for TaskWaker {
+    fn wake(&self) {
+        let mut ready = READY.lock();
+        if !ready.contains(&self.0) {
+            ready.push_back(self.0);
+        }
+    }
+}
+
+/// Spawns new task, which will be polled by the executor loop.
+pub fn spawn<F>(future: F) -> TaskId
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let id = TaskId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+
+    let task: Task = box future;
+    TASKS.lock().insert(id, task);
+    READY.lock().push_back(id);
+
+    id
+}
+
+/// Blocks current thread until `future` resolves, and returns its value.
+///
+/// The future is polled on current thread, which is blocked between polls. It must not be
+/// called from the thread running [`run`], as spawned tasks would not make progress.
+///
+/// [`run`]: ./fn.run.html
+pub fn block_on<F: Future>(mut future: F) -> F::Output {
+    let waker = Waker::for_thread(thread::current());
+
+    loop {
+        if let Poll::Ready(value) = future.poll(&waker) {
+            return value;
+        }
+
+        thread::block();
+    }
+}
+
+/// Runs spawned tasks forever, halting CPU when none of them is ready.
+///
+/// Task woken up right before CPU gets halted waits for the next interrupt, which is at most
+/// one system tick.
+///
+/// **This function should be called only by one thread, the idle one.**
+pub fn run() -> ! {
+    loop {
+        if !poll_ready() {
+            kio::idle();
+        }
+    }
+}
+
+/// Polls all ready tasks. Returns `false` if there were none.
+fn poll_ready() -> bool {
+    let mut polled = false;
+
+    loop {
+        let id = match READY.lock().pop_front() {
+            Some(id) => id,
+            None => return polled,
+        };
+
+        // Task may have already completed
+        let task = TASKS.lock().remove(&id);
+        if let Some(mut task) = task {
+            polled = true;
+
+            let waker = Waker::new(Arc::new(TaskWaker(id)));
+            if let Poll::Pending = task.poll(&waker) {
+                TASKS.lock().insert(id, task);
+            }
+        }
+    }
+}
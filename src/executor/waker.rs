@@ -0,0 +1,50 @@
+use alloc::arc::Arc;
+
+use thread::{self, ThreadId};
+
+/// Something which can be woken up, usually a task or a thread.
+pub trait Wake: Send + Sync {
+    /// Notifies that the woken entity is ready to make progress.
+    ///
+    /// This function has to be safe to call from interrupt handlers.
+    fn wake(&self);
+}
+
+/// Handle used to wake up a task when it is ready to make progress.
+///
+/// Waker is passed to [`Future::poll`], and future which returns `Pending` is responsible
+/// for storing it and calling [`wake`] when its state changes.
+///
+/// [`Future::poll`]: ./trait.Future.html#tymethod.poll
+/// [`wake`]: #method.wake
+#[derive(Clone)]
+pub struct Waker {
+    inner: Arc<Wake>,
+}
+
+impl Waker {
+    /// Creates new waker calling given wake implementation.
+    pub fn new(inner: Arc<Wake>) -> Waker {
+        Waker { inner }
+    }
+
+    /// Creates new waker which wakes up given thread.
+    pub fn for_thread(id: ThreadId) -> Waker {
+        Waker::new(Arc::new(ThreadWaker(id)))
+    }
+
+    /// Wakes up the task associated with this waker.
+    ///
+    /// This function can be called from interrupt handlers.
+    pub fn wake(&self) {
+        self.inner.wake();
+    }
+}
+
+struct ThreadWaker(ThreadId);
+
+impl Wake for ThreadWaker {
+    fn wake(&self) {
+        thread::wake(self.0);
+    }
+}
@@ -0,0 +1,144 @@
+//! Deferred interrupt work (bottom halves)
+//!
+//! Interrupt handlers should be as short as possible: they should only acknowledge the hardware,
+//! grab the data and leave the rest to be processed later. This module provides a queue of such
+//! *deferred work* items. Handlers [`schedule`] work, and the kernel drains the queue in
+//! [`run_pending`] with interrupts enabled, so the work may allocate memory and take locks.
+//!
+//! The queue has fixed capacity and scheduling work never allocates, so it is safe to do it in
+//! interrupt context. Work items run one at a time: while a thread drains the queue, even if it
+//! is preempted, other threads leave it alone.
+//!
+//! [`schedule`]: ./fn.schedule.html
+//! [`run_pending`]: ./fn.run_pending.html
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use sync::IrqSpinLock;
+use thread::{self, ThreadId};
+
+/// Maximum number of pending work items
+const QUEUE_SIZE: usize = 256;
+
+/// Single unit of deferred work, a function with an argument.
+#[derive(Copy, Clone)]
+struct Work {
+    func: fn(usize),
+    arg: usize,
+}
+
+struct WorkQueue {
+    items: [Option<Work>; QUEUE_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl WorkQueue {
+    const fn new() -> WorkQueue {
+        WorkQueue {
+            items: [None; QUEUE_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, work: Work) -> bool {
+        if self.len == QUEUE_SIZE {
+            return false;
+        }
+
+        let tail = (self.head + self.len) % QUEUE_SIZE;
+        self.items[tail] = Some(work);
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) -> Option<Work> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let work = self.items[self.head].take();
+        self.head = (self.head + 1) % QUEUE_SIZE;
+        self.len -= 1;
+        work
+    }
+}
+
+static QUEUE: IrqSpinLock<WorkQueue> = IrqSpinLock::new(WorkQueue::new());
+
+/// Number of work items lost due to queue overflow.
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Thread draining the queue, `None` before threading is initialized
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct Runner(Option<ThreadId>);
+
+/// Set while some thread drains the queue.
+static RUNNER: IrqSpinLock<Option<Runner>> = IrqSpinLock::new(None);
+
+/// Schedules `func(arg)` to be called later, outside of interrupt context.
+///
+/// Returns `false` if the queue is full, in which case work is dropped.
+///
+/// This function never allocates nor blocks, and can be called from interrupt handlers.
+///
+/// Work runs on whichever thread drains the queue, so it must not block either. Blocking from
+/// deferred work panics.
+pub fn schedule(func: fn(usize), arg: usize) -> bool {
+    let ok = QUEUE.lock().push(Work { func, arg });
+    if !ok {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+    ok
+}
+
+/// Returns `true` if there is any work waiting to be run.
+pub fn has_pending() -> bool {
+    QUEUE.lock().len != 0
+}
+
+/// Returns `true` if called from deferred work. Other threads running while deferred work
+/// is preempted get `false`.
+pub fn is_running() -> bool {
+    let current = Runner(thread::try_current());
+    *RUNNER.lock() == Some(current)
+}
+
+/// Returns number of work items lost due to queue overflow so far.
+pub fn dropped() -> usize {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// Runs all pending work, including work scheduled while this function runs. Does nothing if
+/// another thread is running work already, it will run the rest once it is scheduled again.
+///
+/// **Must not be called from interrupt handlers.**
+pub fn run_pending() {
+    let current = Runner(thread::try_current());
+    let nested = {
+        let mut runner = RUNNER.lock();
+        let state = *runner;
+        match state {
+            // Work busy-waits for something, keep draining on its behalf
+            Some(other) if other == current => true,
+            Some(_) => return,
+            None => {
+                *runner = Some(current);
+                false
+            }
+        }
+    };
+
+    loop {
+        let work = QUEUE.lock().pop();
+        match work {
+            Some(work) => (work.func)(work.arg),
+            None => break,
+        }
+    }
+
+    if !nested {
+        *RUNNER.lock() = None;
+    }
+}
@@ -5,14 +5,17 @@ use core::mem;
 use spin::{Mutex, Once};
 use x86_64;
 use x86_64::VirtualAddress;
+use x86_64::registers::control_regs;
 use x86_64::structures::gdt::SegmentSelector;
 use x86_64::structures::idt::{ExceptionStackFrame, HandlerFunc, Idt, PageFaultErrorCode};
 use x86_64::structures::tss::TaskStateSegment;
 
 use dev::text_video::{TextColor, TextStyle};
 use kio;
-use mem::alloc_stack;
+use mem::{alloc_stack, copy_on_write};
 use mem::gdt::{self, Gdt};
+use proc;
+use thread;
 
 const DOUBLE_FAULT_IST_INDEX: usize = 0;
 const MACHINE_CHECK_IST_INDEX: usize = 1;
@@ -20,7 +23,23 @@ const MACHINE_CHECK_IST_INDEX: usize = 1;
 static IDT: Mutex<Option<Idt>> = Mutex::new(None);
 
 static GDT: Once<Gdt> = Once::new();
-static TSS: Once<TaskStateSegment> = Once::new();
+static SELECTORS: Once<Selectors> = Once::new();
+
+/// Task State Segment, mutable because kernel stack pointer is updated on every thread switch
+static mut TSS: Option<TaskStateSegment> = None;
+
+/// Segment selectors of kernel's GDT
+///
+/// The order of segments is dictated by `syscall`/`sysret` instructions, which expect kernel
+/// data right after kernel code, and user data right before user code.
+#[derive(Debug, Copy, Clone)]
+pub struct Selectors {
+    pub kernel_code: SegmentSelector,
+    pub kernel_data: SegmentSelector,
+    pub user_data: SegmentSelector,
+    pub user_code: SegmentSelector,
+    pub tss: SegmentSelector,
+}
 
 /// Initializes kernel's Interrupt Descriptor Table.
 ///
@@ -35,29 +54,54 @@ pub unsafe fn init() {
     let double_fault_stack =
         alloc_stack(1).expect("could not allocate double fault interrupt handler stack");
 
-    let tss = TSS.call_once(|| {
+    TSS = Some({
         let mut tss = TaskStateSegment::new();
         tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX] = VirtualAddress(double_fault_stack.top);
         tss
     });
+    let tss = TSS.as_ref().unwrap();
 
-    let mut code_selector = SegmentSelector(0);
-    let mut tss_selector = SegmentSelector(0);
+    let mut selectors = None;
     let gdt = GDT.call_once(|| {
         let mut gdt = Gdt::new();
-        code_selector = gdt.add_entry(gdt::Descriptor::kernel_code_segment());
-        tss_selector = gdt.add_entry(gdt::Descriptor::tss_segment(&tss));
+        selectors = Some(Selectors {
+            kernel_code: gdt.add_entry(gdt::Descriptor::kernel_code_segment()),
+            kernel_data: gdt.add_entry(gdt::Descriptor::kernel_data_segment()),
+            user_data: gdt.add_entry(gdt::Descriptor::user_data_segment()),
+            user_code: gdt.add_entry(gdt::Descriptor::user_code_segment()),
+            tss: gdt.add_entry(gdt::Descriptor::tss_segment(tss)),
+        });
         gdt
     });
+    let selectors = SELECTORS.call_once(|| selectors.unwrap());
 
     gdt.load();
 
-    x86_64::instructions::segmentation::set_cs(code_selector);
-    x86_64::instructions::tables::load_tss(tss_selector);
+    x86_64::instructions::segmentation::set_cs(selectors.kernel_code);
+    x86_64::instructions::tables::load_tss(selectors.tss);
 
     load_idt();
 }
 
+/// Returns segment selectors of kernel's GDT.
+///
+/// ## Panics
+///
+/// Panics if IDT has not been initialized yet.
+pub fn selectors() -> &'static Selectors {
+    SELECTORS.try().expect("GDT has not been initialized yet")
+}
+
+/// Sets the stack which CPU switches to when an interrupt or exception arrives in user mode.
+///
+/// **Interrupts are required to be disabled, this function is meant to be called by scheduler
+/// on every thread switch.**
+pub unsafe fn set_kernel_stack(stack_top: usize) {
+    if let Some(ref mut tss) = TSS {
+        tss.privilege_stack_table[0] = VirtualAddress(stack_top);
+    }
+}
+
 /// Registers handler function for custom interrupts (INTn >= 32)
 ///
 /// **IDT has to be initialized before calling this function.**
@@ -81,6 +125,41 @@ pub unsafe fn disable() {
     x86_64::instructions::interrupts::disable();
 }
 
+/// Returns `true` if maskable interrupts are enabled (`IF` flag is set).
+pub fn are_enabled() -> bool {
+    const IF: u64 = 1 << 9;
+
+    let rflags: u64;
+    unsafe {
+        asm!("pushfq; pop $0" : "=r"(rflags) : : "memory" : "volatile");
+    }
+    rflags & IF != 0
+}
+
+/// Runs `f` with interrupts disabled, restoring previous interrupt state afterwards.
+pub fn without_interrupts<R>(f: impl FnOnce() -> R) -> R {
+    let were_enabled = are_enabled();
+    if were_enabled {
+        unsafe { disable() };
+    }
+
+    let result = f();
+
+    if were_enabled {
+        unsafe { enable() };
+    }
+
+    result
+}
+
+/// Atomically enables interrupts and halts CPU until next interrupt.
+///
+/// Because of `sti` instruction semantics, no interrupt can arrive between enabling and halting,
+/// so it is safe to check for pending work with interrupts disabled and then call this function.
+pub unsafe fn enable_and_halt() {
+    asm!("sti; hlt" : : : "memory" : "volatile");
+}
+
 fn load_idt() {
     // WTF
     let mut idt_lock = IDT.lock();
@@ -114,13 +193,13 @@ fn create_idt() -> Idt {
     // TODO: Invalid TSS
     // TODO: Segment Not Present
     // TODO: Stack Segment Fault
-    // TODO: General Protection Fault
+    idt.general_protection_fault
+        .set_handler_fn(general_protection_fault_handler);
 
-    unsafe {
-        idt.page_fault
-            .set_handler_fn(page_fault_handler)
-            .set_stack_index(DOUBLE_FAULT_IST_INDEX as u16);
-    }
+    // Page faults are handled on the stack of faulting thread, as resolving copy-on-write
+    // faults needs more than the small IST stack, and killing the faulting process switches
+    // threads. Kernel stack overflow can not push the exception frame and ends in double fault.
+    idt.page_fault.set_handler_fn(page_fault_handler);
 
     // TODO: x87 Floating-Point Exception
     // TODO: Alignment Check Exception
@@ -143,7 +222,8 @@ fn create_idt() -> Idt {
 }
 
 extern "x86-interrupt" fn divide_by_zero_handler(stack_frame: &mut ExceptionStackFrame) {
-    print_exception("DIVIDE BY ZERO", stack_frame);
+    kill_user_task("DIVIDE BY ZERO", stack_frame);
+    print_fatal_exception("DIVIDE BY ZERO", stack_frame);
     loop {}
 }
 
@@ -152,12 +232,26 @@ extern "x86-interrupt" fn breakpoint_handler(stack_frame: &mut ExceptionStackFra
 }
 
 extern "x86-interrupt" fn overflow_handler(stack_frame: &mut ExceptionStackFrame) {
-    print_exception("OVERFLOW", stack_frame);
+    kill_user_task("OVERFLOW", stack_frame);
+    print_fatal_exception("OVERFLOW", stack_frame);
     loop {}
 }
 
 extern "x86-interrupt" fn invalid_opcode_handler(stack_frame: &mut ExceptionStackFrame) {
-    print_exception("INVALID OPCODE", stack_frame);
+    kill_user_task("INVALID OPCODE", stack_frame);
+    print_fatal_exception("INVALID OPCODE", stack_frame);
+    loop {}
+}
+
+extern "x86-interrupt" fn general_protection_fault_handler(
+    stack_frame: &mut ExceptionStackFrame,
+    error_code: u64,
+) {
+    kill_user_task("GENERAL PROTECTION FAULT", stack_frame);
+    print_fatal_exception_ex("GENERAL PROTECTION FAULT", || {
+        println!("Error code: {:#x}", error_code);
+        println!("{:#?}", stack_frame);
+    });
     loop {}
 }
 
@@ -165,7 +259,7 @@ extern "x86-interrupt" fn double_fault_handler(
     stack_frame: &mut ExceptionStackFrame,
     _error_code: u64,
 ) {
-    print_exception("DOUBLE FAULT", stack_frame);
+    print_fatal_exception("DOUBLE FAULT", stack_frame);
     loop {}
 }
 
@@ -173,7 +267,19 @@ extern "x86-interrupt" fn page_fault_handler(
     stack_frame: &mut ExceptionStackFrame,
     error_code: PageFaultErrorCode,
 ) {
-    print_exception_ex("PAGE FAULT", || {
+    let address = control_regs::cr2().0;
+
+    // Write to a page shared by forked processes gets its own copy of the page
+    let cow_fault = PageFaultErrorCode::PROTECTION_VIOLATION | PageFaultErrorCode::CAUSED_BY_WRITE;
+    if error_code.contains(cow_fault) && address >= proc::USER_START && address < proc::USER_END
+        && copy_on_write(address)
+    {
+        return;
+    }
+
+    kill_user_task("PAGE FAULT", stack_frame);
+    print_fatal_exception_ex("PAGE FAULT", || {
+        println!("Address: {:#x}", address);
         println!("Error code: {:#?}", error_code);
         println!("{:#?}", stack_frame);
     });
@@ -181,18 +287,57 @@ extern "x86-interrupt" fn page_fault_handler(
 }
 
 extern "x86-interrupt" fn machine_check_handler(stack_frame: &mut ExceptionStackFrame) {
-    print_exception("OOPS MACHINE CHECK", stack_frame);
+    print_fatal_exception("OOPS MACHINE CHECK", stack_frame);
     loop {}
 }
 
 extern "x86-interrupt" fn default_handler(_stack_frame: &mut ExceptionStackFrame) {}
 
+/// Returns `true` if the interrupted code has been running in user mode.
+pub fn from_user_mode(stack_frame: &ExceptionStackFrame) -> bool {
+    // Privilege level of interrupted code is in the lowest bits of its code segment selector
+    stack_frame.code_segment & 0b11 == 3
+}
+
+/// Terminates current thread if the exception has been raised by user mode code.
+/// Faulty user program should not bring the whole kernel down.
+fn kill_user_task(name: &str, stack_frame: &ExceptionStackFrame) {
+    if !from_user_mode(stack_frame) {
+        return;
+    }
+
+    println!(
+        "thread {} killed: {} at {:#x}",
+        thread::current(),
+        name,
+        stack_frame.instruction_pointer.0
+    );
+
+    proc::exit(proc::EXIT_FAULT);
+}
+
 fn print_exception(name: &str, stack_frame: &ExceptionStackFrame) {
     print_exception_ex(name, || {
         println!("{:#?}", stack_frame);
     });
 }
 
+fn print_fatal_exception(name: &str, stack_frame: &ExceptionStackFrame) {
+    print_fatal_exception_ex(name, || {
+        println!("{:#?}", stack_frame);
+    });
+}
+
+/// Prints exception info from a handler which never returns.
+fn print_fatal_exception_ex(name: &str, info_provider: impl FnOnce()) {
+    // Exception could have been raised by code holding output lock, e.g. a page fault inside
+    // video driver. Waiting for the lock would deadlock, and the interrupted code will never
+    // resume, so we take the lock over unconditionally.
+    unsafe { kio::force_unlock_output() };
+
+    print_exception_ex(name, info_provider);
+}
+
 fn print_exception_ex(name: &str, info_provider: impl FnOnce()) {
     let header = TextStyle {
         foreground: TextColor::White,
@@ -3,18 +3,28 @@
 #[macro_use]
 mod macros;
 
+pub mod defer;
 pub mod idt;
 pub mod pic;
+pub mod pit;
 pub mod port;
+pub mod time;
+pub mod timer;
 
 use core::fmt::{self, Write};
 
+use x86_64::instructions::tables::{lidt, DescriptorTablePointer};
+
 use dev::text_video::{TextStyle, TextVideo};
 use dev::output_serial::OutputSerial;
 use drv::gfx::vga::text_buffer::VGA_TEXT_VIDEO;
+use drv::serial::uart16550;
+use kio::port::UnsafePort;
+use thread;
 
 /// Performs early initialization of KIO subsystem, setting up
-/// so called *early console* which enables usage of [`println!`] family macros.
+/// so called *early console* which enables usage of [`println!`] family macros. Console output
+/// goes to VGA text buffer and is mirrored to COM1 serial port, if there is one.
 ///
 /// No other subsystem is required to be initialized yet.
 ///
@@ -28,6 +38,8 @@ pub unsafe fn early_init() {
         video.clear();
     }
 
+    uart16550::init_console();
+
     println!("early console works");
 }
 
@@ -64,6 +76,71 @@ pub fn with_output_style(text_style: TextStyle, f: impl FnOnce()) {
     }
 }
 
+/// Lets other threads run, or if there are none ready, halts CPU until next interrupt arrives.
+/// Then runs all the [deferred work] which interrupt handlers have left to be done, like
+/// processing keyboard input or running expired timers.
+///
+/// CPU is not halted if there is deferred work already waiting.
+///
+/// This function should be called by all kernel busy-wait loops.
+///
+/// [deferred work]: ./defer/index.html
+pub fn idle() {
+    if !thread::yield_now() {
+        unsafe {
+            idt::disable();
+            if defer::has_pending() {
+                idt::enable();
+            } else {
+                idt::enable_and_halt();
+            }
+        }
+    }
+
+    defer::run_pending();
+}
+
+/// Restarts the machine.
+///
+/// Pulses CPU reset line through PS/2 controller, which is supported by all PCs. If that
+/// does not work, causes triple fault by raising an exception with empty IDT loaded.
+pub fn reboot() -> ! {
+    /// Status register on read, command register on write
+    const PS2_STATUS_PORT: UnsafePort<u8> = unsafe { UnsafePort::new(0x64) };
+    const STATUS_INPUT_FULL: u8 = 1 << 1;
+    const CMD_PULSE_RESET: u8 = 0xfe;
+
+    unsafe {
+        idt::disable();
+
+        for _ in 0..100_000 {
+            if PS2_STATUS_PORT.read() & STATUS_INPUT_FULL == 0 {
+                break;
+            }
+        }
+        PS2_STATUS_PORT.write(CMD_PULSE_RESET);
+
+        // Give the controller some time, every port access takes about a microsecond
+        for _ in 0..100_000 {
+            PS2_STATUS_PORT.read();
+        }
+
+        lidt(&DescriptorTablePointer { base: 0, limit: 0 });
+        asm!("int3" : : : : "volatile");
+    }
+
+    loop {}
+}
+
+/// Forcibly unlocks kernel output device, so that panic and fatal exception handlers are
+/// able to print messages even if they have interrupted code which was printing something.
+///
+/// **This function is only meant to be used in panic and fatal exception handlers.**
+pub unsafe fn force_unlock_output() {
+    VGA_TEXT_VIDEO.force_unlock();
+    uart16550::force_unlock_console();
+}
+
 #[doc(hidden)]
 pub fn _print(args: fmt::Arguments) {
     VGA_TEXT_VIDEO
@@ -71,4 +148,6 @@ pub fn _print(args: fmt::Arguments) {
         .writer()
         .write_fmt(args)
         .expect("KIO: Kernel Output write failure");
+
+    uart16550::console_write_fmt(args);
 }
@@ -3,8 +3,11 @@
 //! This module configures master and slave PICs to signal IRQs 0-15
 //! as IRQs 32-48. It also provides primitives for handling these interrupts.
 
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use kio::port::Port;
 use kio::port::UnsafePort;
+use sync::IrqSpinLock;
 
 const MASTER_OFFSET: u8 = 0x20;
 const SLAVE_OFFSET: u8 = MASTER_OFFSET + 8;
@@ -57,12 +60,25 @@ const ICW4_BUF_MASTER: u8 = 0x0C;
 /// Special fully nested (not)
 const ICW4_SFNM: u8 = 0x10;
 
+/// Guards read-modify-write sequences on PIC registers.
+static LOCK: IrqSpinLock<()> = IrqSpinLock::new(());
+
+/// Number of handled interrupts of every IRQ line
+static COUNTS: [AtomicUsize; 16] = [
+    AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0),
+    AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0),
+    AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0),
+    AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0),
+];
+
 /// Initializes PIC
 ///
 /// **This function should be only called once.**
 ///
 /// **IDT is required to be initialized.**
 pub unsafe fn init() {
+    let _guard = LOCK.lock();
+
     // Tell each PIC that we're going to send it a three-byte
     // initialization sequence on its data port.
     MASTER_CMD.write(ICW1_INIT + ICW1_ICW4);
@@ -98,6 +114,9 @@ pub unsafe fn init() {
 /// Notifies end of interrupt
 pub unsafe fn eoi(irq: u8) {
     assert!(valid_irq(irq));
+    COUNTS[(irq - MASTER_OFFSET) as usize].fetch_add(1, Ordering::Relaxed);
+
+    let _guard = LOCK.lock();
 
     if irq >= SLAVE_OFFSET {
         SLAVE_CMD.write(PIC_EOI);
@@ -106,9 +125,19 @@ pub unsafe fn eoi(irq: u8) {
     MASTER_CMD.write(PIC_EOI);
 }
 
+/// Returns number of interrupts handled so far on every IRQ line, indexed by line number.
+pub fn counts() -> [usize; 16] {
+    let mut counts = [0; 16];
+    for (count, counter) in counts.iter_mut().zip(COUNTS.iter()) {
+        *count = counter.load(Ordering::Relaxed);
+    }
+    counts
+}
+
 /// Clears mask of IRQ in IMR (enables)
 pub unsafe fn enable(irq: u8) {
     assert!(valid_irq(irq));
+    let _guard = LOCK.lock();
     let (port, irqline) = get_data_port_and_irqline(irq);
     let val = port.read() & !(1u8 << irqline);
     port.write(val);
@@ -117,6 +146,7 @@ pub unsafe fn enable(irq: u8) {
 /// Sets mask of IRQ in IMR (disables)
 pub unsafe fn disable(irq: u8) {
     assert!(valid_irq(irq));
+    let _guard = LOCK.lock();
     let (port, irqline) = get_data_port_and_irqline(irq);
     let val = port.read() | (1u8 << irqline);
     port.write(val);
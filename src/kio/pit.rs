@@ -0,0 +1,62 @@
+//! Intel 8253/8254 Programmable Interval Timer driver
+//!
+//! Channel 0 of the PIT is programmed to fire IRQ0 with frequency of [`TICK_HZ`],
+//! which is the source of kernel system tick.
+//!
+//! [`TICK_HZ`]: ../time/constant.TICK_HZ.html
+
+use x86_64::structures::idt::ExceptionStackFrame;
+
+use kio::idt::{self, register_interrupt};
+use kio::pic;
+use kio::port::UnsafePort;
+use kio::time::{self, TICK_HZ};
+use kio::timer;
+use proc;
+use thread;
+
+const IRQ: u8 = 32;
+
+/// Channel 0 data port (read/write)
+const CHANNEL0_PORT: UnsafePort<u8> = unsafe { UnsafePort::new(0x40) };
+
+/// Mode/Command register (write only, a read is ignored)
+const CMD_PORT: UnsafePort<u8> = unsafe { UnsafePort::new(0x43) };
+
+/// Frequency of PIT oscillator in Hz
+const BASE_FREQUENCY: u64 = 1_193_182;
+
+/// Channel 0, access mode lobyte/hibyte, mode 3 (square wave generator), binary mode
+const CMD_CHANNEL0_SQUARE_WAVE: u8 = 0b0011_0110;
+
+/// Programs PIT to generate system ticks and starts handling IRQ0.
+///
+/// **This function should be only called once.**
+///
+/// **IDT and PIC are required to be initialized.**
+pub unsafe fn init() {
+    let divisor = (BASE_FREQUENCY / TICK_HZ) as u16;
+
+    CMD_PORT.write(CMD_CHANNEL0_SQUARE_WAVE);
+    CHANNEL0_PORT.write((divisor & 0xff) as u8);
+    CHANNEL0_PORT.write((divisor >> 8) as u8);
+
+    register_interrupt(IRQ, handle_irq);
+    pic::enable(IRQ);
+}
+
+extern "x86-interrupt" fn handle_irq(stack_frame: &mut ExceptionStackFrame) {
+    time::tick();
+    timer::on_tick();
+    unsafe {
+        pic::eoi(IRQ);
+    }
+
+    // This may switch to other thread, so interrupt has to be acknowledged already.
+    thread::on_tick();
+
+    // Killed process spinning in user mode would never enter the kernel on its own
+    if idt::from_user_mode(stack_frame) {
+        unsafe { proc::exit_on_return_if_killed(stack_frame) };
+    }
+}
@@ -0,0 +1,68 @@
+//! Kernel time keeping
+//!
+//! Time is measured in system ticks counted since PIT initialization. Ticks are generated
+//! with frequency of [`TICK_HZ`], so single tick lasts exactly one millisecond.
+//!
+//! [`TICK_HZ`]: ./constant.TICK_HZ.html
+
+use core::ops::{Add, Sub};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// System tick frequency in Hz
+pub const TICK_HZ: u64 = 1000;
+
+static TICKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Represents point in time, with millisecond precision.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Instant(u64);
+
+impl Instant {
+    /// Returns current point in time.
+    pub fn now() -> Instant {
+        Instant(ticks())
+    }
+
+    /// Returns point in time specified number of milliseconds after system start.
+    pub const fn from_millis(millis: u64) -> Instant {
+        Instant(millis)
+    }
+
+    /// Returns number of milliseconds elapsed since system start till this point in time.
+    pub const fn as_millis(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns number of milliseconds elapsed since this point in time.
+    pub fn elapsed_millis(&self) -> u64 {
+        ticks().saturating_sub(self.0)
+    }
+}
+
+impl Add<u64> for Instant {
+    type Output = Instant;
+
+    /// Returns point in time `rhs` milliseconds after `self`.
+    fn add(self, rhs: u64) -> Instant {
+        Instant(self.0 + rhs)
+    }
+}
+
+impl Sub<u64> for Instant {
+    type Output = Instant;
+
+    /// Returns point in time `rhs` milliseconds before `self`.
+    fn sub(self, rhs: u64) -> Instant {
+        Instant(self.0 - rhs)
+    }
+}
+
+/// Returns number of system ticks since PIT initialization.
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed) as u64
+}
+
+/// Advances system tick counter, called by PIT interrupt handler.
+pub(super) fn tick() {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
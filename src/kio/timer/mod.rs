@@ -0,0 +1,111 @@
+//! Kernel timers
+//!
+//! Timers allow scheduling callbacks to be called at some point in the future. Pending timers
+//! are kept in hierarchical timing wheel, which is advanced with system tick. Callbacks are never
+//! called from interrupt context, instead every tick schedules [`run_expired`] as deferred work,
+//! so they may freely allocate memory or take locks.
+//!
+//! ## Examples
+//!
+//! ```
+//! use kio::time::Instant;
+//! use kio::timer::Timer;
+//!
+//! let timer = Timer::schedule(Instant::now() + 500, || {
+//!     println!("half a second has passed");
+//! });
+//!
+//! // Changed mind
+//! timer.cancel();
+//! ```
+//!
+//! [`run_expired`]: ./fn.run_expired.html
+
+mod wheel;
+
+use alloc::Vec;
+use alloc::boxed::{Box, FnBox};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use kio::defer;
+use kio::time::{self, Instant};
+use sync::IrqSpinLock;
+
+use self::wheel::{TimerId, Wheel};
+
+type Callback = Box<FnBox() + Send>;
+
+lazy_static! {
+    static ref WHEEL: IrqSpinLock<Wheel<Callback>> = IrqSpinLock::new(Wheel::new(time::ticks()));
+}
+
+/// Set if [`run_expired`] is already waiting in deferred work queue.
+///
+/// [`run_expired`]: ./fn.run_expired.html
+static RUN_SCHEDULED: AtomicBool = AtomicBool::new(false);
+
+/// Handle to scheduled timer
+#[derive(Debug)]
+#[must_use = "timer is scheduled anyway, but it cannot be cancelled without its handle"]
+pub struct Timer {
+    id: TimerId,
+}
+
+impl Timer {
+    /// Schedules `callback` to be called once `deadline` passes.
+    ///
+    /// If `deadline` has already passed, callback will be called on next [`run_expired`] call.
+    ///
+    /// [`run_expired`]: ./fn.run_expired.html
+    pub fn schedule<F>(deadline: Instant, callback: F) -> Timer
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let callback: Callback = box callback;
+        let id = WHEEL.lock().insert(deadline.as_millis(), callback);
+        Timer { id }
+    }
+
+    /// Schedules `callback` to be called after `millis` milliseconds.
+    pub fn after_millis<F>(millis: u64, callback: F) -> Timer
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        Timer::schedule(Instant::now() + millis, callback)
+    }
+
+    /// Cancels timer, so its callback will never be called.
+    ///
+    /// Returns `false` if timer has already expired.
+    pub fn cancel(self) -> bool {
+        WHEEL.lock().cancel(self.id).is_some()
+    }
+}
+
+/// Schedules expired timers processing, called by PIT interrupt handler on every tick.
+pub(super) fn on_tick() {
+    if !RUN_SCHEDULED.swap(true, Ordering::Acquire) && !defer::schedule(run_expired_work, 0) {
+        // queue is full, retry on the next tick
+        RUN_SCHEDULED.store(false, Ordering::Release);
+    }
+}
+
+fn run_expired_work(_: usize) {
+    RUN_SCHEDULED.store(false, Ordering::Release);
+    run_expired();
+}
+
+/// Calls callbacks of all timers which have expired till now.
+///
+/// Callbacks are called without wheel lock held, so they are free to schedule new timers.
+///
+/// **Must not be called from interrupt handlers.**
+pub fn run_expired() {
+    let mut expired = Vec::new();
+
+    WHEEL.lock().advance(time::ticks(), &mut expired);
+
+    for callback in expired {
+        callback();
+    }
+}
@@ -0,0 +1,183 @@
+//! Hierarchical timing wheel
+//!
+//! The wheel consists of [`LEVELS`] levels of [`SLOTS`] slots each. Level `L` slot covers
+//! `SLOTS^L` ticks, so level 0 has one-tick resolution, while each next level is `SLOTS` times
+//! coarser. Timers are always put into the finest level capable to hold them, and are
+//! *cascaded* down to finer levels when wheel time reaches the range of their slot.
+//! Timers which are too far in the future to fit in the wheel at all are kept on overflow list,
+//! which is revisited every time the whole wheel turns around.
+//!
+//! This gives `O(1)` insertion and amortized `O(1)` expiration per timer.
+//!
+//! [`LEVELS`]: ./constant.LEVELS.html
+//! [`SLOTS`]: ./constant.SLOTS.html
+
+use alloc::{BTreeMap, Vec};
+use core::mem;
+
+const SLOT_BITS: usize = 6;
+
+/// Number of slots in single wheel level
+pub const SLOTS: usize = 1 << SLOT_BITS;
+
+/// Number of wheel levels
+pub const LEVELS: usize = 4;
+
+const SLOT_MASK: u64 = SLOTS as u64 - 1;
+
+/// Identifier of timer stored in wheel
+pub type TimerId = u64;
+
+struct Entry<T> {
+    deadline: u64,
+    payload: T,
+}
+
+/// Hierarchical timing wheel storing payloads of type `T`
+pub struct Wheel<T> {
+    /// Last tick which has been fully processed.
+    current: u64,
+    /// `LEVELS * SLOTS` slots, each holding identifiers of timers.
+    slots: Vec<Vec<TimerId>>,
+    /// Timers too far in the future to fit in wheel.
+    overflow: Vec<TimerId>,
+    /// Timers whose deadline has already passed at the time of insertion.
+    due: Vec<TimerId>,
+    /// Actual timer data, slots refer to it by id. Cancelled timers are removed from here only,
+    /// leaving dangling ids in slots which are skipped lazily.
+    entries: BTreeMap<TimerId, Entry<T>>,
+    next_id: TimerId,
+}
+
+impl<T> Wheel<T> {
+    /// Creates new, empty wheel with time set to `current` tick.
+    pub fn new(current: u64) -> Wheel<T> {
+        Wheel {
+            current,
+            slots: (0..LEVELS * SLOTS).map(|_| Vec::new()).collect(),
+            overflow: Vec::new(),
+            due: Vec::new(),
+            entries: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Inserts new timer which should expire at `deadline` tick.
+    ///
+    /// If `deadline` has already passed, timer will expire on next [`advance`] call.
+    ///
+    /// [`advance`]: #method.advance
+    pub fn insert(&mut self, deadline: u64, payload: T) -> TimerId {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.entries.insert(id, Entry { deadline, payload });
+        self.place(id, deadline);
+
+        id
+    }
+
+    /// Removes pending timer from wheel, returning its payload.
+    ///
+    /// Returns `None` if timer has already expired or has been cancelled.
+    pub fn cancel(&mut self, id: TimerId) -> Option<T> {
+        self.entries.remove(&id).map(|e| e.payload)
+    }
+
+    /// Advances wheel time up to `target` tick (inclusive), pushing payloads of all
+    /// expired timers to `expired`, in order of their deadlines.
+    pub fn advance(&mut self, target: u64, expired: &mut Vec<T>) {
+        let due = mem::replace(&mut self.due, Vec::new());
+        self.collect(due, expired);
+
+        if self.entries.is_empty() {
+            // Nothing to wait for, so we can skip turning the wheel tick by tick.
+            // Slots may still contain ids of cancelled timers, drop them.
+            for slot in self.slots.iter_mut() {
+                slot.clear();
+            }
+            self.overflow.clear();
+            if self.current < target {
+                self.current = target;
+            }
+            return;
+        }
+
+        while self.current < target {
+            self.current += 1;
+
+            if self.current & level_mask(LEVELS) == 0 {
+                let overflow = mem::replace(&mut self.overflow, Vec::new());
+                for id in overflow {
+                    self.reinsert(id);
+                }
+            }
+
+            for level in (1..LEVELS).rev() {
+                if self.current & level_mask(level) == 0 {
+                    let index = slot_index(level, self.current);
+                    let ids = mem::replace(&mut self.slots[index], Vec::new());
+                    for id in ids {
+                        self.reinsert(id);
+                    }
+                }
+            }
+
+            let index = slot_index(0, self.current);
+            let ids = mem::replace(&mut self.slots[index], Vec::new());
+            self.collect(ids, expired);
+
+            let due = mem::replace(&mut self.due, Vec::new());
+            self.collect(due, expired);
+        }
+    }
+
+    fn collect(&mut self, ids: Vec<TimerId>, expired: &mut Vec<T>) {
+        for id in ids {
+            if let Some(entry) = self.entries.remove(&id) {
+                expired.push(entry.payload);
+            }
+        }
+    }
+
+    /// Puts already existing timer into proper slot, relatively to current wheel time.
+    fn reinsert(&mut self, id: TimerId) {
+        let deadline = match self.entries.get(&id) {
+            Some(entry) => entry.deadline,
+            // Timer has been cancelled
+            None => return,
+        };
+        self.place(id, deadline);
+    }
+
+    fn place(&mut self, id: TimerId, deadline: u64) {
+        if deadline <= self.current {
+            self.due.push(id);
+            return;
+        }
+
+        let delta = deadline - self.current;
+        for level in 0..LEVELS {
+            if delta <= level_mask(level + 1) {
+                let index = slot_index(level, deadline);
+                self.slots[index].push(id);
+                return;
+            }
+        }
+
+        self.overflow.push(id);
+    }
+}
+
+/// Returns mask of tick bits below given level, i.e. `SLOTS^level - 1`.
+#[inline]
+fn level_mask(level: usize) -> u64 {
+    (1u64 << (SLOT_BITS * level)) - 1
+}
+
+/// Returns index in flat slot table of slot at given level, which covers given tick.
+#[inline]
+fn slot_index(level: usize, tick: u64) -> usize {
+    let slot = (tick >> (SLOT_BITS * level)) & SLOT_MASK;
+    level * SLOTS + slot as usize
+}
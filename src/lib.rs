@@ -4,11 +4,14 @@
 #![feature(asm)]
 #![feature(box_patterns)]
 #![feature(box_syntax)]
+#![feature(const_atomic_bool_new)]
 #![feature(const_atomic_usize_new)]
 #![feature(const_fn)]
 #![feature(const_unique_new)]
 #![feature(const_unsafe_cell_new)]
+#![feature(fnbox)]
 #![feature(global_allocator)]
+#![feature(global_asm)]
 #![feature(lang_items)]
 #![feature(slice_patterns)]
 #![feature(unique)]
@@ -38,15 +41,18 @@ pub mod kio;
 
 pub mod dev;
 pub mod drv;
+pub mod executor;
 pub mod mem;
+pub mod proc;
 pub mod shell;
-
-use linked_list_allocator::LockedHeap;
+pub mod sync;
+pub mod thread;
 
 use dev::text_video::{TextColor, TextStyle};
+use mem::alloc::KernelHeap;
 
 #[global_allocator]
-static HEAP_ALLOCATOR: LockedHeap = LockedHeap::empty();
+static HEAP_ALLOCATOR: KernelHeap = KernelHeap::empty();
 
 /// Real kernel entry point
 #[no_mangle]
@@ -70,18 +76,42 @@ pub extern "C" fn krnl_main(mb_info_addr: usize) {
         mem::init(boot_info);
         kio::idt::init();
         kio::pic::init();
+        kio::pit::init();
+        proc::init();
         kio::idt::enable();
     }
 
     // ATTENTION: now everything is fine
 
+    proc::programs::register_boot_modules(boot_info);
+
     dev::mgr::init();
 
-    drv::hid::atkbd::init();
+    drv::init();
+
+    if let Some(cmdline) = cmdline {
+        apply_cmdline(cmdline);
+    }
+
+    thread::init();
 
-    shell::start();
+    thread::spawn_named("shell", shell::start);
 
-    unreachable!();
+    // Boot thread becomes the idle thread, it runs async tasks and deferred work, and halts
+    // CPU if there is nothing else to do.
+    executor::run();
+}
+
+/// Applies `name=value` options given on kernel command line.
+fn apply_cmdline(cmdline: &str) {
+    for option in cmdline.split_whitespace() {
+        let mut parts = option.splitn(2, '=');
+        if let (Some("keymap"), Some(name)) = (parts.next(), parts.next()) {
+            if !dev::kbd::keymap::set_active(name) {
+                println!("cmdline: unknown keymap {}", name);
+            }
+        }
+    }
 }
 
 /// TODO: The heck is this?
@@ -92,7 +122,10 @@ extern "C" fn eh_personality() {}
 #[lang = "panic_fmt"]
 #[no_mangle]
 pub extern "C" fn panic_fmt(fmt: core::fmt::Arguments, file: &'static str, line: u32) -> ! {
-    unsafe { kio::idt::disable() }
+    unsafe {
+        kio::idt::disable();
+        kio::force_unlock_output();
+    }
 
     let header = TextStyle {
         foreground: TextColor::White,
@@ -0,0 +1,110 @@
+use x86_64::PhysicalAddress as NPhysicalAddress;
+use x86_64::registers::control_regs::{cr3, cr3_write};
+
+use super::{MemoryController, MEMORY};
+use super::paging::{Frame, FrameAlloc, InactivePageTable, Page, PhysicalAddress, VirtualAddress};
+
+/// Virtual address space of a user process.
+///
+/// Only the user part of address space (`paging::USER_P4_ENTRIES`) is private, the kernel part
+/// is shared with all other address spaces.
+pub struct AddressSpace {
+    table: InactivePageTable,
+}
+
+impl AddressSpace {
+    /// Creates new address space with empty user part. Returns `None` if there is no memory.
+    pub fn new() -> Option<AddressSpace> {
+        let mut memory = MEMORY.lock();
+        let &mut MemoryController {
+            ref mut frame_alloc,
+            ref mut active_table,
+            ref mut tmp_page,
+            ..
+        } = memory.as_mut().expect("memory subsystem is not initialized");
+
+        let frame = frame_alloc.alloc()?;
+        let table = InactivePageTable::new_sharing_kernel(frame, active_table, tmp_page);
+
+        Some(AddressSpace { table })
+    }
+
+    /// Creates copy of this address space, sharing all user pages copy-on-write. Returns `None`
+    /// if there is no memory.
+    ///
+    /// **This address space is required to be active.**
+    pub fn fork(&self) -> Option<AddressSpace> {
+        assert_eq!(current_p4(), self.p4_address(), "forking inactive address space");
+
+        let mut memory = MEMORY.lock();
+        let &mut MemoryController {
+            ref mut frame_alloc,
+            ref mut active_table,
+            ref mut tmp_page,
+            ..
+        } = memory.as_mut().expect("memory subsystem is not initialized");
+
+        let frame = frame_alloc.alloc()?;
+        let mut table = InactivePageTable::new_sharing_kernel(frame, active_table, tmp_page);
+        active_table.fork_user_part(&mut table, tmp_page, frame_alloc);
+
+        Some(AddressSpace { table })
+    }
+
+    /// Returns physical address of P4 table of this address space.
+    pub fn p4_address(&self) -> PhysicalAddress {
+        self.table.p4_frame.start_address()
+    }
+}
+
+impl Drop for AddressSpace {
+    /// Frees all user frames, page tables and the P4 table back to the frame allocator.
+    fn drop(&mut self) {
+        assert_ne!(current_p4(), self.p4_address(), "dropping active address space");
+
+        let mut memory = MEMORY.lock();
+        let &mut MemoryController {
+            ref mut frame_alloc,
+            ref mut active_table,
+            ref mut tmp_page,
+            ..
+        } = memory.as_mut().expect("memory subsystem is not initialized");
+
+        active_table.with(&mut self.table, tmp_page, |mapper| {
+            mapper.unmap_user_part(frame_alloc)
+        });
+        frame_alloc.dealloc(Frame::containing_address(self.p4_address()));
+    }
+}
+
+/// Resolves write to `COPY_ON_WRITE` page at given address in the active address space, giving
+/// it a private, writable copy of the page. Returns `false` if the page is not copy-on-write or
+/// there is no memory.
+pub fn copy_on_write(address: VirtualAddress) -> bool {
+    let mut memory = MEMORY.lock();
+    let &mut MemoryController {
+        ref mut frame_alloc,
+        ref mut active_table,
+        ref mut tmp_page,
+        ..
+    } = memory.as_mut().expect("memory subsystem is not initialized");
+
+    active_table.copy_on_write(Page::containing_address(address), tmp_page, frame_alloc)
+}
+
+/// Returns physical address of currently active P4 table.
+pub fn current_p4() -> PhysicalAddress {
+    cr3().0 as usize
+}
+
+/// Activates page table with P4 at given physical address, unless it is already active.
+///
+/// **The table has to share kernel part with the active one, i.e. it has to be kernel's table
+/// or belong to some [`AddressSpace`].**
+///
+/// [`AddressSpace`]: ./struct.AddressSpace.html
+pub unsafe fn switch_p4(p4: PhysicalAddress) {
+    if current_p4() != p4 {
+        cr3_write(NPhysicalAddress(p4 as u64));
+    }
+}
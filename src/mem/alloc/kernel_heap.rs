@@ -0,0 +1,63 @@
+use alloc::heap::{Alloc, AllocErr, Layout};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use linked_list_allocator::Heap;
+
+use sync::IrqSpinLock;
+
+/// Kernel heap allocator, safe to use from interrupt handlers and preemptible threads.
+///
+/// This is a wrapper around `linked_list_allocator::Heap` which, unlike `LockedHeap` provided
+/// by that crate, guards the heap with [`IrqSpinLock`]. Otherwise a thread preempted while
+/// allocating memory would block every allocation made with interrupts disabled, forever.
+///
+/// [`IrqSpinLock`]: ../../sync/struct.IrqSpinLock.html
+pub struct KernelHeap {
+    heap: IrqSpinLock<Heap>,
+    /// Number of bytes allocated at the moment
+    used: AtomicUsize,
+}
+
+impl KernelHeap {
+    /// Creates empty heap, all allocations will fail until it gets initialized.
+    pub const fn empty() -> KernelHeap {
+        KernelHeap {
+            heap: IrqSpinLock::new(Heap::empty()),
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    /// Initializes heap with given memory region.
+    ///
+    /// **This function should be called only once, and the memory has to be mapped
+    /// and unused.**
+    pub unsafe fn init(&self, heap_start: usize, heap_size: usize) {
+        self.heap.lock().init(heap_start, heap_size);
+    }
+
+    /// Returns size of the heap in bytes.
+    pub fn size(&self) -> usize {
+        self.heap.lock().size()
+    }
+
+    /// Returns number of bytes allocated at the moment, not counting allocator overhead.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+}
+
+unsafe impl<'a> Alloc for &'a KernelHeap {
+    unsafe fn alloc(&mut self, layout: Layout) -> Result<*mut u8, AllocErr> {
+        let size = layout.size();
+        let result = self.heap.lock().allocate_first_fit(layout);
+        if result.is_ok() {
+            self.used.fetch_add(size, Ordering::Relaxed);
+        }
+        result
+    }
+
+    unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout) {
+        self.used.fetch_sub(layout.size(), Ordering::Relaxed);
+        self.heap.lock().deallocate(ptr, layout)
+    }
+}
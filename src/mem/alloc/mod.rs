@@ -1,4 +1,6 @@
 mod kernel_alloc;
+mod kernel_heap;
 mod util;
 
 pub use self::kernel_alloc::KernelAlloc;
+pub use self::kernel_heap::KernelHeap;
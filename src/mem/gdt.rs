@@ -22,15 +22,19 @@ impl Gdt {
     }
 
     pub fn add_entry(&mut self, entry: Descriptor) -> SegmentSelector {
-        let index = match entry {
-            Descriptor::UserSegment(value) => self.push(value),
+        let (index, dpl) = match entry {
+            Descriptor::UserSegment(value) => (self.push(value), value.get_bits(45..47)),
             Descriptor::SystemSegment(value_low, value_high) => {
                 let index = self.push(value_low);
                 self.push(value_high);
-                index
+                (index, value_low.get_bits(45..47))
             }
         };
-        SegmentSelector::new(index as u16, PrivilegeLevel::Ring0)
+        let privilege = match dpl {
+            3 => PrivilegeLevel::Ring3,
+            _ => PrivilegeLevel::Ring0,
+        };
+        SegmentSelector::new(index as u16, privilege)
     }
 
     fn push(&mut self, value: u64) -> usize {
@@ -65,6 +69,21 @@ impl Descriptor {
         Descriptor::UserSegment(flags.bits())
     }
 
+    pub fn kernel_data_segment() -> Descriptor {
+        let flags = F::USER_SEGMENT | F::PRESENT | F::WRITABLE;
+        Descriptor::UserSegment(flags.bits())
+    }
+
+    pub fn user_code_segment() -> Descriptor {
+        let flags = F::USER_SEGMENT | F::PRESENT | F::EXECUTABLE | F::LONG_MODE | F::DPL_RING_3;
+        Descriptor::UserSegment(flags.bits())
+    }
+
+    pub fn user_data_segment() -> Descriptor {
+        let flags = F::USER_SEGMENT | F::PRESENT | F::WRITABLE | F::DPL_RING_3;
+        Descriptor::UserSegment(flags.bits())
+    }
+
     pub fn tss_segment(tss: &'static TaskStateSegment) -> Descriptor {
         let ptr = tss as *const _ as u64;
 
@@ -86,9 +105,11 @@ impl Descriptor {
 
 bitflags! {
     struct DescriptorFlags: u64 {
+        const WRITABLE     = 1 << 41;
         const CONFORMING   = 1 << 42;
         const EXECUTABLE   = 1 << 43;
         const USER_SEGMENT = 1 << 44;
+        const DPL_RING_3   = 3 << 45;
         const PRESENT      = 1 << 47;
         const LONG_MODE    = 1 << 53;
     }
@@ -1,5 +1,6 @@
 //! Memory management subsystem
 
+mod address_space;
 pub mod alloc;
 pub mod gdt;
 pub mod paging;
@@ -9,21 +10,34 @@ use core::cmp;
 
 use multiboot2::BootInformation;
 
+use sync::IrqSpinLock;
+
 use super::HEAP_ALLOCATOR;
 
-use self::paging::{remap_kernel, ActivePageTable, CoreFrameAlloc, Frame, Page};
+use self::paging::{remap_kernel, ActivePageTable, CoreFrameAlloc, Frame, Page, TmpPage, PAGE_SIZE};
+use self::paging::{EntryFlags, PhysicalAddress, VirtualAddress};
 use self::stack::{Stack, StackAllocator};
 
+pub use self::address_space::{copy_on_write, current_p4, switch_p4, AddressSpace};
+
 pub(super) const HEAP_START: usize = 0o_000_004_000_000_0000;
 pub(super) const HEAP_SIZE: usize = 100 * 1024; // 100 KiB
 pub(super) const HEAP_END: usize = HEAP_START + HEAP_SIZE - 1;
 
-const STACK_PAGES: usize = 100;
+const STACK_PAGES: usize = 1024;
+
+/// Page used to access page tables which are not active, right below the kernel heap
+const TMP_PAGE_ADDR: usize = HEAP_START - PAGE_SIZE;
+
+/// State of memory subsystem, available after initialization.
+struct MemoryController {
+    frame_alloc: CoreFrameAlloc,
+    active_table: ActivePageTable,
+    stack_alloc: StackAllocator,
+    tmp_page: TmpPage,
+}
 
-// FIXME: Get rid of these static muts
-static mut FRAME_ALLOC: Option<CoreFrameAlloc> = None;
-static mut ACTIVE_PAGE_TABLE: Option<ActivePageTable> = None;
-static mut STACK_ALLOCATOR: Option<StackAllocator> = None;
+static MEMORY: IrqSpinLock<Option<MemoryController>> = IrqSpinLock::new(None);
 
 /// Initializes memory subsystem.
 ///
@@ -53,6 +67,15 @@ pub unsafe fn init(boot_info: &BootInformation) {
         let multiboot_start = boot_info.start_address();
         let multiboot_end = boot_info.end_address();
 
+        // Boot loaders put modules next to each other, so they are reserved as single range
+        let (modules_start, modules_end) = boot_info
+            .module_tags()
+            .map(|m| (m.start_address() as usize, m.end_address() as usize))
+            .fold(
+                (usize::max_value(), usize::min_value()),
+                |(accs, acce), (s, e)| (cmp::min(accs, s), cmp::max(acce, e)),
+            );
+
         [
             (
                 Frame::containing_address(kernel_start),
@@ -62,6 +85,10 @@ pub unsafe fn init(boot_info: &BootInformation) {
                 Frame::containing_address(multiboot_start),
                 Frame::containing_address(multiboot_end),
             ),
+            (
+                Frame::containing_address(modules_start),
+                Frame::containing_address(modules_end),
+            ),
         ]
     };
 
@@ -76,7 +103,8 @@ pub unsafe fn init(boot_info: &BootInformation) {
         active_table.map(page, paging::EntryFlags::WRITABLE, &mut frame_alloc);
     }
 
-    HEAP_ALLOCATOR.lock().init(HEAP_START, HEAP_SIZE);
+    HEAP_ALLOCATOR.init(HEAP_START, HEAP_SIZE);
+    frame_alloc.enable_reuse();
 
     println!(
         "  Kernel heap      {:#x}-{:#x}",
@@ -88,21 +116,115 @@ pub unsafe fn init(boot_info: &BootInformation) {
     let stack_end_page = stack_start_page + STACK_PAGES;
     let stack_alloc = StackAllocator::new(Page::range_inclusive(stack_start_page, stack_end_page));
 
-    FRAME_ALLOC = Some(frame_alloc);
-    ACTIVE_PAGE_TABLE = Some(active_table);
-    STACK_ALLOCATOR = Some(stack_alloc);
+    let tmp_page = TmpPage::new(Page::containing_address(TMP_PAGE_ADDR), &mut frame_alloc);
+
+    *MEMORY.lock() = Some(MemoryController {
+        frame_alloc,
+        active_table,
+        stack_alloc,
+        tmp_page,
+    });
+}
+
+/// Memory usage summary
+#[derive(Debug, Copy, Clone)]
+pub struct MemoryStats {
+    /// Size of kernel heap in bytes
+    pub heap_size: usize,
+    /// Bytes of kernel heap allocated at the moment
+    pub heap_used: usize,
+    /// Physical frames handed out by frame allocator and not freed yet
+    pub frames_used: usize,
+}
+
+/// Returns current memory usage.
+pub fn stats() -> MemoryStats {
+    let frames_used = MEMORY
+        .lock()
+        .as_ref()
+        .map_or(0, |memory| memory.frame_alloc.allocated());
+
+    MemoryStats {
+        heap_size: HEAP_ALLOCATOR.size(),
+        heap_used: HEAP_ALLOCATOR.used(),
+        frames_used,
+    }
 }
 
 /// Allocates new stack from global stack pool.
+pub fn alloc_stack(size_in_pages: usize) -> Option<Stack> {
+    let mut memory = MEMORY.lock();
+    let &mut MemoryController {
+        ref mut frame_alloc,
+        ref mut active_table,
+        ref mut stack_alloc,
+        ..
+    } = memory.as_mut().expect("memory subsystem is not initialized");
+    stack_alloc.alloc(active_table, frame_alloc, size_in_pages)
+}
+
+/// Returns stack back to global stack pool.
+pub fn dealloc_stack(stack: Stack) {
+    let mut memory = MEMORY.lock();
+    let controller = memory.as_mut().expect("memory subsystem is not initialized");
+    controller.stack_alloc.dealloc(stack);
+}
+
+/// Identity maps range of physical memory which is not available RAM, e.g. firmware tables
+/// or memory mapped device registers, and returns its virtual address, equal to physical one.
+/// Pages which are mapped already are left untouched.
 ///
-/// **This function is not thread safe!**
-pub unsafe fn alloc_stack(size_in_pages: usize) -> Option<Stack> {
-    let stack_allocator = STACK_ALLOCATOR.as_mut().unwrap();
-    let active_table = ACTIVE_PAGE_TABLE.as_mut().unwrap();
-    let frame_alloc = FRAME_ALLOC.as_mut().unwrap();
-    stack_allocator.alloc(active_table, frame_alloc, size_in_pages)
+/// Returns `None` if the range reaches kernel heap, above which identity mapping is not
+/// possible.
+pub fn map_physical(
+    start: PhysicalAddress,
+    size: usize,
+    flags: EntryFlags,
+) -> Option<VirtualAddress> {
+    if !can_map_physical(start, size) {
+        return None;
+    }
+    let end = start + size.max(1) - 1;
+
+    with_active_table(|active_table, frame_alloc| {
+        let first = Page::containing_address(start);
+        let last = Page::containing_address(end);
+        for page in Page::range_inclusive(first, last) {
+            if active_table.translate_page(page).is_none() {
+                let frame = Frame::containing_address(page.start_address());
+                active_table.identity_map(frame, flags, frame_alloc);
+            }
+        }
+    });
+
+    Some(start)
 }
 
+/// Returns `true` if range of physical memory is below kernel heap, so that
+/// [`map_physical`] can map it.
+///
+/// [`map_physical`]: ./fn.map_physical.html
+pub fn can_map_physical(start: PhysicalAddress, size: usize) -> bool {
+    match start.checked_add(size.max(1) - 1) {
+        Some(end) => end < TMP_PAGE_ADDR,
+        None => false,
+    }
+}
+
+/// Runs `f` with active page table and frame allocator, e.g. to map user pages.
+///
+/// `f` is run with memory subsystem locked and interrupts disabled, so it should be short.
+pub fn with_active_table<R>(
+    f: impl FnOnce(&mut ActivePageTable, &mut CoreFrameAlloc) -> R,
+) -> R {
+    let mut memory = MEMORY.lock();
+    let &mut MemoryController {
+        ref mut frame_alloc,
+        ref mut active_table,
+        ..
+    } = memory.as_mut().expect("memory subsystem is not initialized");
+    f(active_table, frame_alloc)
+}
 
 fn enable_nxe_bit() {
     use x86_64::registers::msr::{rdmsr, wrmsr, IA32_EFER};
@@ -1,12 +1,17 @@
+use alloc::Vec;
 use core::ops::{Deref, DerefMut};
+use core::ptr;
 
 use x86_64::instructions::tlb;
 use x86_64::registers::control_regs::{cr3, cr3_write};
 use x86_64::PhysicalAddress as NPhysicalAddress;
 
+use super::PAGE_SIZE;
 use super::frame::Frame;
+use super::frame_alloc::{CoreFrameAlloc, FrameAlloc};
 use super::inactive_page_table::InactivePageTable;
 use super::mapper::Mapper;
+use super::page::Page;
 use super::page_table::EntryFlags as F;
 use super::tmp_page::TmpPage;
 
@@ -49,6 +54,76 @@ impl ActivePageTable {
         tmp_page.unmap(self);
     }
 
+    /// Maps user part of this table in `table`, which has to have empty user part, sharing
+    /// the frames. Writable pages become read-only `COPY_ON_WRITE` pages in both tables.
+    pub fn fork_user_part(
+        &mut self,
+        table: &mut InactivePageTable,
+        tmp_page: &mut TmpPage,
+        allocator: &mut CoreFrameAlloc,
+    ) {
+        let mut mappings = Vec::new();
+        self.update_user_pages(|page, frame, flags| {
+            let flags = if flags.contains(F::WRITABLE) {
+                (flags - F::WRITABLE) | F::COPY_ON_WRITE
+            } else {
+                flags
+            };
+            mappings.push((page, frame.clone(), flags));
+            flags
+        });
+        tlb::flush_all();
+
+        self.with(table, tmp_page, |mapper| {
+            for (page, frame, flags) in mappings {
+                allocator.share(&frame);
+                mapper.map_to(page, frame, flags, allocator);
+            }
+        });
+    }
+
+    /// Makes `COPY_ON_WRITE` page writable again, copying it to a fresh frame if the current one
+    /// is shared. Returns `false` if the page is not copy-on-write or there is no memory.
+    pub fn copy_on_write(
+        &mut self,
+        page: Page,
+        tmp_page: &mut TmpPage,
+        allocator: &mut CoreFrameAlloc,
+    ) -> bool {
+        let flags = match self.page_flags(page) {
+            Some(flags) if flags.contains(F::COPY_ON_WRITE) => flags,
+            _ => return false,
+        };
+        let flags = (flags - F::COPY_ON_WRITE) | F::WRITABLE;
+
+        let frame = self.translate_page(page).unwrap();
+        if allocator.ref_count(&frame) == 1 {
+            // Other sharers are gone already
+            self.set_flags(page, flags);
+            return true;
+        }
+
+        let copy = match allocator.alloc() {
+            Some(copy) => copy,
+            None => return false,
+        };
+
+        let address = tmp_page.map(copy.clone(), self);
+        unsafe {
+            ptr::copy_nonoverlapping(
+                page.start_address() as *const u8,
+                address as *mut u8,
+                PAGE_SIZE,
+            )
+        };
+        tmp_page.unmap(self);
+
+        // Drops one reference to the shared frame
+        self.unmap(page, allocator);
+        self.map_to(page, copy, flags, allocator);
+        true
+    }
+
     pub fn switch(&mut self, new_table: InactivePageTable) -> InactivePageTable {
         let old_table = InactivePageTable {
             p4_frame: Frame::containing_address(cr3().0 as usize),
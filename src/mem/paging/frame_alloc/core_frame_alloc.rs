@@ -1,3 +1,5 @@
+use alloc::{BTreeMap, Vec};
+
 use multiboot2::{MemoryArea, MemoryAreaIter};
 
 use super::{Frame, FrameAlloc};
@@ -6,22 +8,64 @@ pub struct CoreFrameAlloc {
     next_free_frame: Frame,
     current_area: Option<&'static MemoryArea>,
     areas: MemoryAreaIter,
-    reserved_frames: [(Frame, Frame); 2],
+    reserved_frames: [(Frame, Frame); 3],
+    /// Deallocated frames, reused before untouched ones
+    free_frames: Vec<Frame>,
+    /// Deallocated frames can only be recorded once kernel heap is available
+    reuse_enabled: bool,
+    /// Reference counts of frames mapped more than once, indexed by frame number. Frames which
+    /// are missing here have a single owner.
+    ref_counts: BTreeMap<usize, usize>,
+    /// Number of frames handed out and not deallocated yet
+    allocated: usize,
 }
 
+// Memory areas iterator points into Multiboot information table, which is mapped and never
+// changed for whole kernel lifetime.
+unsafe impl Send for CoreFrameAlloc {}
+
 impl CoreFrameAlloc {
     /// Constructs new core frame allocator
-    pub fn new(areas: MemoryAreaIter, reserved_frames: [(Frame, Frame); 2]) -> CoreFrameAlloc {
+    pub fn new(areas: MemoryAreaIter, reserved_frames: [(Frame, Frame); 3]) -> CoreFrameAlloc {
         let mut alloc = CoreFrameAlloc {
             next_free_frame: Frame::containing_address(0),
             current_area: None,
             areas,
             reserved_frames,
+            free_frames: Vec::new(),
+            reuse_enabled: false,
+            ref_counts: BTreeMap::new(),
+            allocated: 0,
         };
         alloc.pick_next_area();
         alloc
     }
 
+    /// Starts recording deallocated frames for reuse. Frames deallocated before are leaked.
+    ///
+    /// **Kernel heap is required to be initialized.**
+    pub fn enable_reuse(&mut self) {
+        self.reuse_enabled = true;
+    }
+
+    /// Adds a reference to allocated frame, which is then freed only after `dealloc` is called
+    /// once more for each added reference.
+    ///
+    /// **Kernel heap is required to be initialized.**
+    pub fn share(&mut self, frame: &Frame) {
+        *self.ref_counts.entry(frame.number).or_insert(1) += 1;
+    }
+
+    /// Returns number of references to allocated frame.
+    pub fn ref_count(&self, frame: &Frame) -> usize {
+        self.ref_counts.get(&frame.number).cloned().unwrap_or(1)
+    }
+
+    /// Returns number of frames in use.
+    pub fn allocated(&self) -> usize {
+        self.allocated
+    }
+
     /// Chooses the area with the minimal base address that still has free frames,
     /// and updates next_free_frame to first frame in picked area.
     fn pick_next_area(&mut self) {
@@ -54,7 +98,10 @@ impl CoreFrameAlloc {
 
 impl FrameAlloc for CoreFrameAlloc {
     fn alloc(&mut self) -> Option<Frame> {
-        // TODO: Pick first reusable free frame
+        if let Some(frame) = self.free_frames.pop() {
+            self.allocated += 1;
+            return Some(frame);
+        }
 
         // If there are no reusable frames, try to pick untouched one.
         while let Some(area) = self.current_area {
@@ -75,6 +122,7 @@ impl FrameAlloc for CoreFrameAlloc {
 
             // Otherwise, picked frame is available to allocating.
             self.next_free_frame = next_frame(&self.next_free_frame);
+            self.allocated += 1;
             return Some(frame);
         }
 
@@ -83,9 +131,25 @@ impl FrameAlloc for CoreFrameAlloc {
         None
     }
 
-    fn dealloc(&mut self, _frame: Frame) {
-        // TODO: Reusing frames
-        println!("FRAME DEALLOCATING NOT IMPLEMENTED YET");
+    fn dealloc(&mut self, frame: Frame) {
+        // Shared frame only loses one reference
+        match self.ref_counts.get(&frame.number).cloned() {
+            Some(2) => {
+                self.ref_counts.remove(&frame.number);
+                return;
+            }
+            Some(count) => {
+                self.ref_counts.insert(frame.number, count - 1);
+                return;
+            }
+            None => {}
+        }
+
+        // Frames set up by the boot loader have never been counted
+        self.allocated = self.allocated.saturating_sub(1);
+        if self.reuse_enabled {
+            self.free_frames.push(frame);
+        }
     }
 }
 
@@ -1,5 +1,7 @@
+use super::USER_P4_ENTRIES;
 use super::active_page_table::ActivePageTable;
 use super::frame::Frame;
+use super::page_table::ENTRY_COUNT;
 use super::page_table::EntryFlags as F;
 use super::tmp_page::TmpPage;
 
@@ -23,4 +25,27 @@ impl InactivePageTable {
 
         InactivePageTable { p4_frame: frame }
     }
+
+    /// Creates page table which shares kernel part of address space with the active table,
+    /// and has empty user part.
+    pub fn new_sharing_kernel(
+        frame: Frame,
+        active_table: &mut ActivePageTable,
+        tmp_page: &mut TmpPage,
+    ) -> InactivePageTable {
+        {
+            let table = tmp_page.map_table_frame(frame.clone(), active_table);
+            table.clear();
+            // Copy kernel entries, except the recursive one
+            for index in 0..ENTRY_COUNT - 1 {
+                if index < USER_P4_ENTRIES.start || index >= USER_P4_ENTRIES.end {
+                    table[index] = active_table.p4()[index];
+                }
+            }
+            table[511].set(frame.clone(), F::PRESENT | F::WRITABLE);
+        }
+        tmp_page.unmap(active_table);
+
+        InactivePageTable { p4_frame: frame }
+    }
 }
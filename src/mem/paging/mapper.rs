@@ -3,7 +3,7 @@ use core::ptr::Unique;
 use x86_64::instructions::tlb;
 use x86_64::VirtualAddress as NVirtualAddress;
 
-use super::{PhysicalAddress, VirtualAddress, PAGE_SIZE};
+use super::{PhysicalAddress, VirtualAddress, PAGE_SIZE, USER_P4_ENTRIES};
 use super::frame::Frame;
 use super::frame_alloc::FrameAlloc;
 use super::page::Page;
@@ -77,6 +77,23 @@ impl Mapper {
         })
     }
 
+    /// Returns flags of the entry mapping given page, or `None` if the page is not mapped.
+    /// Huge pages are not supported.
+    pub fn page_flags(&self, page: Page) -> Option<EntryFlags> {
+        self.p4()
+            .next_table(page.p4_index())
+            .and_then(|p3| p3.next_table(page.p3_index()))
+            .and_then(|p2| p2.next_table(page.p2_index()))
+            .and_then(|p1| {
+                let flags = p1[page.p1_index()].flags();
+                if flags.contains(F::PRESENT) {
+                    Some(flags)
+                } else {
+                    None
+                }
+            })
+    }
+
     /// Maps the page to the frame with the provided flags.
     /// The `PRESENT` flag is added by default. This function needs
     /// a `FrameAllocator` as it might need to create new page tables.
@@ -87,10 +104,11 @@ impl Mapper {
         flags: EntryFlags,
         allocator: &mut impl FrameAlloc,
     ) {
+        let user = flags.contains(EntryFlags::USER_ACCESSIBLE);
         let p4 = self.p4_mut();
-        let p3 = p4.next_table_create(page.p4_index(), allocator);
-        let p2 = p3.next_table_create(page.p3_index(), allocator);
-        let p1 = p2.next_table_create(page.p2_index(), allocator);
+        let p3 = p4.next_table_create(page.p4_index(), user, allocator);
+        let p2 = p3.next_table_create(page.p3_index(), user, allocator);
+        let p1 = p2.next_table_create(page.p2_index(), user, allocator);
 
         assert!(p1[page.p1_index()].is_unused());
         p1[page.p1_index()].set(frame, flags | EntryFlags::PRESENT);
@@ -115,6 +133,21 @@ impl Mapper {
         self.map_to(page, frame, flags, allocator)
     }
 
+    /// Changes flags of already mapped page. The `PRESENT` flag is added by default.
+    pub fn set_flags(&mut self, page: Page, flags: EntryFlags) {
+        let p1 = self.p4_mut()
+            .next_table_mut(page.p4_index())
+            .and_then(|p3| p3.next_table_mut(page.p3_index()))
+            .and_then(|p2| p2.next_table_mut(page.p2_index()))
+            .expect("page is not mapped");
+
+        let frame = p1[page.p1_index()].pointed_frame().expect("page is not mapped");
+
+        p1[page.p1_index()].set(frame, flags | EntryFlags::PRESENT);
+
+        tlb::flush(NVirtualAddress(page.start_address()));
+    }
+
     /// Unmaps the given page and adds all freed frames to the given
     /// `FrameAllocator`.
     pub fn unmap(&mut self, page: Page, allocator: &mut impl FrameAlloc) {
@@ -135,4 +168,68 @@ impl Mapper {
         // FIXME: free P{1,2,3} table if empty
         allocator.dealloc(frame);
     }
+
+    /// Unmaps the whole user part of address space (`USER_P4_ENTRIES`) and adds all mapped
+    /// frames and the page tables mapping them to the given `FrameAllocator`.
+    ///
+    /// Meant to be used on inactive tables, TLB is not flushed. Huge pages are not supported.
+    pub fn unmap_user_part(&mut self, allocator: &mut impl FrameAlloc) {
+        for p4_index in USER_P4_ENTRIES {
+            if let Some(p3) = self.p4().next_table(p4_index) {
+                for p3_index in 0..ENTRY_COUNT {
+                    if let Some(p2) = p3.next_table(p3_index) {
+                        for p2_index in 0..ENTRY_COUNT {
+                            if let Some(p1) = p2.next_table(p2_index) {
+                                for p1_index in 0..ENTRY_COUNT {
+                                    if let Some(frame) = p1[p1_index].pointed_frame() {
+                                        allocator.dealloc(frame);
+                                    }
+                                }
+                                allocator.dealloc(p2[p2_index].pointed_frame().unwrap());
+                            }
+                        }
+                        allocator.dealloc(p3[p3_index].pointed_frame().unwrap());
+                    }
+                }
+                allocator.dealloc(self.p4()[p4_index].pointed_frame().unwrap());
+            }
+            self.p4_mut()[p4_index].set_unused();
+        }
+    }
+
+    /// Calls `f` for every page mapped in the user part of address space, with the frame it is
+    /// mapped to and its flags, and replaces the flags with returned ones. TLB is not flushed.
+    pub(super) fn update_user_pages(
+        &mut self,
+        mut f: impl FnMut(Page, &Frame, EntryFlags) -> EntryFlags,
+    ) {
+        for p4_index in USER_P4_ENTRIES {
+            let p3 = match self.p4_mut().next_table_mut(p4_index) {
+                Some(p3) => p3,
+                None => continue,
+            };
+            for p3_index in 0..ENTRY_COUNT {
+                let p2 = match p3.next_table_mut(p3_index) {
+                    Some(p2) => p2,
+                    None => continue,
+                };
+                for p2_index in 0..ENTRY_COUNT {
+                    let p1 = match p2.next_table_mut(p2_index) {
+                        Some(p1) => p1,
+                        None => continue,
+                    };
+                    for p1_index in 0..ENTRY_COUNT {
+                        if let Some(frame) = p1[p1_index].pointed_frame() {
+                            let page = Page {
+                                number: p4_index << 27 | p3_index << 18 | p2_index << 9
+                                    | p1_index,
+                            };
+                            let flags = f(page, &frame, p1[p1_index].flags());
+                            p1[p1_index].set(frame, flags);
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
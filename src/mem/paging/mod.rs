@@ -7,25 +7,31 @@ mod page;
 mod page_table;
 mod tmp_page;
 
+use core::ops::Range;
+
 use multiboot2::BootInformation;
 
 use drv::gfx::vga::text_buffer::VGA_TEXT_BUFFER_ADDR;
 
-use self::inactive_page_table::InactivePageTable;
 use self::page_table::EntryFlags as F;
-use self::tmp_page::TmpPage;
 
 pub use self::active_page_table::ActivePageTable;
 pub use self::frame::*;
 pub use self::frame_alloc::*;
+pub use self::inactive_page_table::InactivePageTable;
 pub use self::page::{Page, PageIter};
 pub use self::page_table::EntryFlags;
+pub use self::tmp_page::TmpPage;
 
 pub type VirtualAddress = usize;
 pub type PhysicalAddress = usize;
 
 pub const PAGE_SIZE: usize = 4096;
 
+/// P4 entries which are private to each address space, the lower half without the first 512 GiB.
+/// All other entries map kernel and are shared by all address spaces.
+pub const USER_P4_ENTRIES: Range<usize> = 1..256;
+
 const REMAP_TMP_PAGE_NUMBER: usize = 0xdeadbeef;
 
 pub fn remap_kernel(
@@ -105,6 +111,25 @@ pub fn remap_kernel(
         for frame in Frame::range_inclusive(multiboot_start, multiboot_end) {
             mapper.identity_map(frame, F::PRESENT, allocator);
         }
+
+        // Identity map boot modules, e.g. user programs
+        for module in boot_info.module_tags() {
+            let module_start = Frame::containing_address(module.start_address() as usize);
+            let module_end = Frame::containing_address(module.end_address() as usize - 1);
+            println!(
+                "  Module           {:#x}-{:#x} {}",
+                module_start.start_address(),
+                module_end.end_address(),
+                module.name()
+            );
+            for frame in Frame::range_inclusive(module_start, module_end) {
+                // Small modules may share frames with each other or with boot info
+                let page = Page::containing_address(frame.start_address());
+                if mapper.translate_page(page).is_none() {
+                    mapper.identity_map(frame, F::PRESENT | F::NO_EXECUTE, allocator);
+                }
+            }
+        }
     });
 
     let old_table = active_table.switch(new_table);
@@ -89,9 +89,14 @@ impl<L: HierarchicalLevel> PageTable<L> {
             .map(|address| unsafe { &mut *(address as *mut _) })
     }
 
+    /// Returns next level table, creating it if it does not exist.
+    ///
+    /// `USER_ACCESSIBLE` flag of the entry is set if requested, as user pages have to be
+    /// accessible on every level of the hierarchy.
     pub fn next_table_create(
         &mut self,
         index: usize,
+        user_accessible: bool,
         allocator: &mut impl FrameAlloc,
     ) -> &mut PageTable<L::NextLevel> {
         if self.next_table(index).is_none() {
@@ -103,6 +108,9 @@ impl<L: HierarchicalLevel> PageTable<L> {
             self.entries[index].set(frame, F::PRESENT | F::WRITABLE);
             self.next_table_mut(index).unwrap().clear();
         }
+        if user_accessible {
+            self.entries[index].add_flags(F::USER_ACCESSIBLE);
+        }
         self.next_table_mut(index).unwrap()
     }
 }
@@ -146,6 +154,11 @@ impl Entry {
         self.0 = 0;
     }
 
+    /// Sets given flags, keeping the rest of the entry intact.
+    pub fn add_flags(&mut self, flags: EntryFlags) {
+        self.0 |= flags.bits();
+    }
+
     /// Set value of this entry.
     ///
     /// The start address of a frame should be page aligned and smaller than 2^52
@@ -179,6 +192,8 @@ bitflags! {
         const HUGE_PAGE =       1 << 7;
         /// Page isn't flushed from caches on address space switch.
         const GLOBAL =          1 << 8;
+        /// Page is shared read-only and gets copied on first write (OS-defined bit).
+        const COPY_ON_WRITE =   1 << 9;
         /// Forbid executing code on this page (the NXE bit in the EFER register must be set).
         const NO_EXECUTE =      1 << 63;
     }
@@ -205,6 +220,28 @@ impl EntryFlags {
 
         flags
     }
+
+    /// Converts flags of ELF program header (`p_flags`) to page flags, the same way as
+    /// [`from_elf_section_flags`] does for sections.
+    ///
+    /// [`from_elf_section_flags`]: #method.from_elf_section_flags
+    pub fn from_elf_program_flags(p_flags: u32) -> EntryFlags {
+        const PF_X: u32 = 1 << 0;
+        const PF_W: u32 = 1 << 1;
+
+        // loadable segments are always present in memory
+        let mut flags = F::PRESENT;
+
+        if p_flags & PF_W != 0 {
+            flags = flags | F::WRITABLE;
+        }
+
+        if p_flags & PF_X == 0 {
+            flags = flags | F::NO_EXECUTE;
+        }
+
+        flags
+    }
 }
 
 /// Addresses are expected to be canonical (bits 48-63 must be the same as bit 47),
@@ -1,4 +1,6 @@
-use super::paging::{ActivePageTable, FrameAlloc, Page, PageIter};
+use alloc::Vec;
+
+use super::paging::{ActivePageTable, FrameAlloc, Page, PageIter, PAGE_SIZE};
 use super::paging::EntryFlags as F;
 
 #[derive(Debug)]
@@ -17,16 +19,26 @@ impl Stack {
             __guard: (),
         }
     }
+
+    /// Returns size of this stack in pages.
+    pub fn size_in_pages(&self) -> usize {
+        (self.top - self.bottom) / PAGE_SIZE
+    }
 }
 
 #[derive(Debug)]
 pub struct StackAllocator {
     page_range: PageIter,
+    /// Deallocated stacks, kept mapped for reuse.
+    free_stacks: Vec<Stack>,
 }
 
 impl StackAllocator {
-    pub const fn new(page_range: PageIter) -> StackAllocator {
-        StackAllocator { page_range }
+    pub fn new(page_range: PageIter) -> StackAllocator {
+        StackAllocator {
+            page_range,
+            free_stacks: Vec::new(),
+        }
     }
 
     pub fn alloc(
@@ -39,6 +51,14 @@ impl StackAllocator {
             return None;
         }
 
+        // Prefer reusing stacks which are already mapped
+        if let Some(index) = self.free_stacks
+            .iter()
+            .position(|s| s.size_in_pages() == size_in_pages)
+        {
+            return Some(self.free_stacks.swap_remove(index));
+        }
+
         let mut range = self.page_range.clone();
 
         let guard_page = range.next();
@@ -61,4 +81,11 @@ impl StackAllocator {
             None
         }
     }
+
+    /// Takes stack back for future reuse.
+    ///
+    /// Stack pages stay mapped, so the stack has to be no longer used by anyone.
+    pub fn dealloc(&mut self, stack: Stack) {
+        self.free_stacks.push(stack);
+    }
 }
@@ -0,0 +1,172 @@
+//! ELF64 executable parser
+//!
+//! Only what is needed to load statically linked x86_64 executables is supported: the file
+//! header and program headers.
+
+use core::fmt;
+
+const MAGIC: &[u8] = b"\x7fELF";
+const CLASS_64: u8 = 2;
+const DATA_LITTLE_ENDIAN: u8 = 1;
+const TYPE_EXECUTABLE: u16 = 2;
+const MACHINE_X86_64: u16 = 0x3e;
+
+const HEADER_SIZE: usize = 64;
+const PROGRAM_HEADER_SIZE: usize = 56;
+
+const PT_LOAD: u32 = 1;
+
+/// Reason why the file is not a valid executable
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The file is not an ELF file at all
+    BadMagic,
+    /// The file is not a 64-bit little endian x86_64 executable
+    Unsupported,
+    /// The file is cut short
+    Truncated,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::BadMagic => write!(f, "not an ELF file"),
+            Error::Unsupported => write!(f, "not a x86_64 ELF64 executable"),
+            Error::Truncated => write!(f, "truncated ELF file"),
+        }
+    }
+}
+
+/// Parsed ELF64 executable
+#[derive(Debug, Copy, Clone)]
+pub struct Elf<'a> {
+    data: &'a [u8],
+    entry: usize,
+    ph_offset: usize,
+    ph_count: usize,
+}
+
+impl<'a> Elf<'a> {
+    /// Parses and validates headers of executable file.
+    pub fn parse(data: &'a [u8]) -> Result<Elf<'a>, Error> {
+        if data.len() < HEADER_SIZE {
+            return Err(Error::Truncated);
+        }
+
+        if &data[0..4] != MAGIC {
+            return Err(Error::BadMagic);
+        }
+
+        let supported = data[4] == CLASS_64 && data[5] == DATA_LITTLE_ENDIAN
+            && read_u16(data, 16) == TYPE_EXECUTABLE
+            && read_u16(data, 18) == MACHINE_X86_64
+            && read_u16(data, 54) as usize == PROGRAM_HEADER_SIZE;
+        if !supported {
+            return Err(Error::Unsupported);
+        }
+
+        let elf = Elf {
+            data,
+            entry: read_u64(data, 24) as usize,
+            ph_offset: read_u64(data, 32) as usize,
+            ph_count: read_u16(data, 56) as usize,
+        };
+
+        let ph_end = elf.ph_count
+            .checked_mul(PROGRAM_HEADER_SIZE)
+            .and_then(|size| size.checked_add(elf.ph_offset));
+        match ph_end {
+            Some(end) if end <= data.len() => {}
+            _ => return Err(Error::Truncated),
+        }
+
+        for segment in elf.segments() {
+            let file_end = segment.offset.checked_add(segment.file_size);
+            match file_end {
+                Some(end) if end <= data.len() && segment.file_size <= segment.mem_size => {}
+                _ => return Err(Error::Truncated),
+            }
+        }
+
+        Ok(elf)
+    }
+
+    /// Returns address of program entry point.
+    pub fn entry(&self) -> usize {
+        self.entry
+    }
+
+    /// Returns iterator over loadable segments.
+    pub fn segments(&self) -> Segments<'a> {
+        Segments {
+            elf: *self,
+            index: 0,
+        }
+    }
+}
+
+/// Loadable segment of executable (`PT_LOAD` program header)
+#[derive(Debug, Copy, Clone)]
+pub struct Segment<'a> {
+    /// Virtual address the segment is loaded at
+    pub address: usize,
+    /// Size of segment in memory, the part not present in file is zeroed
+    pub mem_size: usize,
+    /// Raw `p_flags` value, see `EntryFlags::from_elf_program_flags`
+    pub flags: u32,
+    offset: usize,
+    file_size: usize,
+    data: &'a [u8],
+}
+
+impl<'a> Segment<'a> {
+    /// Returns contents of segment stored in the file.
+    pub fn file_data(&self) -> &'a [u8] {
+        &self.data[self.offset..self.offset + self.file_size]
+    }
+}
+
+/// Iterator over loadable segments of executable
+pub struct Segments<'a> {
+    elf: Elf<'a>,
+    index: usize,
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Segment<'a>;
+
+    fn next(&mut self) -> Option<Segment<'a>> {
+        while self.index < self.elf.ph_count {
+            let header = self.elf.ph_offset + self.index * PROGRAM_HEADER_SIZE;
+            self.index += 1;
+
+            let data = self.elf.data;
+            if read_u32(data, header) != PT_LOAD {
+                continue;
+            }
+
+            return Some(Segment {
+                address: read_u64(data, header + 16) as usize,
+                mem_size: read_u64(data, header + 40) as usize,
+                flags: read_u32(data, header + 4),
+                offset: read_u64(data, header + 8) as usize,
+                file_size: read_u64(data, header + 32) as usize,
+                data,
+            });
+        }
+
+        None
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    (data[offset] as u16) | (data[offset + 1] as u16) << 8
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    (read_u16(data, offset) as u32) | (read_u16(data, offset + 2) as u32) << 16
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    (read_u32(data, offset) as u64) | (read_u32(data, offset + 4) as u64) << 32
+}
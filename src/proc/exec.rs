@@ -0,0 +1,114 @@
+use alloc::{BTreeMap, String, Vec};
+use core::{cmp, fmt, ptr};
+use core::mem::size_of;
+
+use mem::paging::{EntryFlags, PAGE_SIZE};
+
+use super::elf::{self, Elf};
+use super::process::Pid;
+use super::{map_zeroed, pages, programs, set_flags, spawn_task, STACK_BOTTOM, USER_START};
+
+/// Maximum total size of program arguments, including pointers to them
+const MAX_ARGS_SIZE: usize = 4 * PAGE_SIZE;
+
+/// Reason why program could not be started
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExecError {
+    /// There is no program with given name
+    NotFound,
+    /// Program is not a valid executable
+    InvalidExecutable(elf::Error),
+    /// Program has to be loaded outside of user part of address space
+    BadAddress,
+    /// Arguments do not fit on user stack
+    ArgumentsTooLong,
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExecError::NotFound => write!(f, "program not found"),
+            ExecError::InvalidExecutable(error) => write!(f, "invalid executable: {}", error),
+            ExecError::BadAddress => write!(f, "program is not linked for user space"),
+            ExecError::ArgumentsTooLong => write!(f, "arguments too long"),
+        }
+    }
+}
+
+/// Starts new process running program with given name, passing `args` to it.
+///
+/// By convention, the first argument is program name.
+pub fn exec(name: &str, args: &[&str]) -> Result<Pid, ExecError> {
+    let image = programs::find(name).ok_or(ExecError::NotFound)?;
+    let elf = Elf::parse(image).map_err(ExecError::InvalidExecutable)?;
+
+    // Leave a guard page between program image and user stack
+    let image_limit = STACK_BOTTOM - PAGE_SIZE;
+
+    if elf.entry() < USER_START || elf.entry() >= image_limit {
+        return Err(ExecError::BadAddress);
+    }
+
+    for segment in elf.segments() {
+        match segment.address.checked_add(segment.mem_size) {
+            Some(end) if segment.address >= USER_START && end <= image_limit => {}
+            _ => return Err(ExecError::BadAddress),
+        }
+    }
+
+    let args_size: usize = args.iter().map(|a| a.len() + 1 + size_of::<usize>()).sum();
+    if args_size > MAX_ARGS_SIZE {
+        return Err(ExecError::ArgumentsTooLong);
+    }
+    let args: Vec<String> = args.iter().map(|&a| String::from(a)).collect();
+
+    Ok(spawn_task(name, args, move || load(&elf)))
+}
+
+/// Maps and fills loadable segments of executable in current address space. Returns entry
+/// point and end of program image.
+///
+/// Pages shared by segments get union of their permissions: they are writable if any of the
+/// segments is, and executable if any of them is.
+fn load(elf: &Elf) -> (usize, usize) {
+    let mut image_end = USER_START;
+
+    for segment in elf.segments().filter(|s| s.mem_size > 0) {
+        let flags = EntryFlags::USER_ACCESSIBLE | EntryFlags::WRITABLE;
+        map_zeroed(segment.address, segment.mem_size, flags);
+
+        let data = segment.file_data();
+        unsafe { ptr::copy_nonoverlapping(data.as_ptr(), segment.address as *mut u8, data.len()) };
+
+        image_end = cmp::max(image_end, segment.address + segment.mem_size);
+    }
+
+    // Flags by page address
+    let mut page_flags: BTreeMap<usize, EntryFlags> = BTreeMap::new();
+    for segment in elf.segments().filter(|s| s.mem_size > 0) {
+        let flags = EntryFlags::from_elf_program_flags(segment.flags);
+        for page in pages(segment.address, segment.mem_size) {
+            let page = page.start_address();
+            let merged = match page_flags.get(&page) {
+                Some(&other) => union_flags(other, flags),
+                None => flags,
+            };
+            page_flags.insert(page, merged);
+        }
+    }
+
+    for (&page, &flags) in page_flags.iter() {
+        set_flags(page, PAGE_SIZE, flags | EntryFlags::USER_ACCESSIBLE);
+    }
+
+    (elf.entry(), image_end)
+}
+
+/// Returns flags granting both segment permissions, for page shared by two segments.
+fn union_flags(a: EntryFlags, b: EntryFlags) -> EntryFlags {
+    let mut flags = a | b;
+    if !(a.contains(EntryFlags::NO_EXECUTE) && b.contains(EntryFlags::NO_EXECUTE)) {
+        flags.remove(EntryFlags::NO_EXECUTE);
+    }
+    flags
+}
@@ -0,0 +1,275 @@
+//! User mode processes
+//!
+//! User code runs in ring 3, in the lower half of address space above the first 512 GiB, which
+//! belong to the kernel (identity mapped physical memory, kernel heap and stacks). Each process
+//! has own [`AddressSpace`] and is listed in the [process table](process/index.html); its thread
+//! is a kernel thread which has dropped to user mode. Interrupts and exceptions coming from user
+//! mode are handled on its kernel stack. Faulting process is killed, the kernel keeps running.
+//!
+//! ## Examples
+//!
+//! ```
+//! // jmp $
+//! let pid = proc::spawn("spin", &[0xeb, 0xfe]);
+//! proc::kill(pid);
+//!
+//! let pid = proc::exec("hello", &["hello", "world"])?;
+//! println!("exit code {}", proc::wait(pid)?);
+//! ```
+//!
+//! [`AddressSpace`]: ../mem/struct.AddressSpace.html
+
+pub mod elf;
+mod exec;
+pub mod process;
+pub mod programs;
+pub mod syscall;
+mod usermode;
+
+use alloc::{String, Vec};
+use alloc::arc::Arc;
+use core::ptr;
+
+use x86_64::structures::idt::ExceptionStackFrame;
+
+use kio::idt;
+use mem;
+use mem::AddressSpace;
+use mem::paging::{EntryFlags, Page, PAGE_SIZE};
+use thread;
+
+use self::syscall::Error;
+
+pub use self::exec::{exec, ExecError};
+pub use self::process::{current, exit, exit_if_killed, is_killed, kill, list, wait, Pid,
+                        ProcessInfo, ProcessState, WaitError, EXIT_FAULT, EXIT_KILLED};
+pub use self::usermode::enter_user_mode;
+
+use self::usermode::{return_to_kernel, return_to_user_mode};
+
+/// Lowest address available to user code
+pub const USER_START: usize = 0x0000_0080_0000_0000;
+/// End of user part of address space (exclusive), which is the end of lower canonical half
+pub const USER_END: usize = 0x0000_8000_0000_0000;
+
+/// Size of user stack
+const STACK_PAGES: usize = 16;
+/// Top of user stack, there is an unmapped guard page between stack and anonymous mappings
+const STACK_TOP: usize = USER_END;
+const STACK_BOTTOM: usize = STACK_TOP - STACK_PAGES * PAGE_SIZE;
+
+/// Initializes system call interface.
+///
+/// **IDT is required to be initialized.**
+///
+/// **This function should be called only once.**
+pub unsafe fn init() {
+    syscall::init();
+}
+
+/// Sets kernel stack used when current thread enters the kernel from user mode, either by
+/// interrupt or system call.
+///
+/// **Interrupts are required to be disabled, this function is meant to be called by scheduler
+/// on every thread switch.**
+pub unsafe fn set_kernel_stack(stack_top: usize) {
+    idt::set_kernel_stack(stack_top);
+    syscall::set_kernel_stack(stack_top);
+}
+
+/// Makes current thread exit once the interrupt handler returns, if its process has been
+/// killed. Exiting right in the handler would free process memory in interrupt context.
+///
+/// **`stack_frame` has to be the frame of an interrupt which has arrived in user mode, and
+/// the handler must return right after this call.**
+pub unsafe fn exit_on_return_if_killed(stack_frame: &mut ExceptionStackFrame) {
+    if is_killed() {
+        return_to_kernel(stack_frame, exit_killed);
+    }
+}
+
+extern "C" fn exit_killed() -> ! {
+    exit(EXIT_KILLED);
+}
+
+/// Spawns process running given raw machine code in user mode.
+///
+/// The code is copied to read-only executable pages at `USER_START`.
+///
+/// ## Panics
+///
+/// Panics if there is no memory.
+pub fn spawn(name: &str, code: &[u8]) -> Pid {
+    assert!(!code.is_empty(), "no code to run");
+
+    let code = code.to_vec();
+    spawn_task(name, Vec::new(), move || {
+        let flags = EntryFlags::USER_ACCESSIBLE | EntryFlags::WRITABLE;
+        map_zeroed(USER_START, code.len(), flags);
+        unsafe { ptr::copy_nonoverlapping(code.as_ptr(), USER_START as *mut u8, code.len()) };
+        set_flags(USER_START, code.len(), EntryFlags::USER_ACCESSIBLE);
+
+        (USER_START, USER_START + code.len())
+    })
+}
+
+/// Creates process with fresh address space and spawns its thread, which runs `load` to set up
+/// program image, and then enters user mode.
+///
+/// `load` returns entry point and end of program image, anonymous mappings are placed after it.
+/// User stack contains `args`, laid out as System V ABI describes.
+fn spawn_task<F>(name: &str, args: Vec<String>, load: F) -> Pid
+where
+    F: FnOnce() -> (usize, usize) + Send + 'static,
+{
+    let address_space = Arc::new(AddressSpace::new().expect("out of memory for address space"));
+    let pid = process::create(name, address_space.clone());
+
+    thread::spawn_in(name, address_space, move || {
+        process::attach_current_thread(pid);
+        let (entry, image_end) = load();
+
+        map_zeroed(
+            STACK_BOTTOM,
+            STACK_TOP - STACK_BOTTOM,
+            EntryFlags::USER_ACCESSIBLE | EntryFlags::WRITABLE | EntryFlags::NO_EXECUTE,
+        );
+        let stack_pointer = unsafe { push_args(STACK_TOP, &args) };
+
+        process::with_current(|process| {
+            process.mmap_next = page_align_up(image_end);
+            process.mmap_end = STACK_BOTTOM - PAGE_SIZE;
+        });
+
+        // Entering user mode never returns, so nothing would be dropped otherwise
+        drop(args);
+
+        unsafe { enter_user_mode(entry, stack_pointer) }
+    });
+
+    pid
+}
+
+/// Copies arguments to user stack and pushes `argc`, `argv` and empty `envp` in front of them.
+/// Returns new stack pointer, which is 16-byte aligned.
+///
+/// **Stack has to be mapped and large enough.**
+unsafe fn push_args(stack_top: usize, args: &[String]) -> usize {
+    let mut sp = stack_top;
+
+    let mut pointers = Vec::with_capacity(args.len());
+    for arg in args {
+        sp -= arg.len() + 1;
+        ptr::copy_nonoverlapping(arg.as_ptr(), sp as *mut u8, arg.len());
+        *((sp + arg.len()) as *mut u8) = 0;
+        pointers.push(sp);
+    }
+
+    sp &= !0xf;
+    // argc, argv, NULL terminating argv and NULL terminating envp
+    if (args.len() + 3) % 2 != 0 {
+        sp -= 8;
+    }
+
+    {
+        let mut push = |value: usize| {
+            sp -= 8;
+            *(sp as *mut usize) = value;
+        };
+
+        push(0);
+        push(0);
+        for &pointer in pointers.iter().rev() {
+            push(pointer);
+        }
+        push(args.len());
+    }
+
+    sp
+}
+
+/// Creates copy of calling process, which shares its memory copy-on-write. Thread of the new
+/// process returns from the system call with 0. Returns identifier of the new process.
+///
+/// **Current thread is required to be handling a system call.**
+fn fork() -> Result<Pid, Error> {
+    let registers = unsafe { syscall::saved_registers() };
+    let (name, address_space) = process::with_current(|process| {
+        (process.name.clone(), process.address_space.clone())
+    }).ok_or(Error::InvalidArgument)?;
+
+    let address_space = address_space.ok_or(Error::InvalidArgument)?;
+    let child_space = Arc::new(address_space.fork().ok_or(Error::OutOfMemory)?);
+    let pid = process::create_fork(child_space.clone());
+
+    thread::spawn_in(&name, child_space, move || {
+        process::attach_current_thread(pid);
+        unsafe { return_to_user_mode(&registers, 0) }
+    });
+
+    Ok(pid)
+}
+
+/// Maps `len` bytes of zeroed memory in user space of current process, returns its address.
+fn map_anonymous(len: usize) -> Result<usize, Error> {
+    if len == 0 || len > USER_END - USER_START {
+        return Err(Error::InvalidArgument);
+    }
+    let size = page_align_up(len);
+
+    let start = process::with_current(|process| {
+        if size > process.mmap_end - process.mmap_next {
+            return Err(Error::OutOfMemory);
+        }
+
+        let start = process.mmap_next;
+        process.mmap_next += size;
+        Ok(start)
+    }).ok_or(Error::InvalidArgument)??;
+
+    map_zeroed(
+        start,
+        size,
+        EntryFlags::USER_ACCESSIBLE | EntryFlags::WRITABLE | EntryFlags::NO_EXECUTE,
+    );
+
+    Ok(start)
+}
+
+/// Maps fresh, zeroed pages covering `size` bytes starting at `start` in current address space,
+/// and accounts them to current process. Pages which are already mapped are left intact.
+///
+/// `flags` have to contain `WRITABLE`, so that the pages can be cleared.
+fn map_zeroed(start: usize, size: usize, flags: EntryFlags) {
+    let mapped = mem::with_active_table(|table, frame_alloc| {
+        let mut mapped = 0;
+        for page in pages(start, size) {
+            if table.page_flags(page).is_none() {
+                table.map(page, flags, frame_alloc);
+                unsafe { ptr::write_bytes(page.start_address() as *mut u8, 0, PAGE_SIZE) };
+                mapped += 1;
+            }
+        }
+        mapped
+    });
+
+    process::with_current(|process| process.pages += mapped);
+}
+
+fn set_flags(start: usize, size: usize, flags: EntryFlags) {
+    mem::with_active_table(|table, _| {
+        for page in pages(start, size) {
+            table.set_flags(page, flags);
+        }
+    });
+}
+
+fn page_align_up(address: usize) -> usize {
+    (address + PAGE_SIZE - 1) & !(PAGE_SIZE - 1)
+}
+
+fn pages(start: usize, size: usize) -> mem::paging::PageIter {
+    let start_page = Page::containing_address(start);
+    let end_page = Page::containing_address(start + size - 1);
+    Page::range_inclusive(start_page, end_page)
+}
@@ -0,0 +1,388 @@
+//! Process table
+//!
+//! Every user program runs as a process, identified by [`Pid`]. Process owns an address space
+//! and threads running in it, and remembers which process has started it. When the last thread
+//! of a process exits, its memory is freed and the process becomes a zombie, which keeps only
+//! the exit code until the parent collects it with [`wait`].
+//!
+//! [`Pid`]: ./struct.Pid.html
+//! [`wait`]: ./fn.wait.html
+
+use alloc::{BTreeMap, String, Vec};
+use alloc::arc::Arc;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use mem::AddressSpace;
+use mem::paging::PAGE_SIZE;
+use sync::{IrqSpinLock, WaitQueue};
+use thread::{self, ThreadId};
+
+use super::syscall::KeyInput;
+
+/// Exit code of process killed because it has caused an exception
+pub const EXIT_FAULT: isize = -1;
+/// Exit code of process terminated by [`kill`](./fn.kill.html)
+pub const EXIT_KILLED: isize = -2;
+
+/// Unique identifier of a process
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Pid(usize);
+
+impl Pid {
+    /// Returns process identifier with given number, the process does not need to exist.
+    pub const fn new(number: usize) -> Pid {
+        Pid(number)
+    }
+
+    /// Returns number of the process identifier.
+    pub const fn as_usize(&self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for Pid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// State of a process
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ProcessState {
+    /// Some thread of the process is running or ready to run
+    Running,
+    /// All threads of the process are blocked
+    Blocked,
+    /// Process has exited with given code and waits for its parent to collect it
+    Zombie(isize),
+}
+
+impl fmt::Display for ProcessState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProcessState::Running => write!(f, "running"),
+            ProcessState::Blocked => write!(f, "blocked"),
+            ProcessState::Zombie(code) => write!(f, "zombie({})", code),
+        }
+    }
+}
+
+/// Information about a process, for diagnostic purposes
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub pid: Pid,
+    /// `None` if the process has been started by the kernel
+    pub parent: Option<Pid>,
+    pub name: String,
+    pub state: ProcessState,
+    /// Number of milliseconds threads of this process have been running
+    pub cpu_time: u64,
+    /// Number of bytes of user memory mapped in process address space
+    pub memory: usize,
+}
+
+/// Reason why [`wait`](./fn.wait.html) has failed
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WaitError {
+    /// There is no process with given identifier
+    NoSuchProcess,
+    /// The process has not been started by the caller
+    NotChild,
+    /// The waiting thread has been interrupted, e.g. because its process has been killed
+    Interrupted,
+}
+
+impl fmt::Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WaitError::NoSuchProcess => write!(f, "no such process"),
+            WaitError::NotChild => write!(f, "not a child process"),
+            WaitError::Interrupted => write!(f, "interrupted"),
+        }
+    }
+}
+
+pub(super) struct Process {
+    pub(super) name: String,
+    parent: Option<Pid>,
+    /// Dropped when the process exits, which frees its memory once no thread runs in it
+    pub(super) address_space: Option<Arc<AddressSpace>>,
+    threads: Vec<ThreadId>,
+    exit_code: Option<isize>,
+    /// Set by `kill`, the process exits next time one of its threads enters the kernel
+    kill_pending: bool,
+    /// Number of user pages mapped
+    pub(super) pages: usize,
+    /// CPU time of threads which have already exited, in milliseconds
+    exited_cpu_time: u64,
+    /// Start of free space for anonymous mappings
+    pub(super) mmap_next: usize,
+    /// Anonymous mappings have to end below user stack
+    pub(super) mmap_end: usize,
+    /// Keyboard input, created by the first `read_key` system call
+    pub(super) key_input: Option<KeyInput>,
+}
+
+static NEXT_PID: AtomicUsize = AtomicUsize::new(1);
+
+lazy_static! {
+    static ref PROCESSES: IrqSpinLock<BTreeMap<Pid, Process>> =
+        IrqSpinLock::new(BTreeMap::new());
+
+    /// Woken whenever some process exits
+    static ref EXITED: WaitQueue = WaitQueue::new();
+}
+
+/// Adds new process without threads to the table, its parent is the calling process.
+pub(super) fn create(name: &str, address_space: Arc<AddressSpace>) -> Pid {
+    let pid = Pid(NEXT_PID.fetch_add(1, Ordering::Relaxed));
+    let process = Process {
+        name: String::from(name),
+        parent: current(),
+        address_space: Some(address_space),
+        threads: Vec::new(),
+        exit_code: None,
+        kill_pending: false,
+        pages: 0,
+        exited_cpu_time: 0,
+        mmap_next: 0,
+        mmap_end: 0,
+        key_input: None,
+    };
+
+    PROCESSES.lock().insert(pid, process);
+    pid
+}
+
+/// Adds copy of the calling process without threads to the table, with given address space.
+/// The new process is a child of the calling one.
+///
+/// ## Panics
+///
+/// Panics if current thread does not belong to any process.
+pub(super) fn create_fork(address_space: Arc<AddressSpace>) -> Pid {
+    let parent = current().expect("forking thread does not belong to any process");
+    let pid = Pid(NEXT_PID.fetch_add(1, Ordering::Relaxed));
+
+    let mut processes = PROCESSES.lock();
+    let process = {
+        let parent_process = &processes[&parent];
+        Process {
+            name: parent_process.name.clone(),
+            parent: Some(parent),
+            address_space: Some(address_space),
+            threads: Vec::new(),
+            exit_code: None,
+            kill_pending: false,
+            pages: parent_process.pages,
+            exited_cpu_time: 0,
+            mmap_next: parent_process.mmap_next,
+            mmap_end: parent_process.mmap_end,
+            key_input: None,
+        }
+    };
+
+    processes.insert(pid, process);
+    pid
+}
+
+/// Makes current thread part of given process.
+pub(super) fn attach_current_thread(pid: Pid) {
+    let id = thread::current();
+    if let Some(process) = PROCESSES.lock().get_mut(&pid) {
+        process.threads.push(id);
+    }
+}
+
+/// Runs `f` with process current thread belongs to, returns `None` for kernel threads.
+pub(super) fn with_current<R>(f: impl FnOnce(&mut Process) -> R) -> Option<R> {
+    let id = thread::current();
+    let mut processes = PROCESSES.lock();
+    processes
+        .values_mut()
+        .find(|process| process.threads.contains(&id))
+        .map(f)
+}
+
+/// Returns identifier of the process current thread belongs to, or `None` for kernel threads.
+pub fn current() -> Option<Pid> {
+    let id = thread::current();
+    PROCESSES
+        .lock()
+        .iter()
+        .find(|&(_, process)| process.threads.contains(&id))
+        .map(|(&pid, _)| pid)
+}
+
+/// Returns information about all processes, including zombies.
+pub fn list() -> Vec<ProcessInfo> {
+    let threads = thread::list();
+    let processes = PROCESSES.lock();
+
+    processes
+        .iter()
+        .map(|(&pid, process)| {
+            let mut cpu_time = process.exited_cpu_time;
+            let mut runnable = false;
+            for info in threads.iter().filter(|t| process.threads.contains(&t.id)) {
+                cpu_time += info.cpu_time;
+                runnable |= info.state == thread::State::Ready
+                    || info.state == thread::State::Running;
+            }
+
+            let state = match process.exit_code {
+                Some(code) => ProcessState::Zombie(code),
+                None if runnable => ProcessState::Running,
+                None => ProcessState::Blocked,
+            };
+
+            ProcessInfo {
+                pid,
+                parent: process.parent,
+                name: process.name.clone(),
+                state,
+                cpu_time,
+                memory: process.pages * PAGE_SIZE,
+            }
+        })
+        .collect()
+}
+
+/// Terminates current thread. If it is the last thread of its process, the process exits with
+/// given code: its memory is freed and its parent is notified.
+///
+/// ## Panics
+///
+/// Panics if current thread does not belong to any process.
+pub fn exit(code: isize) -> ! {
+    let id = thread::current();
+    let cpu_time = thread::list()
+        .into_iter()
+        .find(|info| info.id == id)
+        .map_or(0, |info| info.cpu_time);
+
+    let orphans = {
+        let mut processes = PROCESSES.lock();
+        let pid = processes
+            .iter()
+            .find(|&(_, process)| process.threads.contains(&id))
+            .map(|(&pid, _)| pid)
+            .expect("exiting thread does not belong to any process");
+
+        let last_thread = {
+            let process = processes.get_mut(&pid).unwrap();
+            process.threads.retain(|&thread| thread != id);
+            process.exited_cpu_time += cpu_time;
+            process.threads.is_empty()
+        };
+
+        if last_thread {
+            let process = processes.get_mut(&pid).unwrap();
+            process.exit_code = Some(code);
+            // This thread still runs in the address space, it is freed when the thread is gone
+            process.address_space = None;
+            process.pages = 0;
+            reparent_children(&mut processes, pid)
+        } else {
+            Vec::new()
+        }
+    };
+
+    // Zombie orphans would never be collected
+    drop(orphans);
+    EXITED.wake_all();
+
+    thread::exit();
+}
+
+/// Hands children of given process over to the kernel, zombies among them are removed from
+/// the table and returned.
+fn reparent_children(processes: &mut BTreeMap<Pid, Process>, parent: Pid) -> Vec<Process> {
+    let children: Vec<Pid> = processes
+        .iter()
+        .filter(|&(_, process)| process.parent == Some(parent))
+        .map(|(&pid, _)| pid)
+        .collect();
+
+    let mut zombies = Vec::new();
+    for pid in children {
+        let zombie = {
+            let child = processes.get_mut(&pid).unwrap();
+            child.parent = None;
+            child.exit_code.is_some()
+        };
+        if zombie {
+            zombies.extend(processes.remove(&pid));
+        }
+    }
+
+    zombies
+}
+
+/// Returns `true` if current thread belongs to a process which has been killed.
+pub fn is_killed() -> bool {
+    with_current(|process| process.kill_pending) == Some(true)
+}
+
+/// Terminates current thread if its process has been killed.
+pub fn exit_if_killed() {
+    if is_killed() {
+        exit(EXIT_KILLED);
+    }
+}
+
+/// Kills given process. Returns `false` if there is no such running process.
+///
+/// Threads of the process exit next time they enter the kernel. Their interruptible waits,
+/// e.g. for input or for child processes, are [interrupted], so that system calls return
+/// and the threads exit on their way back to user mode.
+///
+/// [interrupted]: ../../thread/fn.interrupt.html
+pub fn kill(pid: Pid) -> bool {
+    let threads = {
+        let mut processes = PROCESSES.lock();
+        match processes.get_mut(&pid) {
+            Some(ref mut process) if process.exit_code.is_none() => {
+                process.kill_pending = true;
+                process.threads.clone()
+            }
+            _ => return false,
+        }
+    };
+
+    for id in threads {
+        thread::interrupt(id);
+    }
+    true
+}
+
+/// Blocks until given child process exits, removes it from the process table and returns its
+/// exit code.
+///
+/// Kernel threads can wait for processes started by the kernel. Fails with
+/// `WaitError::Interrupted` if current thread gets interrupted while waiting.
+pub fn wait(pid: Pid) -> Result<isize, WaitError> {
+    let caller = current();
+    match PROCESSES.lock().get(&pid) {
+        Some(process) if process.parent != caller => return Err(WaitError::NotChild),
+        Some(_) => {}
+        None => return Err(WaitError::NoSuchProcess),
+    }
+
+    EXITED
+        .wait_until_interruptible(|| {
+            PROCESSES
+                .lock()
+                .get(&pid)
+                .map_or(true, |process| process.exit_code.is_some())
+        })
+        .map_err(|_| WaitError::Interrupted)?;
+
+    // Somebody else might have collected the process in the meantime
+    let process = PROCESSES
+        .lock()
+        .remove(&pid)
+        .ok_or(WaitError::NoSuchProcess)?;
+    Ok(process.exit_code.expect("process has not exited"))
+}
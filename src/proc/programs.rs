@@ -0,0 +1,49 @@
+//! Registry of programs which can be executed
+//!
+//! Programs are executable images kept in memory, currently delivered as Multiboot modules.
+//! Each module is registered under the file name from its command line, e.g. module loaded
+//! with `module2 /boot/hello` is available as `hello`.
+
+use alloc::{BTreeMap, String, Vec};
+use core::slice;
+
+use multiboot2::BootInformation;
+
+use sync::IrqSpinLock;
+
+lazy_static! {
+    static ref PROGRAMS: IrqSpinLock<BTreeMap<String, &'static [u8]>> =
+        IrqSpinLock::new(BTreeMap::new());
+}
+
+/// Registers all Multiboot modules as programs.
+///
+/// **Memory subsystem is required to be initialized, as it maps the modules.**
+pub fn register_boot_modules(boot_info: &BootInformation) {
+    for module in boot_info.module_tags() {
+        let start = module.start_address() as usize;
+        let end = module.end_address() as usize;
+        let image = unsafe { slice::from_raw_parts(start as *const u8, end - start) };
+
+        let path = module.name().split_whitespace().next().unwrap_or("");
+        let name = path.rsplit('/').next().unwrap_or("");
+        if !name.is_empty() {
+            register(name, image);
+        }
+    }
+}
+
+/// Registers program image under given name, replacing previous one.
+pub fn register(name: &str, image: &'static [u8]) {
+    PROGRAMS.lock().insert(name.into(), image);
+}
+
+/// Returns image of program with given name.
+pub fn find(name: &str) -> Option<&'static [u8]> {
+    PROGRAMS.lock().get(name).cloned()
+}
+
+/// Returns names of all registered programs.
+pub fn names() -> Vec<String> {
+    PROGRAMS.lock().keys().cloned().collect()
+}
@@ -0,0 +1,147 @@
+use core::mem::size_of;
+
+use x86_64::registers::msr::{rdmsr, wrmsr, IA32_EFER, IA32_FMASK, IA32_LSTAR, IA32_STAR};
+
+use kio::idt;
+
+/// `IA32_EFER` bit enabling `syscall`/`sysret` instructions
+const EFER_SCE: u64 = 1;
+
+/// `RFLAGS` bits cleared on system call entry: interrupts, trap and direction flags
+const SYSCALL_FLAGS_MASK: u64 = 0x700;
+
+/// Kernel stack of current thread, loaded by the entry stub
+#[no_mangle]
+static mut SYSCALL_KERNEL_RSP: usize = 0;
+
+/// User stack pointer, saved by the entry stub before it switches stacks
+#[no_mangle]
+static mut SYSCALL_USER_RSP: usize = 0;
+
+/// User registers saved by the entry stub at the top of kernel stack, lowest address first
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct SavedRegisters {
+    _padding: usize,
+    pub r9: usize,
+    pub r8: usize,
+    pub r10: usize,
+    pub rdx: usize,
+    pub rsi: usize,
+    pub rdi: usize,
+    pub r15: usize,
+    pub r14: usize,
+    pub r13: usize,
+    pub r12: usize,
+    pub rbp: usize,
+    pub rbx: usize,
+    /// User `RFLAGS`, saved in `r11` by `syscall`
+    pub rflags: usize,
+    /// Return address, saved in `rcx` by `syscall`
+    pub rip: usize,
+    pub rsp: usize,
+}
+
+/// Programs MSRs so that `syscall` instruction enters the kernel through `syscall_entry`.
+pub unsafe fn init() {
+    let selectors = idt::selectors();
+
+    // `syscall` loads CS from STAR[47:32] and SS from the next descriptor, `sysret` loads
+    // SS from STAR[63:48] + 8 and CS from STAR[63:48] + 16.
+    assert_eq!(selectors.kernel_data.0, selectors.kernel_code.0 + 8);
+    assert_eq!(selectors.user_code.0, selectors.user_data.0 + 8);
+
+    let syscall_base = selectors.kernel_code.0 as u64;
+    let sysret_base = (selectors.user_data.0 - 8) as u64;
+    let star = syscall_base << 32 | sysret_base << 48;
+
+    wrmsr(IA32_STAR, star);
+    wrmsr(IA32_LSTAR, syscall_entry as u64);
+    wrmsr(IA32_FMASK, SYSCALL_FLAGS_MASK);
+    wrmsr(IA32_EFER, rdmsr(IA32_EFER) | EFER_SCE);
+}
+
+/// Sets the stack which system calls of current thread are handled on.
+///
+/// **Interrupts are required to be disabled.**
+pub unsafe fn set_kernel_stack(stack_top: usize) {
+    SYSCALL_KERNEL_RSP = stack_top;
+}
+
+/// Returns top of the kernel stack of current thread.
+pub fn kernel_stack_top() -> usize {
+    unsafe { SYSCALL_KERNEL_RSP }
+}
+
+/// Returns user registers of current thread, saved when it has entered the kernel with
+/// system call.
+///
+/// **Current thread is required to be handling a system call.**
+pub unsafe fn saved_registers() -> SavedRegisters {
+    *((SYSCALL_KERNEL_RSP - size_of::<SavedRegisters>()) as *const SavedRegisters)
+}
+
+extern "C" {
+    fn syscall_entry();
+}
+
+// Entered by `syscall` with interrupts disabled, user RIP in rcx, user RFLAGS in r11 and
+// system call number and arguments in rax, rdi, rsi, rdx, r10, r8. Switches to kernel stack,
+// saves user registers as `SavedRegisters`, calls `syscall_dispatch` with interrupts enabled,
+// and returns its result in rax. All registers except rax, rcx and r11 are preserved.
+global_asm!(
+    r#"
+.intel_syntax noprefix
+.global syscall_entry
+syscall_entry:
+    mov qword ptr [rip + SYSCALL_USER_RSP], rsp
+    mov rsp, qword ptr [rip + SYSCALL_KERNEL_RSP]
+
+    push qword ptr [rip + SYSCALL_USER_RSP]
+    push rcx
+    push r11
+    push rbx
+    push rbp
+    push r12
+    push r13
+    push r14
+    push r15
+    push rdi
+    push rsi
+    push rdx
+    push r10
+    push r8
+    push r9
+    sub rsp, 8
+
+    mov r9, r8
+    mov r8, r10
+    mov rcx, rdx
+    mov rdx, rsi
+    mov rsi, rdi
+    mov rdi, rax
+
+    sti
+    call syscall_dispatch
+    cli
+
+    add rsp, 8
+    pop r9
+    pop r8
+    pop r10
+    pop rdx
+    pop rsi
+    pop rdi
+    pop r15
+    pop r14
+    pop r13
+    pop r12
+    pop rbp
+    pop rbx
+    pop r11
+    pop rcx
+    pop rsp
+    sysretq
+.att_syntax prefix
+"#
+);
@@ -0,0 +1,275 @@
+//! System calls
+//!
+//! User code enters the kernel with `syscall` instruction, passing system call number in `rax`
+//! and up to five arguments in `rdi`, `rsi`, `rdx`, `r10` and `r8`. Result is returned in `rax`,
+//! negative values are [`Error`] codes.
+//!
+//! [`Error`]: ./enum.Error.html
+
+mod entry;
+
+use alloc::VecDeque;
+use core::{ptr, slice, str};
+use core::mem::size_of;
+
+use dev;
+use dev::input::{InputDevice, InputEventKind, InputReader};
+use dev::kbd::{Kbd, KeyEvent};
+use kio::time::Instant;
+use mem;
+use mem::paging::{EntryFlags, Page};
+use thread::{self, Interrupted};
+
+use super::{process, Pid, WaitError, USER_END, USER_START};
+
+pub use self::entry::SavedRegisters;
+pub(super) use self::entry::{init, kernel_stack_top, saved_registers, set_kernel_stack};
+
+/// System call numbers
+pub mod nr {
+    /// `write(buf: *const u8, len: usize) -> usize`, writes UTF-8 text to console
+    pub const WRITE: usize = 0;
+    /// `read_key() -> u8`, blocks until a character is typed on keyboard, fails with
+    /// `KeysLost` once after keys have been typed faster than the process has read them.
+    /// Characters outside of ASCII are returned as UTF-8 bytes by consecutive calls
+    pub const READ_KEY: usize = 1;
+    /// `exit(code: isize) -> !`, terminates calling process
+    pub const EXIT: usize = 2;
+    /// `sleep(millis: u64)`, blocks calling thread for given time
+    pub const SLEEP: usize = 3;
+    /// `time() -> u64`, returns number of milliseconds since boot
+    pub const TIME: usize = 4;
+    /// `mmap(len: usize) -> *mut u8`, maps zeroed, anonymous memory
+    pub const MMAP: usize = 5;
+    /// `fork() -> usize`, creates copy of calling process, returns its identifier in the
+    /// caller and 0 in the copy
+    pub const FORK: usize = 6;
+    /// `wait(pid: usize, status: *mut isize)`, blocks until given child process exits and
+    /// stores its exit code, unless `status` is null
+    pub const WAIT: usize = 7;
+}
+
+/// Error codes returned by system calls, negated
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// There is no system call with given number
+    NoSuchCall = 1,
+    /// Pointer argument is not valid
+    BadAddress = 2,
+    /// Argument has invalid value
+    InvalidArgument = 3,
+    /// There is not enough memory
+    OutOfMemory = 4,
+    /// Required device is not present
+    NoDevice = 5,
+    /// There is no process with given identifier
+    NoSuchProcess = 6,
+    /// The process is not a child of calling process
+    NotChild = 7,
+    /// The call has been interrupted because calling process has been killed
+    Interrupted = 8,
+    /// Some keys have been lost, because they were typed faster than they were read
+    KeysLost = 9,
+}
+
+impl From<WaitError> for Error {
+    fn from(error: WaitError) -> Error {
+        match error {
+            WaitError::NoSuchProcess => Error::NoSuchProcess,
+            WaitError::NotChild => Error::NotChild,
+            WaitError::Interrupted => Error::Interrupted,
+        }
+    }
+}
+
+impl From<Interrupted> for Error {
+    fn from(_: Interrupted) -> Error {
+        Error::Interrupted
+    }
+}
+
+type Args = [usize; 5];
+
+type Handler = fn(Args) -> Result<usize, Error>;
+
+/// Handlers indexed by system call number
+static TABLE: [Handler; 8] = [
+    sys_write,
+    sys_read_key,
+    sys_exit,
+    sys_sleep,
+    sys_time,
+    sys_mmap,
+    sys_fork,
+    sys_wait,
+];
+
+/// Called by the entry stub with interrupts enabled, on kernel stack of calling thread.
+#[no_mangle]
+extern "C" fn syscall_dispatch(
+    nr: usize,
+    a0: usize,
+    a1: usize,
+    a2: usize,
+    a3: usize,
+    a4: usize,
+) -> isize {
+    let result = match TABLE.get(nr) {
+        Some(handler) => handler([a0, a1, a2, a3, a4]),
+        None => Err(Error::NoSuchCall),
+    };
+
+    super::exit_if_killed();
+
+    match result {
+        Ok(value) => value as isize,
+        Err(error) => -(error as isize),
+    }
+}
+
+fn sys_write(args: Args) -> Result<usize, Error> {
+    let bytes = user_slice(args[0], args[1])?;
+    let text = str::from_utf8(bytes).map_err(|_| Error::InvalidArgument)?;
+    print!("{}", text);
+    Ok(bytes.len())
+}
+
+/// Keyboard input of a process, from all keyboards installed when it first reads a key. It is
+/// kept between `read_key` calls so that keys typed while the process is busy are not missed.
+pub(super) struct KeyInput {
+    reader: InputReader,
+    /// UTF-8 bytes of characters which have been read from the keyboard, but not by
+    /// the process yet
+    pending: VecDeque<u8>,
+    /// Number of lost keys which have been reported to the process
+    reported_lost: u64,
+}
+
+impl KeyInput {
+    fn new() -> Result<KeyInput, Error> {
+        let keyboards = dev::mgr::devices_of::<Kbd>();
+        if keyboards.is_empty() {
+            return Err(Error::NoDevice);
+        }
+
+        let mut reader = InputReader::new();
+        for keyboard in keyboards {
+            reader.follow(keyboard.downcast::<Kbd>().events());
+        }
+
+        Ok(KeyInput {
+            reader,
+            pending: VecDeque::new(),
+            reported_lost: 0,
+        })
+    }
+
+    fn read(&mut self) -> Result<u8, Error> {
+        loop {
+            if self.reader.lost() != self.reported_lost {
+                self.reported_lost = self.reader.lost();
+                return Err(Error::KeysLost);
+            }
+
+            if let Some(char) = self.pending.pop_front() {
+                return Ok(char);
+            }
+
+            if let InputEventKind::Key(KeyEvent { char: Some(char), .. }) =
+                self.reader.wait_interruptible()?.kind
+            {
+                let mut utf8 = [0; 4];
+                self.pending.extend(char.encode_utf8(&mut utf8).bytes());
+            }
+        }
+    }
+}
+
+fn sys_read_key(_: Args) -> Result<usize, Error> {
+    let input = process::with_current(|process| process.key_input.take()).and_then(|input| input);
+    let mut input = match input {
+        Some(input) => input,
+        None => KeyInput::new()?,
+    };
+
+    let result = input.read();
+    process::with_current(|process| process.key_input = Some(input));
+    result.map(|char| char as usize)
+}
+
+fn sys_exit(args: Args) -> Result<usize, Error> {
+    super::exit(args[0] as isize);
+}
+
+fn sys_sleep(args: Args) -> Result<usize, Error> {
+    thread::sleep_interruptible(args[0] as u64)?;
+    Ok(0)
+}
+
+fn sys_time(_: Args) -> Result<usize, Error> {
+    Ok(Instant::now().as_millis() as usize)
+}
+
+fn sys_mmap(args: Args) -> Result<usize, Error> {
+    super::map_anonymous(args[0])
+}
+
+fn sys_fork(_: Args) -> Result<usize, Error> {
+    super::fork().map(|pid| pid.as_usize())
+}
+
+fn sys_wait(args: Args) -> Result<usize, Error> {
+    let status = args[1];
+    if status != 0 {
+        check_user_range(status, size_of::<isize>(), true)?;
+    }
+
+    let code = super::wait(Pid::new(args[0]))?;
+    if status != 0 {
+        unsafe { ptr::write_unaligned(status as *mut isize, code) };
+    }
+    Ok(0)
+}
+
+/// Returns user memory as byte slice, making sure it is mapped and accessible from user mode.
+fn user_slice<'a>(ptr: usize, len: usize) -> Result<&'a [u8], Error> {
+    check_user_range(ptr, len, false)?;
+    Ok(unsafe { slice::from_raw_parts(ptr as *const u8, len) })
+}
+
+/// Checks that given range of user memory is mapped in calling process' page tables and
+/// accessible from user mode, and writable if requested. Copy-on-write pages are copied
+/// right away when writable memory is requested, so that the kernel can write to them.
+fn check_user_range(start: usize, len: usize, writable: bool) -> Result<(), Error> {
+    if len == 0 {
+        return Ok(());
+    }
+
+    let end = start.checked_add(len).ok_or(Error::BadAddress)?;
+    if start < USER_START || end > USER_END {
+        return Err(Error::BadAddress);
+    }
+
+    let required = if writable {
+        EntryFlags::USER_ACCESSIBLE | EntryFlags::WRITABLE
+    } else {
+        EntryFlags::USER_ACCESSIBLE
+    };
+
+    let start_page = Page::containing_address(start);
+    let end_page = Page::containing_address(end - 1);
+
+    for page in Page::range_inclusive(start_page, end_page) {
+        match mem::with_active_table(|table, _| table.page_flags(page)) {
+            Some(flags) if writable && flags.contains(EntryFlags::COPY_ON_WRITE) => {
+                if !mem::copy_on_write(page.start_address()) {
+                    return Err(Error::OutOfMemory);
+                }
+            }
+            Some(flags) if flags.contains(required) => {}
+            _ => return Err(Error::BadAddress),
+        }
+    }
+
+    Ok(())
+}
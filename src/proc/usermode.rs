@@ -0,0 +1,121 @@
+use core::ptr;
+
+use x86_64::VirtualAddress;
+use x86_64::structures::idt::ExceptionStackFrame;
+
+use kio::idt;
+
+use super::syscall::{self, SavedRegisters};
+
+/// Initial `RFLAGS` value of user code: interrupts enabled, reserved bit 1 set.
+const USER_RFLAGS: usize = 0x202;
+
+/// `RFLAGS` value of kernel code entered by `return_to_kernel`: interrupts enabled
+const KERNEL_RFLAGS: u64 = 0x202;
+
+/// Drops current thread to ring 3, starting execution at `rip` with stack pointer `rsp`.
+///
+/// General purpose registers are cleared, so no kernel data leaks to user code. Interrupts and
+/// exceptions raised in user mode arrive on current thread's kernel stack.
+///
+/// **Both addresses have to be in user accessible pages, and current thread must not be the
+/// boot thread, which has no kernel stack of its own.**
+pub unsafe fn enter_user_mode(rip: usize, rsp: usize) -> ! {
+    let selectors = idt::selectors();
+    let cs = selectors.user_code.0 as usize;
+    let ss = selectors.user_data.0 as usize;
+
+    // Build interrupt stack frame and "return" from interrupt to user mode
+    asm!("
+        push $0
+        push $1
+        push $2
+        push $3
+        push $4
+        xor %rax, %rax
+        xor %rbx, %rbx
+        xor %rcx, %rcx
+        xor %rdx, %rdx
+        xor %rsi, %rsi
+        xor %rdi, %rdi
+        xor %rbp, %rbp
+        xor %r8, %r8
+        xor %r9, %r9
+        xor %r10, %r10
+        xor %r11, %r11
+        xor %r12, %r12
+        xor %r13, %r13
+        xor %r14, %r14
+        xor %r15, %r15
+        iretq"
+        :
+        : "r"(ss), "r"(rsp), "r"(USER_RFLAGS), "r"(cs), "r"(rip)
+        : "memory"
+        : "volatile");
+
+    unreachable!("returned from user mode entry");
+}
+
+/// Makes interrupt handler which has interrupted user code return to `entry` in kernel mode
+/// instead, on empty kernel stack of current thread and with interrupts enabled.
+///
+/// **`stack_frame` has to be the frame of an interrupt which has arrived in user mode, and
+/// the handler must return right after this call.**
+pub unsafe fn return_to_kernel(stack_frame: &mut ExceptionStackFrame, entry: extern "C" fn() -> !) {
+    let selectors = idt::selectors();
+
+    // Stack is aligned as if `entry` has been called, it never returns anyway
+    let frame = ExceptionStackFrame {
+        instruction_pointer: VirtualAddress(entry as usize),
+        code_segment: selectors.kernel_code.0 as u64,
+        cpu_flags: KERNEL_RFLAGS,
+        stack_pointer: VirtualAddress(syscall::kernel_stack_top() - 8),
+        stack_segment: selectors.kernel_data.0 as u64,
+    };
+
+    // The compiler does not know the frame is read by `iretq`
+    ptr::write_volatile(stack_frame, frame);
+}
+
+/// Drops current thread to ring 3 as if it was returning from system call with result `rax`,
+/// restoring given user registers.
+///
+/// **Registers have to be saved by system call entry of a thread running in the same address
+/// space, and current thread must not be the boot thread.**
+pub unsafe fn return_to_user_mode(registers: &SavedRegisters, rax: usize) -> ! {
+    let selectors = idt::selectors();
+    let cs = selectors.user_code.0 as usize;
+    let ss = selectors.user_data.0 as usize;
+
+    // Build interrupt stack frame from saved registers, restore the rest of them and "return"
+    // from interrupt. Result is held in `r11` until all registers are loaded, `rcx` and `r11`
+    // end up with the same values as after `sysret`.
+    asm!("
+        push %rdx
+        push 0x78(%rax)
+        push 0x68(%rax)
+        push %rcx
+        push 0x70(%rax)
+        mov 0x08(%rax), %r9
+        mov 0x10(%rax), %r8
+        mov 0x18(%rax), %r10
+        mov 0x20(%rax), %rdx
+        mov 0x28(%rax), %rsi
+        mov 0x30(%rax), %rdi
+        mov 0x38(%rax), %r15
+        mov 0x40(%rax), %r14
+        mov 0x48(%rax), %r13
+        mov 0x50(%rax), %r12
+        mov 0x58(%rax), %rbp
+        mov 0x60(%rax), %rbx
+        mov 0x70(%rax), %rcx
+        mov %r11, %rax
+        mov 0x10(%rsp), %r11
+        iretq"
+        :
+        : "{rax}"(registers as *const SavedRegisters), "{rcx}"(cs), "{rdx}"(ss), "{r11}"(rax)
+        : "memory"
+        : "volatile");
+
+    unreachable!("returned from user mode entry");
+}
@@ -0,0 +1,93 @@
+//! `kbdctl` command, shows and changes keyboard settings
+
+use alloc::Vec;
+use core::str;
+
+use dev;
+use dev::kbd::{Kbd, Leds, Typematic};
+
+use super::print_error;
+
+const USAGE: &str =
+    "usage: kbdctl [leds [caps] [num] [scroll] | repeat <delay-ms> <rate> | set <n>]";
+
+/// Runs the command with given arguments on the first keyboard.
+pub fn run(args: &[u8]) {
+    let args: Vec<&str> = str::from_utf8(args)
+        .unwrap_or("")
+        .split_whitespace()
+        .collect();
+
+    let device = match dev::mgr::devices_of::<Kbd>().into_iter().next() {
+        Some(device) => device,
+        None => return print_error("no keyboard"),
+    };
+    let kbd = device.downcast::<Kbd>();
+
+    let result = match args.split_first() {
+        None => return show(kbd),
+        Some((&"leds", names)) => match parse_leds(names) {
+            Some(leds) => kbd.set_leds(leds),
+            None => return println!("{}", USAGE),
+        },
+        Some((&"repeat", &[delay, rate])) => match (delay.parse(), rate.parse()) {
+            (Ok(delay), Ok(rate)) => kbd.set_typematic(Typematic { delay, rate })
+                .map(|applied| print_typematic(&applied)),
+            _ => return println!("{}", USAGE),
+        },
+        Some((&"set", &[set])) => match set.parse() {
+            Ok(set) => kbd.set_scancode_set(set),
+            Err(_) => return println!("{}", USAGE),
+        },
+        Some(_) => return println!("{}", USAGE),
+    };
+
+    if let Err(error) = result {
+        print_error(error);
+    }
+}
+
+fn show(kbd: &Kbd) {
+    let leds = kbd.leds();
+    print!("leds:");
+    for &(led, name) in LED_NAMES.iter() {
+        if leds.contains(led) {
+            print!(" {}", name);
+        }
+    }
+    println!();
+
+    match kbd.typematic() {
+        Some(typematic) => print_typematic(&typematic),
+        None => println!("repeat: unknown"),
+    }
+
+    match kbd.scancode_set() {
+        Ok(set) => println!("scancode set: {}", set),
+        Err(error) => println!("scancode set: {}", error),
+    }
+
+    println!("dropped keys: {}", kbd.dropped_keys());
+}
+
+fn print_typematic(typematic: &Typematic) {
+    println!(
+        "repeat: {} ms delay, {} per second",
+        typematic.delay, typematic.rate
+    );
+}
+
+const LED_NAMES: [(Leds, &str); 3] = [
+    (Leds::CAPS_LOCK, "caps"),
+    (Leds::NUM_LOCK, "num"),
+    (Leds::SCROLL_LOCK, "scroll"),
+];
+
+fn parse_leds(names: &[&str]) -> Option<Leds> {
+    let mut leds = Leds::empty();
+    for name in names {
+        let &(led, _) = LED_NAMES.iter().find(|&&(_, led_name)| led_name == *name)?;
+        leds.insert(led);
+    }
+    Some(leds)
+}
@@ -0,0 +1,82 @@
+//! `lspci` command, lists PCI functions
+
+use alloc::Vec;
+use core::str;
+
+use dev;
+use dev::pci::{Bar, PciDevice};
+
+const USAGE: &str = "usage: lspci [-v]";
+
+/// Prints every `pci` device, ordered by address. With `-v` also prints base address
+/// registers, interrupt and capabilities.
+pub fn run(args: &[u8]) {
+    let args: Vec<&str> = str::from_utf8(args)
+        .unwrap_or("")
+        .split_whitespace()
+        .collect();
+
+    let verbose = match args.as_slice() {
+        &[] => false,
+        &["-v"] => true,
+        _ => return println!("{}", USAGE),
+    };
+
+    let mut devices = dev::mgr::devices_of::<PciDevice>();
+    devices.sort_unstable_by_key(|device| device.downcast::<PciDevice>().address());
+
+    for device in devices.iter() {
+        let pci = device.downcast::<PciDevice>();
+        println!(
+            "{} {} [{:02x}{:02x}{:02x}]: {:04x}:{:04x} (rev {:02x})",
+            pci.address(),
+            pci.class_name(),
+            pci.class,
+            pci.subclass,
+            pci.prog_if,
+            pci.vendor_id,
+            pci.device_id,
+            pci.revision
+        );
+        if verbose {
+            show_details(pci);
+        }
+    }
+}
+
+fn show_details(pci: &PciDevice) {
+    for (index, bar) in pci.bars.iter().enumerate() {
+        match *bar {
+            Some(Bar::Memory {
+                address,
+                size,
+                prefetchable,
+                wide,
+            }) => println!(
+                "    BAR{}: memory at {:#x}, {} KiB, {}-bit{}",
+                index,
+                address,
+                size / 1024,
+                if wide { 64 } else { 32 },
+                if prefetchable { ", prefetchable" } else { "" }
+            ),
+            Some(Bar::Io { port, size }) => {
+                println!("    BAR{}: I/O ports at {:#x}, {} bytes", index, port, size)
+            }
+            None => {}
+        }
+    }
+
+    if let Some(pin) = pci.interrupt_pin {
+        println!("    interrupt: {}, IRQ {}", pin, pci.interrupt_line);
+    }
+
+    for capability in pci.capabilities.iter() {
+        println!(
+            "    capability {:#04x} at {:#04x}: {}",
+            capability.id,
+            capability.offset,
+            capability.name().unwrap_or("unknown")
+        );
+    }
+}
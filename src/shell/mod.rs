@@ -1,11 +1,20 @@
 mod calc;
+mod kbdctl;
+mod lspci;
 
-use alloc::Vec;
+use alloc::{String, Vec};
+use alloc::arc::Arc;
+use core::fmt::Display;
+use core::str;
 
 use dev;
-use dev::kbd::Kbd;
+use dev::console::{ConsoleInput, ConsoleKey};
+use dev::kbd::keymap;
+use dev::mgr::{CommonDevice, DeviceState};
 use dev::text_video::{TextColor, TextStyle};
 use kio;
+use proc;
+use thread;
 
 const PROMPT_STYLE: TextStyle = TextStyle {
     foreground: TextColor::White,
@@ -17,11 +26,20 @@ const ERROR_STYLE: TextStyle = TextStyle {
     background: TextColor::Black,
 };
 
+/// Number of previous command lines which can be recalled with arrow keys
+const HISTORY_SIZE: usize = 32;
+
+/// Ctrl+C, kills program running in foreground
+const CTRL_C: u8 = 0x03;
+
 pub fn start() {
     print_header();
+    // Keys typed while a command runs are not lost, they are read by the next prompt
+    let mut input = ConsoleInput::new();
+    let mut history = Vec::new();
     loop {
-        let cmd = prompt();
-        exec(&cmd);
+        let cmd = prompt(&mut input, &mut history);
+        exec(&mut input, &cmd);
     }
 }
 
@@ -52,40 +70,116 @@ fn print_header() {
     }
 }
 
-fn prompt() -> Vec<u8> {
-    let kbd_dev = dev::mgr::get_device("kbd0").unwrap();
-    let kbd = kbd_dev.downcast::<Kbd>();
+/// Reads command line from console input, i.e. any keyboard or serial terminal.
+///
+/// Backspace erases last character, up and down arrows walk through `history`, which gets
+/// the new line appended. The line is UTF-8 encoded.
+fn prompt(input: &mut ConsoleInput, history: &mut Vec<Vec<u8>>) -> Vec<u8> {
     let mut line_vec = Vec::new();
+    // Bytes of non-ASCII character which has not been received whole yet
+    let mut partial = Vec::new();
+    // Position in history, `history.len()` stands for the line being edited
+    let mut recalled = history.len();
 
     kio::with_output_style(PROMPT_STYLE, || {
         print!("> ");
 
         loop {
-            let key: u8 = kbd.wait().into();
-            if key == 0 {
-                continue;
-            }
-            if key == b'\n' {
-                println!();
-                break;
+            match input.read_key() {
+                ConsoleKey::Byte(b'\n') => {
+                    println!();
+                    break;
+                }
+                // Terminals send DEL on Backspace
+                ConsoleKey::Byte(b'\x08') | ConsoleKey::Byte(b'\x7f') => {
+                    if pop_char(&mut line_vec) {
+                        erase(1);
+                    }
+                }
+                ConsoleKey::Up if recalled > 0 => {
+                    recalled -= 1;
+                    replace_line(&mut line_vec, &history[recalled]);
+                }
+                ConsoleKey::Down if recalled < history.len() => {
+                    recalled += 1;
+                    // Going past the last line brings back an empty one
+                    let line = history.get(recalled).map_or(&[][..], |line| &line[..]);
+                    replace_line(&mut line_vec, line);
+                }
+                ConsoleKey::Byte(key @ b' '...b'~') => {
+                    print!("{}", key as char);
+                    line_vec.push(key);
+                }
+                ConsoleKey::Byte(byte) if byte >= 0x80 => {
+                    partial.push(byte);
+                    let done = match str::from_utf8(&partial) {
+                        Ok(text) => {
+                            print!("{}", text);
+                            line_vec.extend_from_slice(text.as_bytes());
+                            true
+                        }
+                        // Invalid sequence is dropped, incomplete one waits for the rest
+                        Err(error) => error.error_len().is_some(),
+                    };
+                    if done {
+                        partial.clear();
+                    }
+                }
+                _ => {}
             }
-            print!("{}", key as char);
-            line_vec.push(key);
         }
     });
 
+    if !line_vec.is_empty() && history.last() != Some(&line_vec) {
+        if history.len() == HISTORY_SIZE {
+            history.remove(0);
+        }
+        history.push(line_vec.clone());
+    }
+
     line_vec
 }
 
-fn exec(cmd: &[u8]) {
-    match cmd {
-        b"lsdev" => {
-            let mut all = dev::mgr::all();
-            all.sort_unstable_by_key(|d| d.name());
-            for dev in all.iter() {
-                println!("{}", dev.name());
-            }
+/// Removes the last character of UTF-8 encoded line, returns `false` if the line is empty.
+fn pop_char(line_vec: &mut Vec<u8>) -> bool {
+    while let Some(byte) = line_vec.pop() {
+        // UTF-8 continuation bytes are `10xx_xxxx`
+        if byte & 0xc0 != 0x80 {
+            return true;
         }
+    }
+    false
+}
+
+/// Replaces edited line with `new` one, both in `line_vec` and on the screen.
+fn replace_line(line_vec: &mut Vec<u8>, new: &[u8]) {
+    erase(String::from_utf8_lossy(line_vec).chars().count());
+    *line_vec = new.to_vec();
+    print!("{}", String::from_utf8_lossy(line_vec));
+}
+
+/// Erases given number of characters before the cursor.
+fn erase(count: usize) {
+    for _ in 0..count {
+        print!("\x08 \x08");
+    }
+}
+
+fn exec(input: &mut ConsoleInput, cmd: &[u8]) {
+    match cmd {
+        b"lsdev" => list_devices(),
+
+        b"ps" => list_processes(),
+        b"keymap" => list_keymaps(),
+        b"kbdctl" => kbdctl::run(b""),
+        b"lspci" => lspci::run(b""),
+
+        cmd if cmd.starts_with(b"exec ") => exec_program(input, &cmd[5..]),
+        cmd if cmd.starts_with(b"kill ") => kill_process(&cmd[5..]),
+        cmd if cmd.starts_with(b"devctl ") => control_device(&cmd[7..]),
+        cmd if cmd.starts_with(b"keymap ") => set_keymap(&cmd[7..]),
+        cmd if cmd.starts_with(b"kbdctl ") => kbdctl::run(&cmd[7..]),
+        cmd if cmd.starts_with(b"lspci ") => lspci::run(&cmd[6..]),
 
         expr => match calc::eval(expr) {
             Ok(result) => println!("{}", result),
@@ -97,3 +191,163 @@ fn exec(cmd: &[u8]) {
         },
     }
 }
+
+/// Runs user program, the first argument is program name. The shell waits for it to exit,
+/// unless the last argument is `&`. Ctrl+C kills program running in foreground.
+fn exec_program(input: &mut ConsoleInput, args: &[u8]) {
+    let mut args: Vec<&str> = str::from_utf8(args)
+        .unwrap_or("")
+        .split_whitespace()
+        .collect();
+    let background = args.last() == Some(&"&");
+    if background {
+        args.pop();
+    }
+
+    let result = match args.first() {
+        Some(name) => proc::exec(name, &args),
+        None => {
+            println!("usage: exec <name> [args...] [&]");
+            return;
+        }
+    };
+
+    let pid = match result {
+        Ok(pid) => pid,
+        Err(error) => return print_error(error),
+    };
+
+    if background {
+        println!("started process {}", pid);
+        // Somebody has to collect the process once it exits
+        thread::spawn_named("exec-wait", move || report_exit(pid, proc::wait(pid)));
+        return;
+    }
+
+    // Separate reader, so that keys typed into the program are not taken from it
+    let mut interrupts = ConsoleInput::new();
+    let watcher = thread::spawn_named("ctrl-c", move || {
+        while let Ok(key) = interrupts.read_key_interruptible() {
+            if key == ConsoleKey::Byte(CTRL_C) {
+                proc::kill(pid);
+            }
+        }
+    });
+
+    let result = proc::wait(pid);
+    thread::interrupt(watcher.id());
+    watcher.join();
+    // Keys typed into the program are not commands
+    input.discard();
+
+    report_exit(pid, result);
+}
+
+/// Prints exit code of process, unless it has exited successfully.
+fn report_exit(pid: proc::Pid, result: Result<isize, proc::WaitError>) {
+    match result {
+        Ok(0) => {}
+        Ok(code) => println!("process {} exited with code {}", pid, code),
+        Err(error) => print_error(error),
+    }
+}
+
+/// Prints process table: identifiers, state, CPU time in milliseconds and memory use.
+fn list_processes() {
+    println!("  PID  PPID STATE        CPU(ms)  MEM(KiB) NAME");
+    for info in proc::list() {
+        let parent = match info.parent {
+            Some(pid) => format!("{}", pid),
+            None => String::from("-"),
+        };
+        println!(
+            "{:>5} {:>5} {:<12} {:>8} {:>9} {}",
+            info.pid,
+            parent,
+            format!("{}", info.state),
+            info.cpu_time,
+            info.memory / 1024,
+            info.name
+        );
+    }
+}
+
+/// Kills process with given identifier.
+fn kill_process(arg: &[u8]) {
+    let pid = str::from_utf8(arg)
+        .ok()
+        .and_then(|arg| arg.trim().parse().ok())
+        .map(proc::Pid::new);
+
+    match pid {
+        Some(pid) if proc::kill(pid) => {}
+        Some(pid) => print_error(format!("no running process {}", pid)),
+        None => println!("usage: kill <pid>"),
+    }
+}
+
+/// Suspends, resumes or uninstalls device together with its children.
+fn control_device(args: &[u8]) {
+    let args: Vec<&str> = str::from_utf8(args)
+        .unwrap_or("")
+        .split_whitespace()
+        .collect();
+
+    let found = match args.as_slice() {
+        &["suspend", name] => dev::mgr::suspend(name),
+        &["resume", name] => dev::mgr::resume(name),
+        &["remove", name] => dev::mgr::uninstall(name),
+        _ => return println!("usage: devctl suspend|resume|remove <device>"),
+    };
+
+    if !found {
+        print_error("no such device");
+    }
+}
+
+/// Prints device tree, children are indented under their parents, bound drivers are shown
+/// in brackets and suspended devices are marked.
+fn list_devices() {
+    let mut all = dev::mgr::all();
+    all.sort_unstable_by_key(|d| d.name());
+
+    fn print_subtree(all: &[Arc<CommonDevice>], parent: Option<&str>, depth: usize) {
+        for dev in all.iter().filter(|d| d.parent() == parent) {
+            let name = dev.name();
+            print!("{:width$}{}", "", name, width = depth * 2);
+            if let Some(driver) = dev.driver() {
+                print!(" [{}]", driver);
+            }
+            if dev.state() == DeviceState::Suspended {
+                print!(" (suspended)");
+            }
+            println!();
+            print_subtree(all, Some(&name), depth + 1);
+        }
+    }
+
+    print_subtree(&all, None, 0);
+}
+
+fn list_keymaps() {
+    let active = keymap::active();
+    for map in keymap::all() {
+        let marker = if map.name == active.name { '*' } else { ' ' };
+        println!("{} {:6} {}", marker, map.name, map.description);
+    }
+}
+
+fn set_keymap(arg: &[u8]) {
+    let name = str::from_utf8(arg).map(|arg| arg.trim()).unwrap_or("");
+    if name.is_empty() {
+        println!("usage: keymap [name]");
+    } else if !keymap::set_active(name) {
+        print_error(format!("unknown keymap {}", name));
+    }
+}
+
+fn print_error(error: impl Display) {
+    kio::with_output_style(ERROR_STYLE, || {
+        println!("error: {}", error);
+    });
+}
@@ -0,0 +1,61 @@
+use executor::Waker;
+use thread;
+
+use super::{MutexGuard, WaitQueue};
+
+/// Condition variable, letting threads sleep until some state protected by [`Mutex`] changes.
+///
+/// Spurious wakeups are possible, so the condition should be checked in a loop.
+///
+/// ## Examples
+///
+/// ```
+/// let mut ready = mutex.lock();
+/// while !*ready {
+///     ready = condvar.wait(ready);
+/// }
+/// ```
+///
+/// [`Mutex`]: ./struct.Mutex.html
+pub struct Condvar {
+    queue: WaitQueue,
+}
+
+impl Condvar {
+    /// Creates new condition variable.
+    pub fn new() -> Condvar {
+        Condvar {
+            queue: WaitQueue::new(),
+        }
+    }
+
+    /// Releases the mutex, blocks current thread until notified, and locks the mutex again.
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = guard.mutex();
+
+        // Registering before unlocking makes sure notification sent right after unlock is not lost
+        let id = self.queue.register(Waker::for_thread(thread::current()));
+        drop(guard);
+
+        thread::block();
+        self.queue.unregister(id);
+
+        mutex.lock()
+    }
+
+    /// Wakes one of waiting threads.
+    pub fn notify_one(&self) {
+        self.queue.wake_one();
+    }
+
+    /// Wakes all waiting threads.
+    pub fn notify_all(&self) {
+        self.queue.wake_all();
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Condvar {
+        Condvar::new()
+    }
+}
@@ -0,0 +1,141 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use kio::idt;
+
+/// Spin lock which disables interrupts while it is held.
+///
+/// Plain spin locks are not safe to use in data shared with interrupt handlers: if an interrupt
+/// arrives while the lock is held, and its handler tries to take the same lock, CPU will spin
+/// forever. `IrqSpinLock` saves current interrupt state and disables interrupts before taking
+/// the lock, and restores it after releasing, so no handler can preempt lock owner.
+///
+/// Exceptions can still occur inside critical section, fatal exception handlers may use
+/// [`force_unlock`] to get out of such situation.
+///
+/// [`force_unlock`]: #method.force_unlock
+pub struct IrqSpinLock<T: ?Sized> {
+    locked: AtomicBool,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Sync for IrqSpinLock<T> {}
+
+unsafe impl<T: ?Sized + Send> Send for IrqSpinLock<T> {}
+
+impl<T> IrqSpinLock<T> {
+    /// Creates new, unlocked lock wrapping `data`.
+    pub const fn new(data: T) -> IrqSpinLock<T> {
+        IrqSpinLock {
+            locked: AtomicBool::new(false),
+            data: UnsafeCell::new(data),
+        }
+    }
+}
+
+impl<T: ?Sized> IrqSpinLock<T> {
+    /// Disables interrupts and spins until lock is acquired.
+    ///
+    /// Interrupts are restored to previous state when returned guard is dropped.
+    pub fn lock(&self) -> IrqSpinLockGuard<T> {
+        let were_enabled = disable_interrupts();
+
+        while self.locked.compare_and_swap(false, true, Ordering::Acquire) {
+            // Wait until lock looks unlocked before retrying, to not hammer the bus with writes.
+            while self.locked.load(Ordering::Relaxed) {}
+        }
+
+        IrqSpinLockGuard {
+            lock: self,
+            were_enabled,
+        }
+    }
+
+    /// Tries to acquire lock without spinning.
+    pub fn try_lock(&self) -> Option<IrqSpinLockGuard<T>> {
+        let were_enabled = disable_interrupts();
+
+        if !self.locked.compare_and_swap(false, true, Ordering::Acquire) {
+            Some(IrqSpinLockGuard {
+                lock: self,
+                were_enabled,
+            })
+        } else {
+            if were_enabled {
+                unsafe { idt::enable() };
+            }
+            None
+        }
+    }
+
+    /// Returns `true` if lock is currently held.
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+
+    /// Forcibly releases the lock, regardless of who holds it.
+    ///
+    /// This is only meant to be used in panic and fatal exception handlers, which need to
+    /// print something, even if faulting code held the lock.
+    pub unsafe fn force_unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+    }
+}
+
+impl<T: Default> Default for IrqSpinLock<T> {
+    fn default() -> IrqSpinLock<T> {
+        IrqSpinLock::new(Default::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for IrqSpinLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => write!(f, "IrqSpinLock {{ data: {:?} }}", &*guard),
+            None => write!(f, "IrqSpinLock {{ <locked> }}"),
+        }
+    }
+}
+
+/// RAII guard of [`IrqSpinLock`], releases the lock and restores interrupts when dropped.
+///
+/// [`IrqSpinLock`]: ./struct.IrqSpinLock.html
+pub struct IrqSpinLockGuard<'a, T: ?Sized + 'a> {
+    lock: &'a IrqSpinLock<T>,
+    were_enabled: bool,
+}
+
+impl<'a, T: ?Sized> Deref for IrqSpinLockGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for IrqSpinLockGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for IrqSpinLockGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+        if self.were_enabled {
+            unsafe { idt::enable() };
+        }
+    }
+}
+
+/// Disables interrupts, returning `true` if they were enabled before.
+#[inline]
+fn disable_interrupts() -> bool {
+    let were_enabled = idt::are_enabled();
+    if were_enabled {
+        unsafe { idt::disable() };
+    }
+    were_enabled
+}
@@ -0,0 +1,27 @@
+//! Synchronization primitives
+//!
+//! [`IrqSpinLock`] protects short critical sections, including those shared with interrupt
+//! handlers. Code which may wait for a long time should use sleeping primitives built on
+//! [`WaitQueue`]: [`Semaphore`], [`Mutex`] and [`Condvar`]. Data passed from a single interrupt
+//! handler to single consumer can also go through lock-free [`spsc_ring`].
+//!
+//! [`IrqSpinLock`]: ./struct.IrqSpinLock.html
+//! [`WaitQueue`]: ./struct.WaitQueue.html
+//! [`Semaphore`]: ./struct.Semaphore.html
+//! [`Mutex`]: ./struct.Mutex.html
+//! [`Condvar`]: ./struct.Condvar.html
+//! [`spsc_ring`]: ./fn.spsc_ring.html
+
+mod condvar;
+mod irq_spin_lock;
+mod mutex;
+mod semaphore;
+mod spsc_ring;
+mod wait_queue;
+
+pub use self::condvar::Condvar;
+pub use self::irq_spin_lock::{IrqSpinLock, IrqSpinLockGuard};
+pub use self::mutex::{Mutex, MutexGuard};
+pub use self::semaphore::Semaphore;
+pub use self::spsc_ring::{spsc_ring, RingConsumer, RingProducer};
+pub use self::wait_queue::{WaitQueue, WaiterId};
@@ -0,0 +1,46 @@
+use super::{IrqSpinLock, WaitQueue};
+
+/// Counting semaphore, blocking threads until a permit is available.
+///
+/// Permits can be released from interrupt handlers and deferred work.
+pub struct Semaphore {
+    permits: IrqSpinLock<usize>,
+    queue: WaitQueue,
+}
+
+impl Semaphore {
+    /// Creates new semaphore with given number of permits.
+    pub fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            permits: IrqSpinLock::new(permits),
+            queue: WaitQueue::new(),
+        }
+    }
+
+    /// Blocks current thread until permit is available, and takes it.
+    pub fn acquire(&self) {
+        self.queue.wait_until(|| self.try_acquire());
+    }
+
+    /// Takes permit if one is available, without blocking.
+    pub fn try_acquire(&self) -> bool {
+        let mut permits = self.permits.lock();
+        if *permits > 0 {
+            *permits -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns permit to the semaphore, waking one of waiting threads.
+    pub fn release(&self) {
+        *self.permits.lock() += 1;
+        self.queue.wake_one();
+    }
+
+    /// Returns number of currently available permits.
+    pub fn available(&self) -> usize {
+        *self.permits.lock()
+    }
+}
@@ -0,0 +1,151 @@
+//! Lock-free single-producer/single-consumer ring buffer
+//!
+//! Fixed-capacity ring for passing values from one interrupt handler to one consumer, e.g.
+//! bytes received by a device to deferred work which processes them. Both ends only touch
+//! atomics and preallocated slots.
+
+use alloc::Vec;
+use alloc::arc::Arc;
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+struct Ring<T> {
+    slots: Box<[UnsafeCell<T>]>,
+    /// Number of values popped so far, written only by consumer
+    head: AtomicUsize,
+    /// Number of values pushed so far, written only by producer
+    tail: AtomicUsize,
+    /// Number of values dropped because the ring was full
+    overflows: AtomicUsize,
+}
+
+// Slot is written by producer only while it is free, and read by consumer only after it has
+// been published by `tail`, so they never access the same slot at the same time.
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Ring<T> {
+    fn len(&self) -> usize {
+        self.tail
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+}
+
+/// Creates lock-free ring buffer for single producer and single consumer, which holds up to
+/// `capacity` values. Slots are preallocated with copies of `fill`.
+///
+/// Neither pushing nor popping ever blocks or allocates, so either side may run in interrupt
+/// context. Values pushed while the ring is full are dropped and counted.
+///
+/// ## Examples
+///
+/// ```
+/// let (mut producer, mut consumer) = sync::spsc_ring(64, 0u8);
+///
+/// // Interrupt handler
+/// producer.push(byte);
+///
+/// // Deferred work
+/// while let Some(byte) = consumer.pop() {
+///     process(byte);
+/// }
+/// ```
+pub fn spsc_ring<T: Copy + Send>(capacity: usize, fill: T) -> (RingProducer<T>, RingConsumer<T>) {
+    assert!(capacity > 0, "ring buffer has to have capacity");
+
+    let slots: Vec<UnsafeCell<T>> = (0..capacity).map(|_| UnsafeCell::new(fill)).collect();
+    let ring = Arc::new(Ring {
+        slots: slots.into_boxed_slice(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        overflows: AtomicUsize::new(0),
+    });
+
+    (
+        RingProducer { ring: ring.clone() },
+        RingConsumer { ring },
+    )
+}
+
+/// Pushing end of a ring buffer created by [`spsc_ring`]
+///
+/// [`spsc_ring`]: ./fn.spsc_ring.html
+pub struct RingProducer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+impl<T: Copy> RingProducer<T> {
+    /// Appends value to the ring. Returns `false` if the ring is full, in which case
+    /// the value is dropped and counted as overflow.
+    pub fn push(&mut self, value: T) -> bool {
+        let ring = &*self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let head = ring.head.load(Ordering::Acquire);
+
+        if tail.wrapping_sub(head) == ring.slots.len() {
+            ring.overflows.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        unsafe {
+            *ring.slots[tail % ring.slots.len()].get() = value;
+        }
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// Returns number of values waiting in the ring.
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    /// Returns `true` if there are no values waiting in the ring.
+    pub fn is_empty(&self) -> bool {
+        self.ring.len() == 0
+    }
+
+    /// Returns number of values dropped because the ring was full.
+    pub fn overflows(&self) -> usize {
+        self.ring.overflows.load(Ordering::Relaxed)
+    }
+}
+
+/// Popping end of a ring buffer created by [`spsc_ring`]
+///
+/// [`spsc_ring`]: ./fn.spsc_ring.html
+pub struct RingConsumer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+impl<T: Copy> RingConsumer<T> {
+    /// Removes the oldest value from the ring, if there is any.
+    pub fn pop(&mut self) -> Option<T> {
+        let ring = &*self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        let tail = ring.tail.load(Ordering::Acquire);
+
+        if head == tail {
+            return None;
+        }
+
+        let value = unsafe { *ring.slots[head % ring.slots.len()].get() };
+        ring.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+
+    /// Returns number of values waiting in the ring.
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    /// Returns `true` if there are no values waiting in the ring.
+    pub fn is_empty(&self) -> bool {
+        self.ring.len() == 0
+    }
+
+    /// Returns number of values dropped because the ring was full.
+    pub fn overflows(&self) -> usize {
+        self.ring.overflows.load(Ordering::Relaxed)
+    }
+}
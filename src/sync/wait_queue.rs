@@ -0,0 +1,135 @@
+use alloc::VecDeque;
+use core::mem;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use executor::Waker;
+use thread::{self, Interrupted};
+
+use super::IrqSpinLock;
+
+/// Identifier of registration in a [`WaitQueue`]
+///
+/// [`WaitQueue`]: ./struct.WaitQueue.html
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct WaiterId(usize);
+
+/// Queue of threads and tasks waiting for some event.
+///
+/// Waiters are represented by [`Waker`]s, so both blocked threads and async tasks can wait
+/// in the same queue. Waking is allowed from interrupt handlers and deferred work.
+///
+/// ## Examples
+///
+/// ```
+/// // Waiting thread
+/// queue.wait_until(|| data_ready());
+///
+/// // Interrupt handler
+/// queue.wake_all();
+/// ```
+///
+/// [`Waker`]: ../executor/struct.Waker.html
+pub struct WaitQueue {
+    waiters: IrqSpinLock<VecDeque<(WaiterId, Waker)>>,
+    next_id: AtomicUsize,
+}
+
+impl WaitQueue {
+    /// Creates new, empty queue.
+    pub fn new() -> WaitQueue {
+        WaitQueue {
+            waiters: IrqSpinLock::new(VecDeque::new()),
+            next_id: AtomicUsize::new(0),
+        }
+    }
+
+    /// Adds `waker` at the end of the queue.
+    ///
+    /// Returned identifier should be used to [`unregister`] the waker if it is no longer
+    /// interested in being woken, otherwise it could swallow a [`wake_one`] meant for others.
+    ///
+    /// [`unregister`]: #method.unregister
+    /// [`wake_one`]: #method.wake_one
+    pub fn register(&self, waker: Waker) -> WaiterId {
+        let id = WaiterId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.waiters.lock().push_back((id, waker));
+        id
+    }
+
+    /// Removes waker from the queue, if it has not been woken yet.
+    pub fn unregister(&self, id: WaiterId) {
+        self.waiters.lock().retain(|&(waiter, _)| waiter != id);
+    }
+
+    /// Blocks current thread until `cond` returns `true`.
+    ///
+    /// The condition is checked before the thread is queued, and after each wakeup.
+    pub fn wait_until(&self, cond: impl FnMut() -> bool) {
+        match self.wait(cond, false) {
+            Ok(()) => {}
+            Err(Interrupted) => unreachable!(),
+        }
+    }
+
+    /// Blocks current thread until `cond` returns `true`, or the thread gets [interrupted].
+    ///
+    /// Interruption is checked together with the condition, so this function fails right away
+    /// if the thread has been interrupted before.
+    ///
+    /// [interrupted]: ../thread/fn.interrupt.html
+    pub fn wait_until_interruptible(&self, cond: impl FnMut() -> bool) -> Result<(), Interrupted> {
+        self.wait(cond, true)
+    }
+
+    fn wait(&self, mut cond: impl FnMut() -> bool, interruptible: bool) -> Result<(), Interrupted> {
+        loop {
+            if cond() {
+                return Ok(());
+            }
+            if interruptible && thread::is_interrupted() {
+                return Err(Interrupted);
+            }
+
+            let id = self.register(Waker::for_thread(thread::current()));
+
+            // Condition could have changed before we got queued
+            if cond() {
+                self.unregister(id);
+                return Ok(());
+            }
+
+            thread::block();
+            self.unregister(id);
+        }
+    }
+
+    /// Wakes the first waiter in the queue. Returns `false` if the queue was empty.
+    pub fn wake_one(&self) -> bool {
+        let waiter = self.waiters.lock().pop_front();
+        match waiter {
+            Some((_, waker)) => {
+                waker.wake();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Wakes all waiters in the queue, returns their number.
+    pub fn wake_all(&self) -> usize {
+        let waiters = mem::replace(&mut *self.waiters.lock(), VecDeque::new());
+        let count = waiters.len();
+
+        for (_, waker) in waiters {
+            waker.wake();
+        }
+
+        count
+    }
+}
+
+impl Default for WaitQueue {
+    fn default() -> WaitQueue {
+        WaitQueue::new()
+    }
+}
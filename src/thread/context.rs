@@ -0,0 +1,104 @@
+/// Saved execution context of a thread which is not running.
+///
+/// Callee-saved registers and flags are pushed on thread's own stack by [`switch`], so the only
+/// thing which has to be remembered is the stack pointer. Caller-saved registers are taken care
+/// of by the compiler, as [`switch`] is an ordinary function call.
+///
+/// [`switch`]: ./fn.switch.html
+#[derive(Debug)]
+pub struct Context {
+    rsp: usize,
+}
+
+/// Initial `RFLAGS` value of new threads: interrupts disabled, reserved bit 1 set.
+const INITIAL_RFLAGS: usize = 0x2;
+
+impl Context {
+    /// Context of already running thread, it gets filled on first switch from it.
+    pub const fn empty() -> Context {
+        Context { rsp: 0 }
+    }
+
+    /// Prepares context which, when switched to, starts executing `entry` on stack with
+    /// given top address. `entry` is started with interrupts disabled.
+    pub unsafe fn new(stack_top: usize, entry: extern "C" fn() -> !) -> Context {
+        let mut rsp = stack_top & !0xf;
+
+        // Fake return address of `entry`, keeps stack aligned as System V ABI requires
+        push(&mut rsp, 0);
+        // Return address of `thread_switch_stacks`
+        push(&mut rsp, entry as usize);
+        // rbp, rbx, r12, r13, r14, r15
+        for _ in 0..6 {
+            push(&mut rsp, 0);
+        }
+        push(&mut rsp, INITIAL_RFLAGS);
+
+        Context { rsp }
+    }
+
+    /// Returns stack pointer and address the thread resumes at, which is right after its
+    /// call to [`switch`] or its entry point, or `None` if the context has not been saved yet.
+    ///
+    /// **The thread must not be running, and its stack must still be allocated.**
+    ///
+    /// [`switch`]: ./fn.switch.html
+    pub unsafe fn saved_position(&self) -> Option<(usize, usize)> {
+        if self.rsp == 0 {
+            return None;
+        }
+
+        // Flags and six callee-saved registers are pushed after the return address
+        let rip = *((self.rsp + 7 * 8) as *const usize);
+        Some((self.rsp, rip))
+    }
+}
+
+#[inline]
+unsafe fn push(rsp: &mut usize, value: usize) {
+    *rsp -= 8;
+    *(*rsp as *mut usize) = value;
+}
+
+/// Saves current execution context in `old` and resumes execution from `new`.
+///
+/// This function returns when some other thread switches back to `old`.
+///
+/// **Interrupts are required to be disabled.**
+pub unsafe fn switch(old: *mut Context, new: *const Context) {
+    thread_switch_stacks(&mut (*old).rsp, (*new).rsp);
+}
+
+extern "C" {
+    fn thread_switch_stacks(old_rsp: *mut usize, new_rsp: usize);
+}
+
+// Pushes callee-saved registers and flags, stores stack pointer in `[rdi]`, loads new one from
+// `rsi` and pops everything back, this time from new thread's stack.
+global_asm!(
+    r#"
+.intel_syntax noprefix
+.global thread_switch_stacks
+thread_switch_stacks:
+    push rbp
+    push rbx
+    push r12
+    push r13
+    push r14
+    push r15
+    pushfq
+
+    mov [rdi], rsp
+    mov rsp, rsi
+
+    popfq
+    pop r15
+    pop r14
+    pop r13
+    pop r12
+    pop rbx
+    pop rbp
+    ret
+.att_syntax prefix
+"#
+);
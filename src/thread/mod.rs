@@ -0,0 +1,417 @@
+//! Kernel threads
+//!
+//! Threads are preemptively scheduled in round-robin fashion, the running thread is switched
+//! on system tick when its time slice is over, or when it blocks or yields on its own.
+//!
+//! ## Examples
+//!
+//! ```
+//! let handle = thread::spawn(|| {
+//!     for i in 0..3 {
+//!         println!("hello from thread {}", i);
+//!         thread::sleep(1000);
+//!     }
+//! });
+//!
+//! handle.join();
+//! ```
+
+mod context;
+mod scheduler;
+
+use alloc::{String, Vec};
+use alloc::arc::Arc;
+use alloc::boxed::{Box, FnBox};
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use kio::defer;
+use kio::idt::{self, without_interrupts};
+use kio::time::{Instant, TICK_HZ};
+use kio::timer::Timer;
+use mem;
+use mem::AddressSpace;
+use mem::stack::Stack;
+use sync::IrqSpinLock;
+
+use self::context::Context;
+
+/// Size of kernel thread stack
+const STACK_PAGES: usize = 4;
+
+/// Information about a thread, for diagnostic purposes
+#[derive(Debug, Clone)]
+pub struct ThreadInfo {
+    pub id: ThreadId,
+    pub name: String,
+    pub state: State,
+    /// Number of milliseconds this thread has been running
+    pub cpu_time: u64,
+    /// Where the thread resumes, if it is ready or blocked
+    pub saved_context: Option<SavedContext>,
+}
+
+/// Stack pointer and instruction pointer of a thread which is not running
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SavedContext {
+    pub rsp: usize,
+    pub rip: usize,
+}
+
+/// Unique identifier of a thread
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct ThreadId(usize);
+
+impl fmt::Display for ThreadId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Scheduling state of a thread
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum State {
+    /// Thread is waiting in ready queue
+    Ready,
+    /// Thread is currently executing
+    Running,
+    /// Thread is waiting for some event and will not be scheduled until woken up
+    Blocked,
+    /// Thread has exited
+    Dead,
+}
+
+struct Thread {
+    id: ThreadId,
+    name: String,
+    state: State,
+    context: Context,
+    /// `None` for boot thread, which runs on boot stack
+    stack: Option<Stack>,
+    /// Code to run, taken on thread start
+    entry: Option<Box<FnBox() + Send>>,
+    /// Set if thread has been woken up before it managed to block
+    wakeup_pending: bool,
+    /// Set by `interrupt`, makes interruptible waits fail
+    interrupted: bool,
+    /// Number of system ticks this thread has been running
+    cpu_ticks: u64,
+    /// `None` for kernel threads, which run in kernel's address space
+    address_space: Option<Arc<AddressSpace>>,
+    packet: Arc<Packet>,
+}
+
+/// State shared between thread and its join handle
+struct Packet {
+    finished: AtomicBool,
+    joiners: IrqSpinLock<Vec<ThreadId>>,
+}
+
+impl Packet {
+    fn new() -> Packet {
+        Packet {
+            finished: AtomicBool::new(false),
+            joiners: IrqSpinLock::new(Vec::new()),
+        }
+    }
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl Thread {
+    fn new(name: String, context: Context, stack: Option<Stack>) -> Thread {
+        Thread {
+            id: ThreadId(NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+            name,
+            state: State::Ready,
+            context,
+            stack,
+            entry: None,
+            wakeup_pending: false,
+            interrupted: false,
+            cpu_ticks: 0,
+            address_space: None,
+            packet: Arc::new(Packet::new()),
+        }
+    }
+
+    fn info(&self) -> ThreadInfo {
+        ThreadInfo {
+            id: self.id,
+            name: self.name.clone(),
+            state: self.state,
+            cpu_time: self.cpu_ticks * 1000 / TICK_HZ,
+            saved_context: match self.state {
+                // Stack of running thread changes all the time, dead one may be gone
+                State::Ready | State::Blocked => unsafe { self.context.saved_position() }
+                    .map(|(rsp, rip)| SavedContext { rsp, rip }),
+                State::Running | State::Dead => None,
+            },
+        }
+    }
+}
+
+/// Error returned by interruptible waits when the waiting thread has been [interrupted].
+///
+/// [interrupted]: ./fn.interrupt.html
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "interrupted")
+    }
+}
+
+/// Handle allowing to wait for thread to exit
+pub struct JoinHandle {
+    id: ThreadId,
+    packet: Arc<Packet>,
+}
+
+impl JoinHandle {
+    /// Returns identifier of the thread.
+    pub fn id(&self) -> ThreadId {
+        self.id
+    }
+
+    /// Blocks current thread until the thread exits.
+    pub fn join(self) {
+        loop {
+            if self.packet.finished.load(Ordering::Acquire) {
+                return;
+            }
+
+            self.packet.joiners.lock().push(current());
+
+            // Thread could have exited before we registered ourselves
+            if self.packet.finished.load(Ordering::Acquire) {
+                return;
+            }
+
+            block();
+        }
+    }
+}
+
+/// Initializes threading, making the currently running code the `boot` thread.
+///
+/// **Memory and KIO subsystems are required to be initialized.**
+///
+/// **This function should be called only once.**
+pub fn init() {
+    let mut boot = Thread::new("boot".into(), Context::empty(), None);
+    boot.state = State::Running;
+    scheduler::init(boot);
+}
+
+/// Spawns new kernel thread running `f`.
+///
+/// ## Panics
+///
+/// Panics if there is no memory for thread stack.
+pub fn spawn<F>(f: F) -> JoinHandle
+where
+    F: FnOnce() + Send + 'static,
+{
+    spawn_named("kthread", f)
+}
+
+/// Spawns new kernel thread running `f`, with given name.
+///
+/// ## Panics
+///
+/// Panics if there is no memory for thread stack.
+pub fn spawn_named<F>(name: &str, f: F) -> JoinHandle
+where
+    F: FnOnce() + Send + 'static,
+{
+    spawn_with(name, None, f)
+}
+
+/// Spawns new thread running `f` in given address space, e.g. to load and run user program.
+///
+/// ## Panics
+///
+/// Panics if there is no memory for thread stack.
+pub fn spawn_in<F>(name: &str, address_space: Arc<AddressSpace>, f: F) -> JoinHandle
+where
+    F: FnOnce() + Send + 'static,
+{
+    spawn_with(name, Some(address_space), f)
+}
+
+fn spawn_with<F>(name: &str, address_space: Option<Arc<AddressSpace>>, f: F) -> JoinHandle
+where
+    F: FnOnce() + Send + 'static,
+{
+    let stack = mem::alloc_stack(STACK_PAGES).expect("out of memory for thread stack");
+    let context = unsafe { Context::new(stack.top, thread_start) };
+
+    let entry: Box<FnBox() + Send> = box f;
+    let mut thread = Thread::new(name.into(), context, Some(stack));
+    thread.entry = Some(entry);
+    thread.address_space = address_space;
+
+    let handle = JoinHandle {
+        id: thread.id,
+        packet: Arc::clone(&thread.packet),
+    };
+
+    scheduler::with(|s| s.add(thread));
+
+    handle
+}
+
+/// Returns information about all existing threads.
+pub fn list() -> Vec<ThreadInfo> {
+    scheduler::with(|s| s.threads().map(|t| t.info()).collect())
+}
+
+/// Returns identifier of currently running thread.
+pub fn current() -> ThreadId {
+    scheduler::with(|s| s.current())
+}
+
+/// Returns identifier of currently running thread, or `None` if threading is not initialized.
+pub fn try_current() -> Option<ThreadId> {
+    if scheduler::is_initialized() {
+        Some(current())
+    } else {
+        None
+    }
+}
+
+/// Gives up the rest of current time slice, letting other threads run.
+///
+/// Returns `false` if there were no other threads ready to run, or threading is not initialized.
+pub fn yield_now() -> bool {
+    if !scheduler::is_initialized() {
+        return false;
+    }
+
+    without_interrupts(|| scheduler::switch_current(State::Ready))
+}
+
+/// Blocks current thread until somebody [wakes] it up.
+///
+/// If the thread has been woken up after last blocking, this function returns immediately.
+/// Spurious wakeups are possible, so callers should check the condition they are waiting for
+/// in a loop.
+///
+/// ## Panics
+///
+/// Panics if called from [deferred work], which runs on behalf of interrupt handlers and
+/// cannot block. Other threads may block while deferred work is preempted.
+///
+/// [wakes]: ./fn.wake.html
+/// [deferred work]: ../kio/defer/index.html
+pub fn block() {
+    assert!(
+        !defer::is_running(),
+        "deferred work tried to block, it should hand blocking operations over to a thread"
+    );
+
+    without_interrupts(|| {
+        scheduler::switch_current(State::Blocked);
+    });
+}
+
+/// Wakes up blocked thread.
+///
+/// This function can be called from interrupt handlers.
+pub fn wake(id: ThreadId) {
+    scheduler::with(|s| s.wake(id));
+}
+
+/// Interrupts thread, so that its current and all future interruptible waits fail with
+/// [`Interrupted`], and wakes it up. Interruption cannot be undone, it is meant to make
+/// the thread finish.
+///
+/// This function can be called from interrupt handlers.
+///
+/// [`Interrupted`]: ./struct.Interrupted.html
+pub fn interrupt(id: ThreadId) {
+    scheduler::with(|s| {
+        if let Some(thread) = s.thread_mut(id) {
+            thread.interrupted = true;
+        }
+        s.wake(id);
+    });
+}
+
+/// Returns `true` if current thread has been [interrupted].
+///
+/// [interrupted]: ./fn.interrupt.html
+pub fn is_interrupted() -> bool {
+    scheduler::with(|s| s.current_thread_mut().interrupted)
+}
+
+/// Puts current thread to sleep for at least `millis` milliseconds.
+pub fn sleep(millis: u64) {
+    let deadline = Instant::now() + millis;
+    while Instant::now() < deadline {
+        sleep_until(deadline);
+    }
+}
+
+/// Puts current thread to sleep for at least `millis` milliseconds, unless it gets
+/// [interrupted] first.
+///
+/// [interrupted]: ./fn.interrupt.html
+pub fn sleep_interruptible(millis: u64) -> Result<(), Interrupted> {
+    let deadline = Instant::now() + millis;
+    loop {
+        if is_interrupted() {
+            return Err(Interrupted);
+        }
+        if Instant::now() >= deadline {
+            return Ok(());
+        }
+        sleep_until(deadline);
+    }
+}
+
+/// Blocks current thread until `deadline`, or until it is woken up earlier.
+fn sleep_until(deadline: Instant) {
+    let id = current();
+    let timer = Timer::schedule(deadline, move || wake(id));
+    block();
+    timer.cancel();
+}
+
+/// Terminates current thread.
+pub fn exit() -> ! {
+    unsafe { idt::disable() };
+
+    let packet = scheduler::with(|s| Arc::clone(&s.current_thread_mut().packet));
+    packet.finished.store(true, Ordering::Release);
+    for joiner in packet.joiners.lock().drain(..) {
+        wake(joiner);
+    }
+    drop(packet);
+
+    scheduler::switch_current(State::Dead);
+    unreachable!("dead thread has been scheduled");
+}
+
+/// Accounts system tick to current thread and preempts it if needed.
+///
+/// **This function is called by the PIT interrupt handler, after interrupt acknowledgement.**
+pub(crate) fn on_tick() {
+    scheduler::on_tick();
+}
+
+/// Entry point of all spawned threads.
+extern "C" fn thread_start() -> ! {
+    scheduler::after_switch();
+
+    let entry = scheduler::with(|s| s.current_thread_mut().entry.take())
+        .expect("thread started twice");
+
+    unsafe { idt::enable() };
+
+    entry();
+
+    exit();
+}
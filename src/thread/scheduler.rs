@@ -0,0 +1,261 @@
+//! Round-robin scheduler
+//!
+//! Runnable threads wait in FIFO *ready queue*. Running thread is preempted when it uses up its
+//! time slice, and goes to the back of the queue. Blocked threads are kept outside of the queue
+//! until somebody wakes them up.
+
+use alloc::{BTreeMap, VecDeque, Vec};
+use alloc::boxed::Box;
+use alloc::btree_map::Values;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use kio::defer;
+use mem;
+use mem::paging::PhysicalAddress;
+use proc;
+use sync::IrqSpinLock;
+
+use super::{State, Thread, ThreadId};
+use super::context::{self, Context};
+
+/// Number of ticks thread can run before it gets preempted.
+const TIME_SLICE: u64 = 10;
+
+pub(super) struct Scheduler {
+    threads: BTreeMap<ThreadId, Box<Thread>>,
+    ready: VecDeque<ThreadId>,
+    current: ThreadId,
+    /// Threads which have exited. They are reaped by deferred work, as their stacks may be
+    /// still in use when they are added here.
+    dead: Vec<ThreadId>,
+    slice_left: u64,
+    /// Page table used by kernel threads
+    kernel_p4: PhysicalAddress,
+}
+
+static SCHEDULER: IrqSpinLock<Option<Scheduler>> = IrqSpinLock::new(None);
+
+/// Set if reaping of dead threads has been scheduled and has not run yet
+static REAP_SCHEDULED: AtomicBool = AtomicBool::new(false);
+
+/// Sets up scheduler, registering currently executing code as `boot` thread.
+pub(super) fn init(boot_thread: Thread) {
+    let id = boot_thread.id;
+
+    let mut threads = BTreeMap::new();
+    threads.insert(id, box boot_thread);
+
+    *SCHEDULER.lock() = Some(Scheduler {
+        threads,
+        ready: VecDeque::new(),
+        current: id,
+        dead: Vec::new(),
+        slice_left: TIME_SLICE,
+        kernel_p4: mem::current_p4(),
+    });
+}
+
+pub(super) fn is_initialized() -> bool {
+    SCHEDULER.lock().is_some()
+}
+
+/// Runs `f` on locked scheduler.
+///
+/// ## Panics
+///
+/// Panics if scheduler is not initialized.
+pub(super) fn with<R>(f: impl FnOnce(&mut Scheduler) -> R) -> R {
+    let mut guard = SCHEDULER.lock();
+    f(guard.as_mut().expect("threading is not initialized"))
+}
+
+/// Switches from current thread to next ready one, putting current thread in `state`.
+///
+/// If current thread is going to be blocked, but it has been woken up in the meantime,
+/// this function returns immediately. Same happens if current thread would stay ready and
+/// there are no other ready threads.
+///
+/// Returns `true` if other thread has been running.
+///
+/// **Interrupts are required to be disabled.**
+pub(super) fn switch_current(state: State) -> bool {
+    let contexts = with(|s| s.pick_next(state));
+
+    match contexts {
+        Some((old, new)) => {
+            unsafe { context::switch(old, new) };
+            after_switch();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Finishes switching to a thread, must be called right after each context switch,
+/// in thread which has been switched to.
+///
+/// Switch may happen in interrupt handler, so resources of dead threads are not freed here.
+/// Dropping the last thread of a process tears down its whole address space, which is left
+/// to deferred work.
+pub(super) fn after_switch() {
+    let has_dead = with(|s| !s.dead.is_empty());
+    if has_dead && !REAP_SCHEDULED.swap(true, Ordering::Acquire) && !defer::schedule(reap, 0) {
+        // Queue is full, retry on next switch
+        REAP_SCHEDULED.store(false, Ordering::Release);
+    }
+}
+
+/// Frees resources of dead threads. Runs as deferred work, on a thread which is not dead, so
+/// none of dead threads' stacks is in use anymore.
+fn reap(_: usize) {
+    REAP_SCHEDULED.store(false, Ordering::Release);
+
+    let dead: Vec<Box<Thread>> = with(|s| {
+        let ids: Vec<ThreadId> = s.dead.drain(..).collect();
+        ids.iter().filter_map(|id| s.threads.remove(id)).collect()
+    });
+
+    // Free resources without scheduler lock held
+    for thread in dead {
+        if let Some(stack) = thread.stack {
+            mem::dealloc_stack(stack);
+        }
+    }
+}
+
+/// Accounts system tick to current thread, and preempts it if its time slice is over.
+///
+/// **Must be called with interrupts disabled, after the interrupt has been acknowledged.**
+pub(super) fn on_tick() {
+    let preempt = {
+        let mut guard = SCHEDULER.lock();
+        match guard.as_mut() {
+            Some(s) => s.tick(),
+            None => false,
+        }
+    };
+
+    if preempt {
+        switch_current(State::Ready);
+    }
+}
+
+impl Scheduler {
+    pub(super) fn current(&self) -> ThreadId {
+        self.current
+    }
+
+    pub(super) fn current_thread_mut(&mut self) -> &mut Thread {
+        let id = self.current;
+        self.thread_mut(id).unwrap()
+    }
+
+    pub(super) fn thread_mut(&mut self, id: ThreadId) -> Option<&mut Thread> {
+        self.threads.get_mut(&id).map(|t| &mut **t)
+    }
+
+    pub(super) fn threads(&self) -> Values<ThreadId, Box<Thread>> {
+        self.threads.values()
+    }
+
+    /// Adds new thread to scheduler, and puts it in the ready queue.
+    pub(super) fn add(&mut self, thread: Thread) {
+        let id = thread.id;
+        let r = self.threads.insert(id, box thread);
+        assert!(r.is_none());
+        self.ready.push_back(id);
+    }
+
+    /// Makes blocked thread ready. If the thread is not blocked yet, it will not block
+    /// on its next attempt.
+    pub(super) fn wake(&mut self, id: ThreadId) {
+        let make_ready = match self.threads.get_mut(&id) {
+            Some(thread) => match thread.state {
+                State::Blocked => {
+                    thread.state = State::Ready;
+                    true
+                }
+                State::Ready | State::Running => {
+                    thread.wakeup_pending = true;
+                    false
+                }
+                State::Dead => false,
+            },
+            None => false,
+        };
+
+        if make_ready {
+            self.ready.push_back(id);
+        }
+    }
+
+    fn tick(&mut self) -> bool {
+        self.current_thread_mut().cpu_ticks += 1;
+
+        if self.slice_left > 0 {
+            self.slice_left -= 1;
+        }
+
+        self.slice_left == 0 && !self.ready.is_empty()
+    }
+
+    /// Chooses next thread to run, and updates states of both current and next thread.
+    ///
+    /// Returns pointers to contexts of current and next thread, or `None` if current thread
+    /// should continue running.
+    fn pick_next(&mut self, state: State) -> Option<(*mut Context, *const Context)> {
+        let current_id = self.current;
+
+        {
+            let current = self.current_thread_mut();
+            if state == State::Blocked && current.wakeup_pending {
+                current.wakeup_pending = false;
+                return None;
+            }
+        }
+
+        let next_id = match self.ready.pop_front() {
+            Some(id) => id,
+            None if state == State::Ready => {
+                self.slice_left = TIME_SLICE;
+                return None;
+            }
+            None => panic!("thread {} cannot be blocked, no other thread can run", current_id),
+        };
+
+        match state {
+            State::Ready => self.ready.push_back(current_id),
+            State::Dead => self.dead.push(current_id),
+            State::Blocked => {}
+            State::Running => unreachable!(),
+        }
+
+        self.current = next_id;
+        self.slice_left = TIME_SLICE;
+
+        let old = {
+            let current = self.thread_mut(current_id).unwrap();
+            current.state = state;
+            &mut current.context as *mut Context
+        };
+
+        let kernel_p4 = self.kernel_p4;
+        let new = {
+            let next = self.thread_mut(next_id).unwrap();
+            next.state = State::Running;
+            if let Some(ref stack) = next.stack {
+                // Entries from user mode will use thread's own kernel stack
+                unsafe { proc::set_kernel_stack(stack.top) };
+            }
+            let p4 = match next.address_space {
+                Some(ref space) => space.p4_address(),
+                None => kernel_p4,
+            };
+            // Kernel part of all address spaces is the same, so it is safe to switch here
+            unsafe { mem::switch_p4(p4) };
+            &next.context as *const Context
+        };
+
+        Some((old, new))
+    }
+}